@@ -0,0 +1,74 @@
+// Copying text to the system clipboard.
+//
+// There is no clipboard crate available, so this drives the platform's
+// clipboard tools and, when enabled, falls back to the OSC 52 terminal escape
+// which also works through SSH sessions.
+
+use std::io::{self, IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+use crate::base64;
+use crate::debug_print;
+
+// Tried in order; the first one that succeeds wins
+const CLIPBOARD_TOOLS: &[(&str, &[&str])] = &[
+    ("pbcopy", &[]),
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("clip.exe", &[]),
+];
+
+// Copy `text`, returning the name of the mechanism that worked, or the names
+// of every mechanism tried if none did
+pub fn copy(text: &str, allow_osc52: bool, verbose: bool) -> Result<&'static str, Vec<&'static str>> {
+    let mut tried = Vec::new();
+
+    for (tool, args) in CLIPBOARD_TOOLS {
+        tried.push(*tool);
+        match run_tool(tool, args, text) {
+            Ok(true) => {
+                debug_print(verbose, &format!("Copied using {}", tool));
+                return Ok(tool);
+            }
+            Ok(false) => debug_print(verbose, &format!("{} exited with an error", tool)),
+            Err(e) => debug_print(verbose, &format!("{} not usable: {}", tool, e)),
+        }
+    }
+
+    if allow_osc52 {
+        tried.push("OSC 52");
+        match copy_osc52(text) {
+            Ok(()) => {
+                debug_print(verbose, "Copied using OSC 52 escape sequence");
+                return Ok("OSC 52");
+            }
+            Err(e) => debug_print(verbose, &format!("OSC 52 not usable: {}", e)),
+        }
+    }
+
+    Err(tried)
+}
+
+fn run_tool(tool: &str, args: &[&str], text: &str) -> io::Result<bool> {
+    let mut child = Command::new(tool)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes())?;
+    }
+    Ok(child.wait()?.success())
+}
+
+// The terminal emulator sets the clipboard itself, so this needs a terminal
+fn copy_osc52(text: &str) -> io::Result<()> {
+    let mut stderr = io::stderr();
+    if !stderr.is_terminal() {
+        return Err(io::Error::other("stderr is not a terminal"));
+    }
+    write!(stderr, "\x1b]52;c;{}\x07", base64::encode(text.as_bytes()))?;
+    stderr.flush()
+}
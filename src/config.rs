@@ -0,0 +1,145 @@
+// Defaults from the config file, $XDG_CONFIG_HOME/changedir/config.toml.
+//
+// Only the part of TOML the file needs is understood: `key = value` lines
+// with integers, booleans and strings, `[table]` headers, and comments. A key
+// inside a table is known by its dotted name (`[ui]` + `language` is
+// `ui.language`). Unknown keys and tables are ignored, so a file written for
+// a newer version still loads.
+
+use crate::messages::Language;
+
+#[derive(Default)]
+pub struct Config {
+    pub history_max: Option<usize>,
+    pub color: Option<bool>,
+    pub canonicalize: Option<bool>,
+    pub portable: Option<bool>,
+    pub relative_target: Option<bool>,
+    pub osc52: Option<bool>,
+    pub search_depth: Option<usize>,
+    pub up_depth: Option<usize>,
+    pub default_slot: Option<char>,
+    pub language: Option<Language>,
+}
+
+enum Value {
+    Integer(i64),
+    Bool(bool),
+    String(String),
+}
+
+impl Config {
+    // Read the settings in `text`. Lines that can't be used are skipped and
+    // described in the returned list, so one typo doesn't lose the rest.
+    pub fn parse(text: &str) -> (Config, Vec<String>) {
+        let mut config = Config::default();
+        let mut problems = Vec::new();
+        let mut table: Option<String> = None;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[') {
+                table = Some(name.trim_end_matches(']').trim().to_string());
+                continue;
+            }
+            let problem = |message: &str| format!("line {}: {}", number + 1, message);
+            let Some((key, value)) = line.split_once('=') else {
+                problems.push(problem("expected key = value"));
+                continue;
+            };
+            let key = key.trim().trim_matches('"');
+            let key = match &table {
+                Some(table) => format!("{}.{}", table, key),
+                None => key.to_string(),
+            };
+            let key = key.as_str();
+            let Some(value) = parse_value(value.trim()) else {
+                problems.push(problem(&format!("invalid value for {}", key)));
+                continue;
+            };
+            match (key, value) {
+                ("history_max", Value::Integer(n)) if n >= 0 => config.history_max = Some(n as usize),
+                ("search_depth", Value::Integer(n)) if n > 0 => config.search_depth = Some(n as usize),
+                ("up_depth", Value::Integer(n)) if n >= 0 => config.up_depth = Some(n as usize),
+                ("color", Value::Bool(b)) => config.color = Some(b),
+                ("canonicalize", Value::Bool(b)) => config.canonicalize = Some(b),
+                ("portable", Value::Bool(b)) => config.portable = Some(b),
+                ("relative_target", Value::Bool(b)) => config.relative_target = Some(b),
+                ("osc52", Value::Bool(b)) => config.osc52 = Some(b),
+                ("default_slot", Value::String(s)) if s.chars().count() == 1 => config.default_slot = s.chars().next(),
+                ("ui.language", Value::String(s)) if Language::from_code(&s).is_some() => config.language = Language::from_code(&s),
+                ("history_max" | "search_depth" | "up_depth" | "color" | "canonicalize" | "portable" | "relative_target" | "osc52" | "default_slot" | "ui.language", _) => {
+                    problems.push(problem(&format!("invalid value for {}", key)))
+                }
+                _ => {}
+            }
+        }
+        (config, problems)
+    }
+}
+
+// `line` without a trailing `# comment`, leaving a `#` inside quotes alone
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            '#' if !quoted => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    match text {
+        "true" => Some(Value::Bool(true)),
+        "false" => Some(Value::Bool(false)),
+        _ if text.len() >= 2 && (text.starts_with('"') && text.ends_with('"') || text.starts_with('\'') && text.ends_with('\'')) => {
+            Some(Value::String(text[1..text.len() - 1].to_string()))
+        }
+        _ => text.replace('_', "").parse().ok().map(Value::Integer),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_in_tables_use_their_dotted_name() {
+        let (config, problems) = Config::parse("color = false\n[ui]\nlanguage = \"fr\"\n[other]\ncolor = true\n");
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(config.language, Some(Language::Fr));
+        assert_eq!(config.color, Some(false));
+    }
+
+    #[test]
+    fn relative_target_is_a_boolean() {
+        let (config, problems) = Config::parse("relative_target = true\n");
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(config.relative_target, Some(true));
+        let (config, problems) = Config::parse("relative_target = 1\n");
+        assert_eq!(config.relative_target, None);
+        assert_eq!(problems, vec!["line 1: invalid value for relative_target".to_string()]);
+    }
+
+    #[test]
+    fn osc52_is_a_boolean() {
+        let (config, problems) = Config::parse("osc52 = true\n");
+        assert!(problems.is_empty(), "{:?}", problems);
+        assert_eq!(config.osc52, Some(true));
+        let (_, problems) = Config::parse("osc52 = \"yes\"\n");
+        assert_eq!(problems, vec!["line 1: invalid value for osc52".to_string()]);
+    }
+
+    #[test]
+    fn unknown_language_is_reported() {
+        let (config, problems) = Config::parse("ui.language = \"tlh\"\n");
+        assert_eq!(config.language, None);
+        assert_eq!(problems, vec!["line 1: invalid value for ui.language".to_string()]);
+    }
+}
@@ -1,13 +1,124 @@
+mod base64;
+mod clipboard;
+mod config;
+mod json;
+mod messages;
+mod opener;
+mod shell_history;
+mod shell_init;
+
 use clap::{Arg, Command};
+use messages::{tr, Msg};
 use colored::*;
 use std::fs;
-use std::io::{self, Write};
-use std::path::PathBuf;
+use std::io::{self, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::OnceLock;
 
-const MAX_BOOKMARKS: usize = 36;
+// Entries are selected by a single character: 0-9, a-z, then A-Z
+const SLOT_COUNT: usize = 62;
+const DEFAULT_MAX_BOOKMARKS: usize = 36;
+const DEFAULT_HISTORY_MAX: usize = 10;
+// The store lives in the XDG config directory, the visit log, navigation
+// stacks and target file in the XDG data directory
+const STORE_FILE: &str = "changedir/store.json";
+const VISITS_FILE: &str = "changedir/visits";
+const SESSIONS_DIR: &str = "changedir/sessions";
+const TARGET_FILE: &str = "changedir/target";
+// Where the store and visit log were kept before, relative to the home
+// directory. They are copied to the new locations once.
+const OLD_STORE_FILE: &str = ".local/share/changedir/store.json";
+const OLD_VISITS_FILE: &str = ".local/changeDirectoryVisits";
+// Wrappers pasted before the target file moved read it from here and set
+// CHANGEDIR_WRAPPER=1; the current ones set 2 and ask --print-target-path
+const OLD_TARGET_FILE: &str = ".local/share/changedir.target";
+// Bookmarks and history were kept in these plain-text files before the store
 const BOOKMARK_FILE: &str = ".local/changeDirectory";
 const HISTORY_FILE: &str = ".local/changeDirectoryHistory";
-const TARGET_FILE: &str = ".local/share/changedir.target";
+const ERROR_SUFFIX: &str = ".error";
+const DEFAULT_PAGER: &str = "less -RFX";
+
+// Exit codes, so a wrapper or script can tell failures apart; anything else
+// that goes wrong exits with 1
+// Nothing to choose from: no bookmarks, no history, nothing to go forward to
+const EXIT_EMPTY: i32 = 2;
+// A letter, label or step count that doesn't refer to an entry
+const EXIT_INVALID_SELECTION: i32 = 3;
+// The chosen entry's directory no longer exists
+const EXIT_DIRECTORY_MISSING: i32 = 4;
+// No directory matches the name, pattern or project marker
+const EXIT_NOT_FOUND: i32 = 5;
+// The user cancelled (Ctrl-C, Esc) or the process was terminated
+const EXIT_CANCELLED: i32 = 130;
+
+// Number of state writes currently in flight; signals wait for this to reach zero
+static CRITICAL_SECTIONS: AtomicUsize = AtomicUsize::new(0);
+// Set while a pager runs: Ctrl-C typed in it reaches us too, and is the
+// pager's to handle
+static PAGER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+// How a selected directory is handed back, decided once from the command line
+#[derive(Default, Clone, Copy, PartialEq, Eq)]
+enum Delivery {
+    // Write the target file for the shell wrapper
+    #[default]
+    TargetFile,
+    // Print the path on stdout
+    Stdout,
+    // Print a `cd` command for the shell to eval
+    Eval,
+    // Put the path on the clipboard
+    Copy,
+    // Show the directory in the file manager
+    Open,
+}
+
+#[derive(Default)]
+struct SelectionOptions {
+    delivery: Delivery,
+    // Order the history section by frecency instead of recency
+    sort_frecency: bool,
+    // Order the bookmarks are shown (and lettered) in, if not as stored
+    bookmark_order: Option<BookmarkOrder>,
+    no_history: bool,
+    // Never shorten emitted paths to a relative form
+    absolute: bool,
+    // Only list bookmarks carrying all of these tags (--tag without --bookmark)
+    tag_filter: Vec<String>,
+    // Write the target file with forward slashes (--target-format unix)
+    unix_target: bool,
+    // Slot picked by an empty line at the -c prompt (--default; [0] if None)
+    default_slot: Option<char>,
+    // Store paths as given rather than canonicalized (--no-canonicalize)
+    literal_paths: bool,
+    // Write paths below the home directory as ~/... in the store (--portable)
+    portable: bool,
+    // Choose with fzf: --fzf, --no-fzf, or None to use it when installed
+    fzf: Option<bool>,
+    // Show the subdirectories of the highlighted menu entry (--preview)
+    preview: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum BookmarkOrder {
+    // By the final path component, ignoring case
+    Name,
+    Path,
+    // Most recently visited first, never-visited ones last
+    Recent,
+}
+
+static SELECTION_OPTIONS: OnceLock<SelectionOptions> = OnceLock::new();
+static CONFIG: OnceLock<config::Config> = OnceLock::new();
+
+fn selection_options() -> &'static SelectionOptions {
+    SELECTION_OPTIONS.get_or_init(SelectionOptions::default)
+}
+
+fn settings() -> &'static config::Config {
+    CONFIG.get_or_init(config::Config::default)
+}
 
 fn debug_print(verbose: bool, message: &str) {
     if verbose {
@@ -15,735 +126,4638 @@ fn debug_print(verbose: bool, message: &str) {
     }
 }
 
-fn get_bookmark_path() -> PathBuf {
-    dirs::home_dir()
-        .expect("Could not find home directory")
-        .join(BOOKMARK_FILE)
-}
+// Named profiles keep their state under PROFILES_DIR/<name>/ in the XDG data
+// directory; the default profile (no name) uses STORE_FILE and VISITS_FILE
+const PROFILES_DIR: &str = "changedir/profiles";
+// Where they were kept before, relative to the home directory
+const OLD_PROFILES_DIR: &str = ".local/share/changedir/profiles";
+const DEFAULT_PROFILE: &str = "default";
 
-fn get_history_path() -> PathBuf {
-    dirs::home_dir()
-        .expect("Could not find home directory")
-        .join(HISTORY_FILE)
-}
+static PROFILE: OnceLock<Option<String>> = OnceLock::new();
+static HISTORY_MAX: OnceLock<usize> = OnceLock::new();
+static MAX_BOOKMARKS: OnceLock<usize> = OnceLock::new();
 
-fn get_target_path() -> PathBuf {
-    dirs::home_dir()
-        .expect("Could not find home directory")
-        .join(TARGET_FILE)
+#[derive(Clone, Copy)]
+enum StateFile {
+    Store,
+    Visits,
+    // Legacy files, only read to migrate them into the store
+    Bookmarks,
+    History,
 }
 
-fn delete_target_file(verbose: bool) -> io::Result<()> {
-    let path = get_target_path();
-    if path.exists() {
-        debug_print(verbose, &format!("Deleting existing target file: {}", path.display()));
-        fs::remove_file(&path)?;
-    }
-    Ok(())
+const STATE_FILES: [StateFile; 4] = [StateFile::Store, StateFile::Visits, StateFile::Bookmarks, StateFile::History];
+
+fn active_profile() -> Option<&'static str> {
+    PROFILE.get_or_init(|| None).as_deref()
 }
 
-fn write_target_file(path: &PathBuf, verbose: bool) -> io::Result<()> {
-    let target_path = get_target_path();
-    debug_print(verbose, &format!("Writing target directory to: {}", target_path.display()));
-    
-    // Create parent directory if it doesn't exist
-    if let Some(parent) = target_path.parent() {
-        fs::create_dir_all(parent)?;
-    }
-    
-    fs::write(&target_path, path.to_string_lossy().as_bytes())?;
-    debug_print(verbose, "Target file written successfully");
-    Ok(())
+// 0 means history recording is disabled
+fn history_max() -> usize {
+    *HISTORY_MAX.get_or_init(|| DEFAULT_HISTORY_MAX)
 }
 
-fn load_bookmarks(verbose: bool) -> Vec<PathBuf> {
-    let path = get_bookmark_path();
-    debug_print(verbose, &format!("Loading bookmarks from: {}", path.display()));
-    
-    if !path.exists() {
-        debug_print(verbose, "Bookmark file does not exist");
-        return Vec::new();
-    }
+fn max_bookmarks() -> usize {
+    *MAX_BOOKMARKS.get_or_init(|| DEFAULT_MAX_BOOKMARKS)
+}
 
-    let bookmarks: Vec<PathBuf> = fs::read_to_string(&path)
-        .unwrap_or_default()
-        .lines()
-        .filter_map(|line| {
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(PathBuf::from(trimmed))
+// A count from the environment variable `name`, clamped to `min` and to what
+// fits in the letter range
+fn limit_from_env(name: &str, default: usize, min: usize, verbose: bool) -> usize {
+    let Ok(value) = std::env::var(name) else {
+        return default;
+    };
+    match value.trim().parse::<usize>() {
+        Ok(n) => {
+            let clamped = n.clamp(min, SLOT_COUNT);
+            if clamped != n {
+                debug_print(verbose, &format!("{}={} clamped to {}", name, n, clamped));
             }
-        })
-        .collect();
-    
-    debug_print(verbose, &format!("Loaded {} bookmarks", bookmarks.len()));
-    bookmarks
+            clamped
+        }
+        Err(_) => {
+            eprintln!("{}", tr(Msg::InvalidLimit, &[&name, &value, &default]).yellow());
+            default
+        }
+    }
 }
 
-fn save_bookmarks(bookmarks: &[PathBuf], verbose: bool) -> io::Result<()> {
-    let path = get_bookmark_path();
-    debug_print(verbose, &format!("Saving {} bookmarks to: {}", bookmarks.len(), path.display()));
-    
-    if let Some(parent) = path.parent() {
-        debug_print(verbose, &format!("Creating parent directory: {}", parent.display()));
-        fs::create_dir_all(parent)?;
-    }
+// The profile comes from --profile or CHANGEDIR_PROFILE. It is read straight
+// from the arguments because the stale target file must be removed before
+// clap gets a chance to exit.
+fn early_profile() -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    let from_args = args.iter().enumerate().find_map(|(i, arg)| {
+        if arg == "--profile" {
+            args.get(i + 1).cloned()
+        } else {
+            arg.strip_prefix("--profile=").map(str::to_string)
+        }
+    });
+    from_args
+        .or_else(|| std::env::var("CHANGEDIR_PROFILE").ok())
+        .filter(|name| !name.is_empty() && name != DEFAULT_PROFILE)
+}
 
-    let content = bookmarks
-        .iter()
-        .map(|p| p.to_string_lossy().to_string())
-        .collect::<Vec<_>>()
-        .join("\n");
+fn is_valid_profile_name(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
 
-    fs::write(&path, content)?;
-    debug_print(verbose, "Bookmarks saved successfully");
-    Ok(())
+// A file location from the environment variable `name`, if set. A leading
+// `~` is expanded and other relative paths are taken from the home directory.
+fn path_from_env(name: &str) -> Option<PathBuf> {
+    let value = std::env::var(name).ok().filter(|v| !v.is_empty())?;
+    let home = dirs::home_dir().expect("Could not find home directory");
+    Some(expand_path(&value, &home))
 }
 
-fn load_history(verbose: bool) -> Vec<PathBuf> {
-    let path = get_history_path();
-    debug_print(verbose, &format!("Loading history from: {}", path.display()));
-    
-    if !path.exists() {
-        debug_print(verbose, "History file does not exist");
-        return Vec::new();
+// An XDG base directory: `variable` if it holds an absolute path, as the
+// specification requires, otherwise its default below the home directory.
+// On Windows the default is the platform's folder for it instead, unless
+// changeDir already keeps its files in the Unix-style location there.
+fn xdg_dir(variable: &str, default: &str, windows_dir: fn() -> Option<PathBuf>) -> PathBuf {
+    match std::env::var_os(variable).map(PathBuf::from) {
+        Some(dir) if dir.is_absolute() => dir,
+        _ => {
+            let dir = dirs::home_dir().expect("Could not find home directory").join(default);
+            if cfg!(windows) && !dir.join("changedir").exists() {
+                if let Some(windows_dir) = windows_dir() {
+                    return windows_dir;
+                }
+            }
+            dir
+        }
     }
+}
 
-    let history: Vec<PathBuf> = fs::read_to_string(&path)
-        .unwrap_or_default()
-        .lines()
-        .filter_map(|line| {
-            let trimmed = line.trim();
-            if trimmed.is_empty() {
-                None
-            } else {
-                Some(PathBuf::from(trimmed))
-            }
-        })
-        .collect();
-    
-    debug_print(verbose, &format!("Loaded {} history entries", history.len()));
-    history
+// %APPDATA% on Windows, which roams with the user
+fn xdg_config_home() -> PathBuf {
+    xdg_dir("XDG_CONFIG_HOME", ".config", dirs::config_dir)
 }
 
-fn save_history(history: &[PathBuf], verbose: bool) -> io::Result<()> {
-    let path = get_history_path();
-    debug_print(verbose, &format!("Saving {} history entries to: {}", history.len(), path.display()));
-    
-    if let Some(parent) = path.parent() {
-        debug_print(verbose, &format!("Creating parent directory: {}", parent.display()));
-        fs::create_dir_all(parent)?;
-    }
+// %LOCALAPPDATA% on Windows
+fn xdg_data_home() -> PathBuf {
+    xdg_dir("XDG_DATA_HOME", ".local/share", dirs::data_local_dir)
+}
 
-    let content = history
-        .iter()
-        .map(|p| p.to_string_lossy().to_string())
-        .collect::<Vec<_>>()
-        .join("\n");
+const CONFIG_FILE: &str = "changedir/config.toml";
 
-    fs::write(&path, content)?;
-    debug_print(verbose, "History saved successfully");
-    Ok(())
+// Read the config file. Having none is normal; a file that can't be read or
+// has bad lines only costs a warning, and everything else keeps its default.
+fn load_config(verbose: bool) -> config::Config {
+    let path = xdg_config_home().join(CONFIG_FILE);
+    let text = match fs::read_to_string(&path) {
+        Ok(text) => text,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            debug_print(verbose, &format!("No config file at {}", path.display()));
+            return config::Config::default();
+        }
+        Err(e) => {
+            eprintln!("{}", tr(Msg::ConfigProblem, &[&path.display(), &e]).yellow());
+            return config::Config::default();
+        }
+    };
+    debug_print(verbose, &format!("Reading config file: {}", path.display()));
+    let (config, problems) = config::Config::parse(&text);
+    for problem in problems {
+        eprintln!("{}", tr(Msg::ConfigProblem, &[&path.display(), &problem]).yellow());
+    }
+    config
 }
 
-fn add_to_history(path: PathBuf, verbose: bool) -> io::Result<()> {
-    debug_print(verbose, &format!("Adding to history: {}", path.display()));
-    let mut history = load_history(verbose);
-    
-    // Remove if already exists (to avoid duplicates)
-    let initial_len = history.len();
-    history.retain(|p| p != &path);
-    if history.len() < initial_len {
-        debug_print(verbose, "Removed duplicate entry from history");
+// `new`, after copying the file at `old` there if only that exists. Should
+// the copy fail the old location is used, so nobody is stranded.
+fn migrated_location(new: PathBuf, old: PathBuf) -> PathBuf {
+    if new.exists() || !old.exists() {
+        return new;
     }
-    
-    // Add to front
-    history.insert(0, path.clone());
-    debug_print(verbose, &format!("Added {} to history", path.display()));
-    
-    // Keep only last 10 entries
-    if history.len() > 10 {
-        let removed = history.len() - 10;
-        history.truncate(10);
-        debug_print(verbose, &format!("Truncated history, removed {} old entries", removed));
+    let copied = match new.parent() {
+        Some(parent) => fs::create_dir_all(parent).and_then(|_| fs::copy(&old, &new)),
+        None => fs::copy(&old, &new),
+    };
+    match copied {
+        Ok(_) => new,
+        Err(_) => old,
     }
-    
-    save_history(&history, verbose)
 }
 
-fn list_bookmarks(verbose: bool) -> io::Result<()> {
-    debug_print(verbose, "Listing bookmarks and history");
-    let bookmarks = load_bookmarks(verbose);
-    let history = load_history(verbose);
-    
-    // Filter out history entries that are already in bookmarks
-    let filtered_history: Vec<PathBuf> = history
-        .iter()
-        .filter(|hist_dir| !bookmarks.contains(hist_dir))
-        .cloned()
-        .collect();
-    
-    let total_items = bookmarks.len() + filtered_history.len();
-    
-    if total_items == 0 {
-        println!("{}", "No bookmarked directories.".yellow());
-        return Ok(());
+// The directory holding the named profiles. One that only exists at the old
+// location keeps being used there.
+fn profiles_dir() -> PathBuf {
+    let new = xdg_data_home().join(PROFILES_DIR);
+    if new.exists() {
+        return new;
+    }
+    match dirs::home_dir().map(|home| home.join(OLD_PROFILES_DIR)) {
+        Some(old) if old.is_dir() => old,
+        _ => new,
     }
+}
 
-    // List bookmarks
-    if !bookmarks.is_empty() {
-        debug_print(verbose, &format!("Displaying {} bookmarks", bookmarks.len()));
-        for (i, bookmark) in bookmarks.iter().enumerate() {
-            let prefix = get_prefix_char(i);
-            println!("{} {}", 
-                format!("[{}]", prefix).bright_cyan().bold(),
-                bookmark.display().to_string().bright_white()
-            );
+// The default profile's files can each be moved with an environment variable
+fn state_path(profile: Option<&str>, file: StateFile) -> PathBuf {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    match profile {
+        None => {
+            let variable = match file {
+                StateFile::Store => "CHANGEDIR_STORE_FILE",
+                StateFile::Bookmarks => "CHANGEDIR_BOOKMARK_FILE",
+                StateFile::History => "CHANGEDIR_HISTORY_FILE",
+                StateFile::Visits => "CHANGEDIR_VISITS_FILE",
+            };
+            path_from_env(variable).unwrap_or_else(|| match file {
+                StateFile::Store => migrated_location(xdg_config_home().join(STORE_FILE), home.join(OLD_STORE_FILE)),
+                StateFile::Visits => migrated_location(xdg_data_home().join(VISITS_FILE), home.join(OLD_VISITS_FILE)),
+                StateFile::Bookmarks => home.join(BOOKMARK_FILE),
+                StateFile::History => home.join(HISTORY_FILE),
+            })
         }
+        Some(name) => profiles_dir().join(name).join(match file {
+            StateFile::Store => "store.json",
+            StateFile::Bookmarks => "bookmarks",
+            StateFile::History => "history",
+            StateFile::Visits => "visits",
+        }),
     }
+}
 
-    // List history directories with continuous numbering (excluding duplicates)
-    if !filtered_history.is_empty() {
-        // Add blank line between bookmarks and history if both exist
-        if !bookmarks.is_empty() {
-            println!();
+fn get_visits_path() -> PathBuf {
+    state_path(active_profile(), StateFile::Visits)
+}
+
+// Each profile gets its own target file so wrappers in different profiles
+// never pick up each other's selections
+fn get_target_path() -> PathBuf {
+    let path = path_from_env("CHANGEDIR_TARGET_FILE").unwrap_or_else(|| {
+        match (std::env::var_os("CHANGEDIR_WRAPPER"), dirs::home_dir()) {
+            (Some(value), Some(home)) if value == "1" => home.join(OLD_TARGET_FILE),
+            _ => xdg_data_home().join(TARGET_FILE),
         }
-        
-        debug_print(verbose, &format!("Displaying {} history entries (after filtering duplicates)", filtered_history.len()));
-        let start_index = bookmarks.len();
-        for (i, hist_dir) in filtered_history.iter().enumerate() {
-            let index = start_index + i;
-            // Only show if within the 36-item limit (0-9, a-z)
-            if index < 36 {
-                let prefix = get_prefix_char(index);
-                println!("{} {}", 
-                    format!("[{}]", prefix).bright_cyan().bold(),
-                    hist_dir.display().to_string().bright_white()
-                );
-            }
+    });
+    match active_profile() {
+        Some(name) => {
+            let mut path = path.into_os_string();
+            path.push(format!(".{}", name));
+            PathBuf::from(path)
         }
+        None => path,
     }
-    
-    Ok(())
 }
 
-fn get_prefix_char(index: usize) -> char {
-    if index < 10 {
-        (b'0' + index as u8) as char
-    } else if index < 36 {
-        (b'a' + (index - 10) as u8) as char
-    } else {
-        '?'
+// The shell wrappers ask for the target file's location rather than working
+// it out themselves, so the two can't disagree
+fn print_target_path() -> io::Result<()> {
+    let mut bytes = path_bytes(&get_target_path());
+    bytes.push(b'\n');
+    io::stdout().write_all(&bytes)
+}
+
+// Failures are reported to the shell wrapper through a sibling of the target
+// file, so a wrapper never mistakes an error record for a directory
+fn get_error_path() -> PathBuf {
+    let mut path = get_target_path().into_os_string();
+    path.push(ERROR_SUFFIX);
+    PathBuf::from(path)
+}
+
+fn delete_target_file(verbose: bool) -> io::Result<()> {
+    for path in [get_target_path(), get_error_path()] {
+        if path.exists() {
+            debug_print(verbose, &format!("Deleting stale file: {}", path.display()));
+            fs::remove_file(&path)?;
+        }
     }
+    Ok(())
 }
 
-fn get_index_from_char(ch: char) -> Option<usize> {
-    match ch {
-        '0'..='9' => Some(ch as usize - '0' as usize),
-        'a'..='z' => Some(10 + (ch as usize - 'a' as usize)),
-        _ => None,
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0    success
+  1    error
+  2    nothing to choose from (no bookmarks or history)
+  3    invalid selection (unknown letter, label or step count)
+  4    the chosen directory no longer exists
+  5    no directory matches the name or pattern
+  130  cancelled";
+
+// Record a failure as "<code>\t<message>" for the shell wrapper. The wrapper
+// sets CHANGEDIR_WRAPPER and prints the message itself; otherwise (or if the
+// record cannot be written) the message goes to stderr as usual.
+fn fail(code: i32, message: ColoredString) -> ! {
+    let error_path = get_error_path();
+    let record = format!("{}\t{}\n", code, &*message);
+    let written = error_path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| write_file_atomic(&error_path, record.as_bytes()))
+        .is_ok();
+
+    if !written || std::env::var_os("CHANGEDIR_WRAPPER").is_none() {
+        eprintln!("{}", message);
     }
+    std::process::exit(code);
 }
 
-fn bookmark_current(verbose: bool) -> io::Result<()> {
-    let current_dir = std::env::current_dir()?;
-    debug_print(verbose, &format!("Bookmarking current directory: {}", current_dir.display()));
-    let mut bookmarks = load_bookmarks(verbose);
+// Holding a guard delays signal-driven exits until the guarded writes finish,
+// so a state file is either fully replaced or left untouched.
+struct CriticalSection;
 
-    if bookmarks.iter().any(|b| b == &current_dir) {
-        debug_print(verbose, "Directory already bookmarked");
-        eprintln!("{}", "Current directory is already bookmarked.".yellow());
-        return Ok(());
+impl CriticalSection {
+    fn enter() -> Self {
+        CRITICAL_SECTIONS.fetch_add(1, Ordering::SeqCst);
+        CriticalSection
     }
+}
 
-    debug_print(verbose, &format!("Current bookmark count: {}", bookmarks.len()));
-    if bookmarks.len() >= MAX_BOOKMARKS {
-        eprintln!("{}", format!("Error: Maximum of {} bookmarks reached. Remove a bookmark first.", MAX_BOOKMARKS).red().bold());
-        std::process::exit(1);
+impl Drop for CriticalSection {
+    fn drop(&mut self) {
+        CRITICAL_SECTIONS.fetch_sub(1, Ordering::SeqCst);
     }
+}
 
-    bookmarks.push(current_dir.clone());
-    save_bookmarks(&bookmarks, verbose)?;
-    println!("{}", format!("Bookmarked: {}", current_dir.display()).green());
-    Ok(())
+fn install_signal_handlers(verbose: bool) {
+    use signal_hook::consts::{SIGINT, SIGTERM};
+
+    let mut signals = match signal_hook::iterator::Signals::new([SIGINT, SIGTERM]) {
+        Ok(signals) => signals,
+        Err(e) => {
+            debug_print(verbose, &format!("Could not install signal handlers: {}", e));
+            return;
+        }
+    };
+
+    std::thread::spawn(move || {
+        for signal in signals.forever() {
+            if signal == SIGINT && PAGER_RUNNING.load(Ordering::SeqCst) {
+                continue;
+            }
+            // Let any in-progress save finish its rename before exiting
+            while CRITICAL_SECTIONS.load(Ordering::SeqCst) > 0 {
+                std::thread::sleep(std::time::Duration::from_millis(5));
+            }
+            let _ = crossterm::terminal::disable_raw_mode();
+            if io::stderr().is_terminal() {
+                let _ = crossterm::execute!(io::stderr(), crossterm::cursor::Show);
+                if signal == SIGINT {
+                    // Move past the half-typed prompt line
+                    eprintln!();
+                }
+            }
+            // The wrapper is told too, so it returns the cancelled status
+            fail(EXIT_CANCELLED, tr(Msg::Interrupted, &[]).yellow());
+        }
+    });
 }
 
-fn forget_current(verbose: bool) -> io::Result<()> {
-    let current_dir = std::env::current_dir()?;
-    debug_print(verbose, &format!("Forgetting current directory: {}", current_dir.display()));
-    let mut bookmarks = load_bookmarks(verbose);
+// Write to a temporary sibling file and rename it into place
+fn write_file_atomic(path: &Path, content: &[u8]) -> io::Result<()> {
+    let _guard = CriticalSection::enter();
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let tmp_path = path.with_file_name(format!(".{}.tmp{}", file_name, std::process::id()));
 
-    let initial_len = bookmarks.len();
-    bookmarks.retain(|b| b != &current_dir);
+    let result = fs::write(&tmp_path, content).and_then(|_| fs::rename(&tmp_path, path));
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
 
-    if bookmarks.len() < initial_len {
-        debug_print(verbose, "Directory was bookmarked, removing it");
-        save_bookmarks(&bookmarks, verbose)?;
-        println!("{}", format!("Removed bookmark: {}", current_dir.display()).green());
-    } else {
-        debug_print(verbose, "Directory was not bookmarked");
+// Write `path` as the directory for the shell wrapper to change to. It must
+// be an existing directory: a dead target would leave the wrapper with
+// nowhere to go.
+fn write_target_file(path: &Path, verbose: bool) -> io::Result<()> {
+    if !path.is_dir() {
+        debug_print(verbose, &format!("Refusing to write missing target: {}", path.display()));
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            tr(Msg::TargetNotDirectory, &[&path.display()]),
+        ));
     }
-    Ok(())
+    write_target_file_unchecked(path, verbose)
 }
 
-fn forget_all(verbose: bool) -> io::Result<()> {
-    let path = get_bookmark_path();
-    debug_print(verbose, &format!("Forgetting all bookmarks, file: {}", path.display()));
+// The same without the check, for targets that are known to be fine
+fn write_target_file_unchecked(path: &Path, verbose: bool) -> io::Result<()> {
+    let target_path = get_target_path();
+    debug_print(verbose, &format!("Writing target directory to: {}", target_path.display()));
     
-    if path.exists() {
-        debug_print(verbose, "Removing bookmark file");
-        fs::remove_file(&path)?;
-        println!("{}", "All bookmarks removed.".green());
-    } else {
-        debug_print(verbose, "Bookmark file does not exist");
-        println!("{}", "No bookmarks to remove.".yellow());
+    // Create parent directory if it doesn't exist
+    if let Some(parent) = target_path.parent() {
+        fs::create_dir_all(parent)?;
     }
+    
+    write_file_atomic(&target_path, &target_bytes(path))?;
+    debug_print(verbose, "Target file written successfully");
     Ok(())
 }
 
-fn choose_directory_interactive(verbose: bool) -> io::Result<()> {
-    debug_print(verbose, "Interactive directory selection");
-    let bookmarks = load_bookmarks(verbose);
-    let history = load_history(verbose);
-    
-    // Filter out history entries that are already in bookmarks
-    let filtered_history: Vec<PathBuf> = history
-        .iter()
-        .filter(|hist_dir| !bookmarks.contains(hist_dir))
-        .cloned()
-        .collect();
-    
-    let total_items = bookmarks.len() + filtered_history.len();
-    
-    if total_items == 0 {
-        eprintln!("{}", "No bookmarked directories.".yellow());
-        std::process::exit(1);
+// The path as written to the target file: its raw bytes, so the shell can
+// cd to a directory whose name is not valid UTF-8. On Windows a shell such as
+// Git Bash reading the file may want forward slashes; elsewhere the native
+// form already uses them.
+fn target_bytes(path: &Path) -> Vec<u8> {
+    if selection_options().unix_target && std::path::MAIN_SEPARATOR != '/' {
+        path.to_string_lossy().replace(std::path::MAIN_SEPARATOR, "/").into_bytes()
+    } else {
+        path_bytes(path)
     }
+}
 
-    // Display bookmarks (to stdout for proper color display)
-    if !bookmarks.is_empty() {
-        debug_print(verbose, &format!("Displaying {} bookmarks for selection", bookmarks.len()));
-        for (i, bookmark) in bookmarks.iter().enumerate() {
-            let prefix = get_prefix_char(i);
-            println!("{} {}", 
-                format!("[{}]", prefix).bright_cyan().bold(),
-                bookmark.display().to_string().bright_white()
-            );
-        }
+const DEFAULT_TEMP_DURATION: &str = "24h";
+const DEFAULT_STALE_DAYS: &str = "30";
+const DEFAULT_HISTORY_AGE: &str = "30d";
+const DEFAULT_SEARCH_DEPTH: usize = 5;
+
+// In the legacy bookmark file (and bookmark files given to --import) a line is
+// the path optionally followed by tab-separated attributes: "expires=<unix
+// seconds>" for temporary bookmarks and "pinned" for bookmarks that have been
+// made permanent explicitly.
+#[derive(Clone)]
+struct Bookmark {
+    path: PathBuf,
+    label: Option<String>,
+    tags: Vec<String>,
+    note: Option<String>,
+    expires: Option<u64>,
+    pinned: bool,
+}
+
+impl Bookmark {
+    fn new(path: PathBuf) -> Self {
+        Bookmark { path, label: None, tags: Vec::new(), note: None, expires: None, pinned: false }
     }
 
-    // Display history directories with continuous numbering (to stdout for proper color display, excluding duplicates)
-    if !filtered_history.is_empty() {
-        // Add blank line between bookmarks and history if both exist
-        if !bookmarks.is_empty() {
-            println!();
+    // Lines are "[label\t]path[\tattribute...]". A label is never an absolute
+    // path, so unlabelled lines from older files still parse as before.
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let first = fields.next()?.trim();
+        if first.is_empty() {
+            return None;
         }
-        
-        debug_print(verbose, &format!("Displaying {} history entries for selection (after filtering duplicates)", filtered_history.len()));
-        let start_index = bookmarks.len();
-        for (i, hist_dir) in filtered_history.iter().enumerate() {
-            let index = start_index + i;
-            // Only show if within the 36-item limit (0-9, a-z)
-            if index < 36 {
-                let prefix = get_prefix_char(index);
-                println!("{} {}", 
-                    format!("[{}]", prefix).bright_cyan().bold(),
-                    hist_dir.display().to_string().bright_white()
-                );
+
+        let mut bookmark = if Path::new(first).is_absolute() {
+            Bookmark::new(PathBuf::from(first))
+        } else {
+            let path = fields.next()?.trim();
+            if path.is_empty() {
+                return None;
+            }
+            let mut bookmark = Bookmark::new(PathBuf::from(path));
+            bookmark.label = Some(first.to_string());
+            bookmark
+        };
+        for field in fields {
+            match field.trim().split_once('=') {
+                Some(("expires", value)) => bookmark.expires = value.parse().ok(),
+                Some(("note", value)) if !value.is_empty() => bookmark.note = Some(value.to_string()),
+                Some(("tags", value)) => {
+                    bookmark.tags = value.split(',').filter(|t| !t.is_empty()).map(str::to_string).collect()
+                }
+                None if field.trim() == "pinned" => bookmark.pinned = true,
+                _ => {}
             }
         }
+        Some(bookmark)
     }
-    io::stdout().flush()?;
 
-    // Prompt on stdout (same stream as list for consistency)
-    print!("{}", "Select directory (0-9, a-z): ".bright_yellow());
-    io::stdout().flush()?;
+    fn is_expired(&self, now: u64) -> bool {
+        self.expires.is_some_and(|expires| expires <= now)
+    }
+}
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    debug_print(verbose, &format!("User input: '{}'", input.trim()));
-    
-    let ch = input.trim().chars().next();
-    if let Some(ch) = ch {
-        if let Some(index) = get_index_from_char(ch) {
-            debug_print(verbose, &format!("Parsed index: {}", index));
-            // Check if index is in bookmarks
-            if index < bookmarks.len() {
-                let selected = &bookmarks[index];
-                debug_print(verbose, &format!("Selected directory: {}", selected.display()));
-                add_to_history(selected.clone(), verbose)?;
-                write_target_file(selected, verbose)?;
-                return Ok(());
-            } 
-            // Check if index is in filtered history (accounting for bookmark offset)
-            else if index < total_items && index < 36 {
-                let history_index = index - bookmarks.len();
-                if history_index < filtered_history.len() {
-                    let selected = &filtered_history[history_index];
-                    debug_print(verbose, &format!("Selected directory: {}", selected.display()));
-                    add_to_history(selected.clone(), verbose)?;
-                    write_target_file(selected, verbose)?;
-                    return Ok(());
-                }
+// Labels are typed on the command line and stored in a tab-separated file.
+// Single characters are excluded so a label can never be mistaken for a letter.
+fn is_valid_label(label: &str) -> bool {
+    label.chars().count() > 1
+        && !label.starts_with(['-', '~', '.'])
+        && !label.contains(['/', '\\', '='])
+        && !label.chars().any(char::is_whitespace)
+}
+
+// Notes are free text, but tabs and line breaks would split the bookmark line
+fn clean_note(note: &str) -> Option<String> {
+    let note: String = note
+        .chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect();
+    let note = note.trim();
+    (!note.is_empty()).then(|| note.to_string())
+}
+
+// Tags are stored comma-separated inside a tab-separated attribute
+fn is_valid_tag(tag: &str) -> bool {
+    !tag.is_empty() && !tag.contains([',', '=']) && !tag.chars().any(char::is_whitespace)
+}
+
+fn find_bookmark_by_label<'a>(bookmarks: &'a [Bookmark], label: &str) -> Option<&'a Bookmark> {
+    bookmarks.iter().find(|b| b.label.as_deref() == Some(label))
+}
+
+fn now_secs() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+// Parse durations like "90m", "8h" or "3d" into seconds
+fn parse_duration(value: &str) -> Option<u64> {
+    let value = value.trim();
+    let split = value.find(|c: char| !c.is_ascii_digit()).unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let multiplier = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        "w" => 7 * 24 * 60 * 60,
+        _ => return None,
+    };
+    number.checked_mul(multiplier).filter(|&secs| secs > 0)
+}
+
+// Short human form of a number of seconds, e.g. "45m", "3h", "2d"
+fn format_duration(secs: u64) -> String {
+    if secs < 60 * 60 {
+        format!("{}m", secs.div_ceil(60).max(1))
+    } else if secs < 24 * 60 * 60 {
+        format!("{}h", secs.div_ceil(60 * 60))
+    } else {
+        format!("{}d", secs / (24 * 60 * 60))
+    }
+}
+
+// All bookmarks and history of a profile, kept in one versioned JSON file:
+// {"version":1,"bookmarks":[...],"history":[...]}. This is also the
+// --export format.
+#[derive(Default)]
+struct Store {
+    bookmarks: Vec<Bookmark>,
+    // Most recent first, with the visit time where known
+    history: Vec<(PathBuf, Option<u64>)>,
+}
+
+impl Store {
+    fn to_json(&self) -> String {
+        let bookmark_items: Vec<String> = self
+            .bookmarks
+            .iter()
+            .map(|bookmark| {
+                let mut fields = path_json_fields(&store_form(&bookmark.path));
+                if let Some(label) = &bookmark.label {
+                    fields.push(("label", json::string(label)));
+                }
+                if !bookmark.tags.is_empty() {
+                    fields.push(("tags", json::string_array(&bookmark.tags)));
+                }
+                if let Some(note) = &bookmark.note {
+                    fields.push(("note", json::string(note)));
+                }
+                if let Some(expires) = bookmark.expires {
+                    fields.push(("expires", expires.to_string()));
+                }
+                if bookmark.pinned {
+                    fields.push(("pinned", "true".to_string()));
+                }
+                json::object(&fields)
+            })
+            .collect();
+        let history_items: Vec<String> = self
+            .history
+            .iter()
+            .map(|(path, visited)| {
+                let mut fields = path_json_fields(&store_form(path));
+                if let Some(visited) = visited {
+                    fields.push(("visited", visited.to_string()));
+                }
+                json::object(&fields)
+            })
+            .collect();
+
+        format!(
+            "{{\n  \"version\": {},\n  \"bookmarks\": {},\n  \"history\": {}\n}}\n",
+            STORE_VERSION,
+            json::array_at(&bookmark_items, 1),
+            json::array_at(&history_items, 1)
+        )
+    }
+
+    // The store in `content`, or a description of what is wrong with it
+    fn parse(content: &str) -> Result<Store, String> {
+        let document = json::parse(content)?;
+        match document.get("version").and_then(json::Value::as_u64) {
+            Some(STORE_VERSION) => {}
+            Some(version) => return Err(format!("unsupported version {}", version)),
+            None => return Err("missing version".to_string()),
+        }
+
+        let entries = |key: &str| document.get(key).and_then(json::Value::as_array).unwrap_or_default();
+        let mut store = Store::default();
+        for (index, entry) in entries("bookmarks").iter().enumerate() {
+            let path = path_from_json(entry).ok_or_else(|| format!("bookmark {} has no path", index + 1))?;
+            let mut bookmark = Bookmark::new(expand_home(path));
+            bookmark.label = entry.get("label").and_then(json::Value::as_str).map(str::to_string);
+            bookmark.tags = entry
+                .get("tags")
+                .and_then(json::Value::as_array)
+                .unwrap_or_default()
+                .iter()
+                .filter_map(json::Value::as_str)
+                .map(str::to_string)
+                .collect();
+            bookmark.note = entry.get("note").and_then(json::Value::as_str).and_then(clean_note);
+            bookmark.expires = entry.get("expires").and_then(json::Value::as_u64);
+            bookmark.pinned = entry.get("pinned").and_then(json::Value::as_bool).unwrap_or(false);
+            store.bookmarks.push(bookmark);
+        }
+        for (index, entry) in entries("history").iter().enumerate() {
+            let path = path_from_json(entry).ok_or_else(|| format!("history entry {} has no path", index + 1))?;
+            let visited = entry.get("visited").and_then(json::Value::as_u64);
+            store.history.push((expand_home(path), visited));
+        }
+        Ok(store)
+    }
+}
+
+const STORE_VERSION: u64 = 1;
+
+fn load_store(verbose: bool) -> Store {
+    load_profile_store(active_profile(), verbose)
+}
+
+// A corrupt store stops the command rather than being treated as empty, so
+// the next save can't overwrite it
+fn load_profile_store(profile: Option<&str>, verbose: bool) -> Store {
+    let path = state_path(profile, StateFile::Store);
+    debug_print(verbose, &format!("Loading store from: {}", path.display()));
+
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return migrate_legacy_files(profile, verbose),
+        Err(e) => fail(1, tr(Msg::CannotReadFile, &[&path.display(), &e]).red()),
+    };
+    match Store::parse(&content) {
+        Ok(store) => {
+            debug_print(verbose, &format!("Loaded {} bookmarks and {} history entries", store.bookmarks.len(), store.history.len()));
+            store
+        }
+        Err(e) => fail(1, tr(Msg::CorruptStore, &[&path.display(), &e]).red()),
+    }
+}
+
+// Build the store from the plain-text bookmark and history files of older
+// versions and write it, so this happens once. The old files are left alone.
+fn migrate_legacy_files(profile: Option<&str>, verbose: bool) -> Store {
+    let bookmark_path = state_path(profile, StateFile::Bookmarks);
+    let history_path = state_path(profile, StateFile::History);
+    if !bookmark_path.exists() && !history_path.exists() {
+        debug_print(verbose, "No store or legacy files yet");
+        return Store::default();
+    }
+
+    // A legacy file that exists but can't be read (a directory, say, or no
+    // permission) must not be migrated as empty
+    let read = |path: &Path| match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => fail(1, tr(Msg::CannotReadFile, &[&path.display(), &e]).red()),
+    };
+    let store = Store {
+        bookmarks: read(&bookmark_path).lines().filter_map(Bookmark::parse).collect(),
+        history: read(&history_path).lines().filter_map(parse_legacy_history_line).collect(),
+    };
+    debug_print(verbose, &format!("Migrating {} bookmarks and {} history entries from legacy files", store.bookmarks.len(), store.history.len()));
+    if let Err(e) = save_profile_store(profile, &store, verbose) {
+        debug_print(verbose, &format!("Could not write migrated store: {}", e));
+    }
+    store
+}
+
+// "<unix seconds>\t<path>", or a bare path from before visit times were kept
+fn parse_legacy_history_line(line: &str) -> Option<(PathBuf, Option<u64>)> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() {
+        return None;
+    }
+    match trimmed.split_once('\t') {
+        Some((time, rest)) if !rest.is_empty() && time.parse::<u64>().is_ok() => {
+            Some((PathBuf::from(rest), time.parse().ok()))
+        }
+        _ => Some((PathBuf::from(trimmed), None)),
+    }
+}
+
+fn save_store(store: &Store, verbose: bool) -> io::Result<()> {
+    save_profile_store(active_profile(), store, verbose)
+}
+
+fn save_profile_store(profile: Option<&str>, store: &Store, verbose: bool) -> io::Result<()> {
+    let path = state_path(profile, StateFile::Store);
+    debug_print(verbose, &format!("Saving {} bookmarks and {} history entries to: {}", store.bookmarks.len(), store.history.len(), path.display()));
+
+    if let Some(parent) = path.parent() {
+        debug_print(verbose, &format!("Creating parent directory: {}", parent.display()));
+        fs::create_dir_all(parent)?;
+    }
+    write_file_atomic(&path, store.to_json().as_bytes())?;
+    debug_print(verbose, "Store saved successfully");
+    Ok(())
+}
+
+fn load_bookmarks(verbose: bool) -> Vec<Bookmark> {
+    let now = now_secs();
+    let bookmarks: Vec<Bookmark> = load_store(verbose)
+        .bookmarks
+        .into_iter()
+        .filter(|bookmark| {
+            // Expired temporary bookmarks disappear here and are dropped from
+            // the store on the next save
+            let expired = bookmark.is_expired(now);
+            if expired {
+                debug_print(verbose, &format!("Dropping expired bookmark: {}", bookmark.path.display()));
+            }
+            !expired
+        })
+        .collect();
+    
+    debug_print(verbose, &format!("Loaded {} bookmarks", bookmarks.len()));
+    bookmarks
+}
+
+fn save_bookmarks(bookmarks: &[Bookmark], verbose: bool) -> io::Result<()> {
+    let mut store = load_store(verbose);
+    store.bookmarks = bookmarks.to_vec();
+    save_store(&store, verbose)
+}
+
+// History entries that are not already shown as bookmarks
+fn filter_history(bookmarks: &[Bookmark], history: &[PathBuf]) -> Vec<PathBuf> {
+    history
+        .iter()
+        .filter(|hist_dir| !bookmarks.iter().any(|b| &b.path == *hist_dir))
+        .cloned()
+        .collect()
+}
+
+// The history section as listed and selected after the bookmarks: duplicates
+// of bookmarks removed, ordered by recency or (with --sort frecency) by score
+fn displayed_history(bookmarks: &[Bookmark], verbose: bool) -> Vec<PathBuf> {
+    // Entries past a lowered limit are dropped on the next write; until then
+    // they are just not shown
+    let mut stored = load_history(verbose);
+    stored.truncate(history_max());
+    order_history(filter_history(bookmarks, &stored), verbose)
+}
+
+// Recency order as stored, or with --sort frecency by score
+fn order_history(mut history: Vec<PathBuf>, verbose: bool) -> Vec<PathBuf> {
+    if selection_options().sort_frecency {
+        let visits = load_visits(verbose);
+        let now = now_secs();
+        let score = |path: &PathBuf| {
+            visits
+                .iter()
+                .find(|v| &v.path == path)
+                .map_or(0.0, |v| frecency(v, now))
+        };
+        // Stable sort keeps recency order among equal scores
+        history.sort_by(|a, b| score(b).total_cmp(&score(a)));
+        debug_print(verbose, "Ordered history by frecency");
+    }
+    history
+}
+
+fn load_timed_history(verbose: bool) -> Vec<(PathBuf, Option<u64>)> {
+    load_store(verbose).history
+}
+
+fn load_history(verbose: bool) -> Vec<PathBuf> {
+    load_timed_history(verbose).into_iter().map(|(path, _)| path).collect()
+}
+
+fn save_timed_history(history: &[(PathBuf, Option<u64>)], verbose: bool) -> io::Result<()> {
+    let mut store = load_store(verbose);
+    store.history = history.iter().take(history_max()).cloned().collect();
+    save_store(&store, verbose)
+}
+
+// Save `history`, keeping the visit times already recorded for its entries
+fn save_history(history: &[PathBuf], verbose: bool) -> io::Result<()> {
+    let mut store = load_store(verbose);
+    let times = std::mem::take(&mut store.history);
+    let timed: Vec<(PathBuf, Option<u64>)> = history
+        .iter()
+        .take(history_max())
+        .map(|path| {
+            let time = times.iter().find(|(p, _)| p == path).and_then(|(_, time)| *time);
+            (path.clone(), time)
+        })
+        .collect();
+    store.history = timed;
+    save_store(&store, verbose)
+}
+
+fn add_to_history(path: PathBuf, limit: usize, verbose: bool) -> io::Result<()> {
+    let path = stored_path(&path);
+    debug_print(verbose, &format!("Adding to history: {}", path.display()));
+    let mut history = load_timed_history(verbose);
+    
+    // Remove if already exists (to avoid duplicates), including entries
+    // stored before paths were canonicalized
+    let initial_len = history.len();
+    history.retain(|(p, _)| !same_directory(p, &path));
+    if history.len() < initial_len {
+        debug_print(verbose, "Removed duplicate entry from history");
+    }
+    
+    // Add to front
+    history.insert(0, (path.clone(), Some(now_secs())));
+    debug_print(verbose, &format!("Added {} to history", path.display()));
+    
+    // Keep only the most recent entries
+    if history.len() > limit {
+        let removed = history.len() - limit;
+        history.truncate(limit);
+        debug_print(verbose, &format!("Truncated history to {} entries, removed {} old entries", limit, removed));
+    }
+    
+    save_timed_history(&history, verbose)
+}
+
+// How long ago `time` was, in words
+fn format_age(time: u64, now: u64) -> String {
+    let secs = now.saturating_sub(time);
+    let (count, one, many) = if secs < 60 {
+        return tr(Msg::JustNow, &[]);
+    } else if secs < 60 * 60 {
+        (secs / 60, Msg::MinuteAgo, Msg::MinutesAgo)
+    } else if secs < 24 * 60 * 60 {
+        (secs / (60 * 60), Msg::HourAgo, Msg::HoursAgo)
+    } else {
+        (secs / (24 * 60 * 60), Msg::DayAgo, Msg::DaysAgo)
+    };
+    if count == 1 {
+        tr(one, &[])
+    } else {
+        tr(many, &[&count])
+    }
+}
+
+// History newest first by visit time, with how long ago each visit was.
+// Entries without a recorded time come last, in their stored order.
+fn list_recent(verbose: bool) -> io::Result<()> {
+    let mut history = load_timed_history(verbose);
+    if history.is_empty() {
+        println!("{}", tr(Msg::NoHistory, &[]).yellow());
+        return Ok(());
+    }
+    history.sort_by_key(|(_, time)| std::cmp::Reverse(*time));
+
+    let now = now_secs();
+    let width = history.iter().map(|(p, _)| p.display().to_string().chars().count()).max().unwrap_or(0);
+    for (path, time) in &history {
+        let age = match time {
+            Some(time) => format_age(*time, now),
+            None => tr(Msg::AgeUnknown, &[]),
+        };
+        let shown = path.display().to_string();
+        let padding = " ".repeat(width - shown.chars().count());
+        println!("{}{}  {}", shown.bright_white(), padding, age.dimmed());
+    }
+    Ok(())
+}
+
+// Unlike history, the visit log keeps every directory ever selected (up to
+// MAX_VISITS) with how often and when it was visited. Lines use the same
+// "path<TAB>key=value..." layout as bookmarks.
+const MAX_VISITS: usize = 1000;
+
+#[derive(Clone)]
+struct Visit {
+    path: PathBuf,
+    count: u64,
+    first: u64,
+    last: u64,
+    // Manual rank adjustment set by --boost/--demote: the frecency score is
+    // multiplied by 2^boost, with boost clamped to +/-MAX_BOOST
+    boost: i32,
+}
+
+const MAX_BOOST: i32 = 3;
+
+// Frecency in the style of zoxide: visit count weighted by how recently the
+// directory was last visited, then by the manual boost
+fn frecency(visit: &Visit, now: u64) -> f64 {
+    let age = now.saturating_sub(visit.last);
+    let recency = if age < 60 * 60 {
+        4.0
+    } else if age < 24 * 60 * 60 {
+        2.0
+    } else if age < 7 * 24 * 60 * 60 {
+        0.5
+    } else {
+        0.25
+    };
+    // Boosted entries that were never visited still need a score to multiply
+    (visit.count.max(1) as f64) * recency * 2f64.powi(visit.boost)
+}
+
+impl Visit {
+    fn parse(line: &str) -> Option<Self> {
+        let mut fields = line.split('\t');
+        let path = fields.next()?.trim();
+        if path.is_empty() {
+            return None;
+        }
+
+        let mut visit = Visit { path: parse_path_field(path), count: 0, first: 0, last: 0, boost: 0 };
+        for field in fields {
+            match field.trim().split_once('=') {
+                Some(("count", value)) => visit.count = value.parse().unwrap_or(0),
+                Some(("first", value)) => visit.first = value.parse().unwrap_or(0),
+                Some(("last", value)) => visit.last = value.parse().unwrap_or(0),
+                Some(("boost", value)) => visit.boost = value.parse::<i32>().unwrap_or(0).clamp(-MAX_BOOST, MAX_BOOST),
+                _ => {}
+            }
+        }
+        Some(visit)
+    }
+
+    fn to_line(&self) -> String {
+        let mut line = format!(
+            "{}\tcount={}\tfirst={}\tlast={}",
+            path_field(&self.path),
+            self.count,
+            self.first,
+            self.last
+        );
+        if self.boost != 0 {
+            line.push_str(&format!("\tboost={}", self.boost));
+        }
+        line
+    }
+}
+
+fn load_visits(verbose: bool) -> Vec<Visit> {
+    let path = get_visits_path();
+    debug_print(verbose, &format!("Loading visit log from: {}", path.display()));
+
+    let visits: Vec<Visit> = fs::read_to_string(&path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(Visit::parse)
+        .collect();
+
+    debug_print(verbose, &format!("Loaded {} visit log entries", visits.len()));
+    visits
+}
+
+fn save_visits(visits: &[Visit], verbose: bool) -> io::Result<()> {
+    let path = get_visits_path();
+    debug_print(verbose, &format!("Saving {} visit log entries to: {}", visits.len(), path.display()));
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let content = visits
+        .iter()
+        .map(Visit::to_line)
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    write_file_atomic(&path, content.as_bytes())
+}
+
+// The shape of the stored bookmarks and history, to tell when a cleanup is due
+struct StoreSummary {
+    bookmarks: usize,
+    history: usize,
+    // History entries hidden from listings because they are also bookmarks
+    shadowed: usize,
+    // Bookmarks and shown history entries whose directory is gone
+    missing: usize,
+    deepest: Option<PathBuf>,
+    shallowest: Option<PathBuf>,
+}
+
+fn summarize_store(verbose: bool) -> StoreSummary {
+    let bookmarks = load_bookmarks(verbose);
+    let history = load_history(verbose);
+    let filtered_history = filter_history(&bookmarks, &history);
+    let depth = |path: &PathBuf| {
+        path.components()
+            .filter(|c| matches!(c, std::path::Component::Normal(_)))
+            .count()
+    };
+    StoreSummary {
+        bookmarks: bookmarks.len(),
+        history: history.len(),
+        shadowed: history.len() - filtered_history.len(),
+        missing: bookmarks
+            .iter()
+            .map(|b| &b.path)
+            .chain(&filtered_history)
+            .filter(|p| known_missing(p))
+            .count(),
+        deepest: bookmarks.iter().map(|b| &b.path).max_by_key(|p| depth(p)).cloned(),
+        shallowest: bookmarks.iter().map(|b| &b.path).min_by_key(|p| depth(p)).cloned(),
+    }
+}
+
+fn print_store_summary(summary: &StoreSummary) {
+    let path_text = |path: &Option<PathBuf>| path.as_ref().map_or("-".to_string(), |p| p.display().to_string());
+    let rows = [
+        (tr(Msg::StatsBookmarks, &[]), summary.bookmarks.to_string()),
+        (tr(Msg::StatsHistory, &[]), summary.history.to_string()),
+        (tr(Msg::StatsShadowed, &[]), summary.shadowed.to_string()),
+        (tr(Msg::StatsMissing, &[]), summary.missing.to_string()),
+        (tr(Msg::StatsDeepest, &[]), path_text(&summary.deepest)),
+        (tr(Msg::StatsShallowest, &[]), path_text(&summary.shallowest)),
+    ];
+    let width = rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+    for (label, value) in &rows {
+        let padding = " ".repeat(width - label.chars().count());
+        println!("{}:{}  {}", label, padding, value.bright_white());
+    }
+}
+
+// A summary of the store, then the `limit` most visited directories from the
+// visit log with their share of all recorded jumps. Directories that are gone
+// are still listed, flagged.
+fn list_stats(limit: usize, as_json: bool, verbose: bool) -> io::Result<()> {
+    let summary = summarize_store(verbose);
+    let mut visits: Vec<Visit> = load_visits(verbose).into_iter().filter(|v| v.count > 0).collect();
+    let total: u64 = visits.iter().map(|v| v.count).sum();
+    visits.sort_by(|a, b| b.count.cmp(&a.count).then(b.last.cmp(&a.last)));
+    let shown = &visits[..visits.len().min(limit)];
+    debug_print(verbose, &format!("Showing {} of {} visited directories ({} jumps)", shown.len(), visits.len(), total));
+    let share = |visit: &Visit| visit.count as f64 * 100.0 / total as f64;
+
+    if as_json {
+        let items: Vec<String> = shown
+            .iter()
+            .map(|visit| {
+                let mut fields = vec![
+                    ("path", json::string(&visit.path.to_string_lossy())),
+                    ("visits", visit.count.to_string()),
+                    ("first", visit.first.to_string()),
+                    ("last", visit.last.to_string()),
+                    ("share", format!("{:.4}", share(visit) / 100.0)),
+                ];
+                if known_missing(&visit.path) {
+                    fields.push(("missing", "true".to_string()));
+                }
+                json::object(&fields)
+            })
+            .collect();
+        let path_json = |path: &Option<PathBuf>| path.as_ref().map_or("null".to_string(), |p| json::string(&p.to_string_lossy()));
+        println!("{{");
+        println!("  \"bookmarks\": {},", summary.bookmarks);
+        println!("  \"history\": {},", summary.history);
+        println!("  \"shadowed\": {},", summary.shadowed);
+        println!("  \"missing\": {},", summary.missing);
+        println!("  \"deepest\": {},", path_json(&summary.deepest));
+        println!("  \"shallowest\": {},", path_json(&summary.shallowest));
+        println!("  \"top\": {}", json::array_at(&items, 1));
+        println!("}}");
+        return Ok(());
+    }
+
+    print_store_summary(&summary);
+    println!();
+    if shown.is_empty() {
+        println!("{}", tr(Msg::NoVisits, &[]).yellow());
+        return Ok(());
+    }
+    println!("{}", tr(Msg::StatsHeader, &[&shown.len(), &visits.len(), &total]).bold());
+    let now = now_secs();
+    let shown_path = |visit: &Visit| {
+        let path = visit.path.display().to_string();
+        if known_missing(&visit.path) {
+            (format!("{} {}", path, tr(Msg::MissingSuffix, &[])), true)
+        } else {
+            (path, false)
+        }
+    };
+    let width = shown.iter().map(|v| shown_path(v).0.chars().count()).max().unwrap_or(0);
+    for visit in shown {
+        let (path, missing) = shown_path(visit);
+        let padding = " ".repeat(width - path.chars().count());
+        let percent = format!("{:.1}", share(visit));
+        let details = if visit.count == 1 {
+            tr(Msg::StatsOneVisit, &[&percent, &format_age(visit.last, now)])
+        } else {
+            tr(Msg::StatsVisits, &[&visit.count, &percent, &format_age(visit.first, now), &format_age(visit.last, now)])
+        };
+        let path = if missing { path.red() } else { path.bright_white() };
+        println!("{}{}  {}", path, padding, details.dimmed());
+    }
+    Ok(())
+}
+
+fn record_visit(path: &Path, verbose: bool) -> io::Result<()> {
+    let mut visits = load_visits(verbose);
+    let now = now_secs();
+
+    match visits.iter_mut().find(|v| v.path == path) {
+        Some(visit) => {
+            visit.count += 1;
+            visit.last = now;
+        }
+        None => visits.push(Visit { path: path.to_path_buf(), count: 1, first: now, last: now, boost: 0 }),
+    }
+
+    if visits.len() > MAX_VISITS {
+        // Forget the directories that have gone unvisited the longest
+        visits.sort_by_key(|v| std::cmp::Reverse(v.last));
+        visits.truncate(MAX_VISITS);
+        debug_print(verbose, &format!("Trimmed visit log to {} entries", MAX_VISITS));
+    }
+
+    save_visits(&visits, verbose)
+}
+
+// Resolve a --boost/--demote target: a letter from the combined list, or a path
+fn resolve_entry(target: &str, verbose: bool) -> io::Result<PathBuf> {
+    let mut chars = target.chars();
+    if let (Some(index), None) = (chars.next().and_then(get_index_from_char), chars.next()) {
+        let bookmarks = load_bookmarks(verbose);
+        let history = displayed_history(&bookmarks, verbose);
+        return match index.checked_sub(bookmarks.len()) {
+            None => Ok(bookmarks[lettered_order(&bookmarks, verbose)[index]].path.clone()),
+            Some(i) if i < history.len() => Ok(history[i].clone()),
+            Some(_) => fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidDirectoryLetter, &[&target]).red()),
+        };
+    }
+
+    let path = std::env::current_dir()?.join(target);
+    match fs::canonicalize(&path) {
+        Ok(path) if path.is_dir() => Ok(path),
+        _ => fail(EXIT_NOT_FOUND, tr(Msg::DirectoryNotFound, &[&target]).red()),
+    }
+}
+
+// Adjust the manual rank of an entry by `step` boost levels, or clear it
+fn adjust_boost(target: &str, step: i32, reset: bool, verbose: bool) -> io::Result<()> {
+    let path = resolve_entry(target, verbose)?;
+    debug_print(verbose, &format!("Adjusting boost of {} by {} (reset: {})", path.display(), step, reset));
+    let mut visits = load_visits(verbose);
+
+    let index = match visits.iter().position(|v| v.path == path) {
+        Some(index) => index,
+        None => {
+            visits.push(Visit { path: path.clone(), count: 0, first: 0, last: 0, boost: 0 });
+            visits.len() - 1
+        }
+    };
+    let visit = &mut visits[index];
+    let before = visit.boost;
+    visit.boost = if reset { 0 } else { (visit.boost + step).clamp(-MAX_BOOST, MAX_BOOST) };
+    let boost = visit.boost;
+    save_visits(&visits, verbose)?;
+
+    let message = if reset {
+        tr(Msg::BoostReset, &[&path.display()])
+    } else if boost == before {
+        tr(Msg::BoostLimit, &[&path.display(), &format_boost(boost)])
+    } else {
+        tr(Msg::BoostSet, &[&path.display(), &format_boost(boost)])
+    };
+    println!("{}", message.green());
+    Ok(())
+}
+
+fn format_boost(boost: i32) -> String {
+    if boost >= 0 {
+        format!("x{}", 1u32 << boost)
+    } else {
+        format!("x1/{}", 1u32 << -boost)
+    }
+}
+
+// Pair each bookmark (with its index, i.e. its letter) with its visit record
+fn bookmark_visits(bookmarks: &[Bookmark], visits: &[Visit]) -> Vec<(usize, Option<Visit>)> {
+    bookmarks
+        .iter()
+        .enumerate()
+        .map(|(i, bookmark)| (i, visits.iter().find(|v| v.path == bookmark.path).cloned()))
+        .collect()
+}
+
+// Record a selected directory in history and hand it to the shell wrapper.
+// Both writes happen inside one critical section so a cancel never leaves one
+// without the other.
+fn change_directory(path: &Path, verbose: bool) -> io::Result<()> {
+    // A bookmark or history entry can outlive its directory
+    let Some(resolved) = resolve_bookmark_path(path) else {
+        fail(EXIT_DIRECTORY_MISSING, tr(Msg::RelativeNotFound, &[&path.display()]).red());
+    };
+    if known_missing(&resolved) {
+        fail(EXIT_DIRECTORY_MISSING, tr(Msg::SelectedMissing, &[&path.display()]).red());
+    }
+    if resolved != path {
+        debug_print(verbose, &format!("Resolved {} to {}", path.display(), resolved.display()));
+    }
+    let path = resolved.as_path();
+    let _guard = CriticalSection::enter();
+    record_selection(path, verbose)?;
+    // Only deliveries that actually move the shell have somewhere to come back to
+    if matches!(selection_options().delivery, Delivery::TargetFile | Delivery::Eval) {
+        if let Ok(current) = std::env::current_dir() {
+            push_back_stack(&current, path, verbose)?;
+        }
+    }
+    deliver_selection(path, verbose)
+}
+
+// Put a selected directory at the front of history and count the visit,
+// unless history is off
+fn record_selection(path: &Path, verbose: bool) -> io::Result<()> {
+    if selection_options().no_history {
+        debug_print(verbose, "Not recording history (--no-history)");
+    } else if history_max() == 0 {
+        debug_print(verbose, "Not recording history (history size is 0)");
+    } else {
+        add_to_history(path.to_path_buf(), history_max(), verbose)?;
+        record_visit(path, verbose)?;
+    }
+    Ok(())
+}
+
+// Hand the selection over according to the delivery mode
+fn deliver_selection(path: &Path, verbose: bool) -> io::Result<()> {
+    let options = selection_options();
+    match options.delivery {
+        Delivery::TargetFile => {
+            // The environment variable, when set, wins over the config file
+            let relative_target = match std::env::var_os("CHANGEDIR_RELATIVE_TARGET") {
+                Some(value) => value != "0",
+                None => settings().relative_target == Some(true),
+            };
+            if relative_target && !options.absolute {
+                write_target_file(&display_path(path, verbose), verbose)
+            } else {
+                write_target_file(path, verbose)
+            }
+        }
+        Delivery::Stdout => {
+            println!("{}", display_path(path, verbose).display());
+            Ok(())
+        }
+        Delivery::Eval => {
+            println!("cd -- {}", shell_quote(&display_path(path, verbose).to_string_lossy()));
+            Ok(())
+        }
+        Delivery::Copy => copy_selection(path, verbose),
+        Delivery::Open => open_selection(path, verbose),
+    }
+}
+
+fn open_selection(path: &Path, verbose: bool) -> io::Result<()> {
+    let absolute = canonical_or_literal(path);
+    debug_print(verbose, &format!("Opening in the file manager: {}", absolute.display()));
+    match opener::open(&absolute, verbose) {
+        Ok(_) => {
+            println!("{}", tr(Msg::OpenedDirectory, &[&absolute.display()]).green());
+            Ok(())
+        }
+        Err(tried) => fail(1, tr(Msg::OpenFailed, &[&absolute.display(), &tried.join(", ")]).red()),
+    }
+}
+
+fn copy_selection(path: &Path, verbose: bool) -> io::Result<()> {
+    let absolute = canonical_or_literal(path);
+    let text = absolute.to_string_lossy();
+    // The environment variable, when set, wins over the config file
+    let allow_osc52 = match std::env::var_os("CHANGEDIR_OSC52") {
+        Some(value) => value != "0",
+        None => settings().osc52 == Some(true),
+    };
+    debug_print(verbose, &format!("Copying to clipboard: {}", text));
+    match clipboard::copy(&text, allow_osc52, verbose) {
+        Ok(_) => {
+            println!("{}", tr(Msg::CopiedToClipboard, &[&text]).green());
+            Ok(())
+        }
+        Err(tried) => fail(1, tr(Msg::ClipboardFailed, &[&tried.join(", ")]).red()),
+    }
+}
+
+// Single-quote a string for POSIX shells
+fn shell_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "'\\''"))
+}
+
+// The directory the shell thinks it is in. Shells resolve `..` against $PWD
+// rather than the physical path, so use it whenever it names the same place.
+fn logical_cwd() -> io::Result<PathBuf> {
+    let physical = std::env::current_dir()?;
+    if let Some(pwd) = std::env::var_os("PWD").map(PathBuf::from) {
+        if pwd.is_absolute() && fs::canonicalize(&pwd).ok() == fs::canonicalize(&physical).ok() {
+            return Ok(pwd);
+        }
+    }
+    Ok(physical)
+}
+
+// Path from `base` to `target`, both absolute, using ".." where needed
+fn relative_path(base: &Path, target: &Path) -> PathBuf {
+    let base: Vec<_> = base.components().collect();
+    let target: Vec<_> = target.components().collect();
+    let common = base.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+    let mut relative = PathBuf::new();
+    for _ in common..base.len() {
+        relative.push("..");
+    }
+    for component in &target[common..] {
+        relative.push(component);
+    }
+    if relative.as_os_str().is_empty() {
+        relative.push(".");
+    }
+    relative
+}
+
+#[cfg(unix)]
+fn same_filesystem(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn same_filesystem(_a: &Path, _b: &Path) -> bool {
+    true
+}
+
+// The form of a selected path to emit: relative to the current directory when
+// that is shorter and stays on the same filesystem, otherwise absolute
+fn display_path(path: &Path, verbose: bool) -> PathBuf {
+    if selection_options().absolute || !path.is_absolute() {
+        return path.to_path_buf();
+    }
+    let Ok(cwd) = logical_cwd() else {
+        return path.to_path_buf();
+    };
+    if !same_filesystem(&cwd, path) {
+        debug_print(verbose, "Destination is on another filesystem, using absolute path");
+        return path.to_path_buf();
+    }
+
+    let relative = relative_path(&cwd, path);
+    if relative.as_os_str().len() < path.as_os_str().len() {
+        debug_print(verbose, &format!("Using relative path: {}", relative.display()));
+        relative
+    } else {
+        path.to_path_buf()
+    }
+}
+
+// The bookmarks and history shown by the listing and the choosers. With a
+// tag filter only the matching bookmarks are shown, lettered from [0], and
+// history (which has no tags) is left out.
+fn listed_entries(verbose: bool) -> (Vec<Bookmark>, Vec<PathBuf>) {
+    let mut bookmarks = load_bookmarks(verbose);
+    if let Some(order) = selection_options().bookmark_order {
+        sort_bookmarks(&mut bookmarks, order, verbose);
+    }
+    let tags = &selection_options().tag_filter;
+    if tags.is_empty() {
+        let history = displayed_history(&bookmarks, verbose);
+        return (bookmarks, history);
+    }
+    debug_print(verbose, &format!("Filtering bookmarks by tags: {:?}", tags));
+    let tagged = bookmarks
+        .into_iter()
+        .filter(|b| tags.iter().all(|tag| b.tags.contains(tag)))
+        .collect();
+    (tagged, Vec::new())
+}
+
+// Entries per page of the combined list: one for each letter
+const PAGE_SIZE: usize = SLOT_COUNT;
+
+// The bookmarks and history on `page` of the combined list. Letters start
+// again from [0] on every page.
+fn page_entries<'a>(bookmarks: &'a [Bookmark], history: &'a [PathBuf], page: usize) -> (&'a [Bookmark], &'a [PathBuf]) {
+    let start = page * PAGE_SIZE;
+    let end = start + PAGE_SIZE;
+    let clamp = |index: usize, len: usize| index.min(len);
+    let page_bookmarks = &bookmarks[clamp(start, bookmarks.len())..clamp(end, bookmarks.len())];
+    let history_start = clamp(start.saturating_sub(bookmarks.len()), history.len());
+    let history_end = clamp(end.saturating_sub(bookmarks.len()), history.len());
+    (page_bookmarks, &history[history_start..history_end])
+}
+
+fn sort_bookmarks(bookmarks: &mut [Bookmark], order: BookmarkOrder, verbose: bool) {
+    debug_print(verbose, &format!("Ordering bookmarks by {:?}", order));
+    match order {
+        BookmarkOrder::Name => bookmarks.sort_by_key(|b| {
+            let name = b.path.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+            (name, b.path.clone())
+        }),
+        BookmarkOrder::Path => bookmarks.sort_by(|a, b| a.path.cmp(&b.path)),
+        BookmarkOrder::Recent => {
+            let visits = load_visits(verbose);
+            let last_visit = |bookmark: &Bookmark| {
+                visits
+                    .iter()
+                    .find(|v| same_directory(&v.path, &bookmark.path))
+                    .map(|v| v.last)
+            };
+            // Stable, so unvisited bookmarks keep their stored order
+            bookmarks.sort_by_key(|b| std::cmp::Reverse(last_visit(b)));
+        }
+    }
+}
+
+// Store the bookmarks in the --sort order, then list them
+fn persist_bookmark_order(verbose: bool) -> io::Result<()> {
+    let mut bookmarks = load_bookmarks(verbose);
+    if let Some(order) = selection_options().bookmark_order {
+        sort_bookmarks(&mut bookmarks, order, verbose);
+        save_bookmarks(&bookmarks, verbose)?;
+    }
+    list_bookmarks(false, true, verbose)
+}
+
+fn list_bookmarks(grouped: bool, no_pager: bool, verbose: bool) -> io::Result<()> {
+    debug_print(verbose, "Listing bookmarks and history");
+    let (bookmarks, filtered_history) = listed_entries(verbose);
+    
+    let total_items = bookmarks.len() + filtered_history.len();
+    
+    if total_items == 0 {
+        println!("{}", tr(Msg::NoBookmarks, &[]).yellow());
+        return Ok(());
+    }
+
+    save_listing_snapshot(&bookmarks, &filtered_history, verbose);
+    let (page_bookmarks, page_history) = page_entries(&bookmarks, &filtered_history, 0);
+    let mut lines = render_directory_list(page_bookmarks, page_history, grouped, verbose);
+    if total_items > PAGE_SIZE {
+        lines.push(String::new());
+        lines.push(tr(Msg::EntriesHidden, &[&(total_items - PAGE_SIZE), &PAGE_SIZE]).yellow().to_string());
+    }
+    print_listing(&lines, no_pager, verbose)
+}
+
+// The listing as a JSON array for scripts: the same entries and letters as
+// the chooser, without any colour
+fn list_bookmarks_json(verbose: bool) -> io::Result<()> {
+    debug_print(verbose, "Listing bookmarks and history as JSON");
+    let (bookmarks, filtered_history) = listed_entries(verbose);
+    save_listing_snapshot(&bookmarks, &filtered_history, verbose);
+
+    let bookmark_items = bookmarks.iter().map(|b| (&b.path, Some(b), "bookmark"));
+    let history_items = filtered_history.iter().map(|p| (p, None, "history"));
+    // Entries past the last letter are included too, with a null slot
+    let items: Vec<String> = bookmark_items
+        .chain(history_items)
+        .enumerate()
+        .map(|(index, (path, bookmark, kind))| {
+            let slot = if index < SLOT_COUNT { json::string(&get_prefix_char(index).to_string()) } else { "null".to_string() };
+            let mut fields = vec![("slot", slot)];
+            fields.extend(path_json_fields(path));
+            fields.push(("kind", json::string(kind)));
+            fields.push(("exists", (!known_missing(path)).to_string()));
+            if let Some(label) = bookmark.and_then(|b| b.label.as_deref()) {
+                fields.push(("label", json::string(label)));
+            }
+            if let Some(bookmark) = bookmark.filter(|b| !b.tags.is_empty()) {
+                fields.push(("tags", json::string_array(&bookmark.tags)));
+            }
+            if let Some(note) = bookmark.and_then(|b| b.note.as_deref()) {
+                fields.push(("note", json::string(note)));
+            }
+            json::object(&fields)
+        })
+        .collect();
+
+    println!("{}", json::array(&items));
+    Ok(())
+}
+
+// "<slot>\t<kind>\t<path>" for every entry, in listing order. The slot is
+// "-" past the last letter. The path comes last and is written as raw bytes,
+// so it survives tabs and invalid UTF-8 in directory names.
+fn list_bookmarks_porcelain(verbose: bool) -> io::Result<()> {
+    debug_print(verbose, "Listing bookmarks and history in porcelain format");
+    let (bookmarks, filtered_history) = listed_entries(verbose);
+    save_listing_snapshot(&bookmarks, &filtered_history, verbose);
+
+    let bookmark_items = bookmarks.iter().map(|b| (&b.path, "bookmark"));
+    let history_items = filtered_history.iter().map(|p| (p, "history"));
+    let mut stdout = io::stdout().lock();
+    for (index, (path, kind)) in bookmark_items.chain(history_items).enumerate() {
+        let slot = if index < SLOT_COUNT { get_prefix_char(index) } else { '-' };
+        write!(stdout, "{}\t{}\t", slot, kind)?;
+        stdout.write_all(&path_bytes(path))?;
+        writeln!(stdout)?;
+    }
+    stdout.flush()
+}
+
+// One line of a listing: its position (which gives the letter), the path and
+// any dimmed annotation after it
+struct ListEntry<'a> {
+    index: usize,
+    path: &'a Path,
+    label: Option<&'a str>,
+    note: String,
+    description: Option<&'a str>,
+    // The directory is known not to exist any more
+    missing: bool,
+}
+
+// Unlike is_missing, quiet about paths that can't be checked. A relative
+// bookmark that doesn't resolve from here counts as missing.
+fn known_missing(path: &Path) -> bool {
+    match resolve_bookmark_path(path) {
+        Some(path) => matches!(path.try_exists(), Ok(false)),
+        None => true,
+    }
+}
+
+// Relative bookmarks ("./crates/parser", made with --relative-to) resolve
+// against $CHANGEDIR_BASE if it is set, and otherwise against the nearest of
+// the current directory and its parents that contains them
+fn resolve_bookmark_path(path: &Path) -> Option<PathBuf> {
+    if path.is_absolute() {
+        return Some(path.to_path_buf());
+    }
+    let relative = path.strip_prefix(".").unwrap_or(path);
+    if let Some(base) = std::env::var_os("CHANGEDIR_BASE").filter(|v| !v.is_empty()) {
+        return Some(PathBuf::from(base).join(relative));
+    }
+    let cwd = std::env::current_dir().ok()?;
+    cwd.ancestors().map(|dir| dir.join(relative)).find(|candidate| candidate.is_dir())
+}
+
+// Render bookmarks followed by history with continuous prefix letters, as
+// shown by --list and the interactive chooser. With `grouped`, entries that
+// share a parent directory are shown beneath it (display only; letters and
+// order of selection are unchanged).
+fn render_directory_list(bookmarks: &[Bookmark], filtered_history: &[PathBuf], grouped: bool, verbose: bool) -> Vec<String> {
+    let render = |entries: &[ListEntry]| {
+        if grouped {
+            render_grouped_section(entries)
+        } else {
+            entries.iter().map(|e| format_entry(e, &e.path.display().to_string(), 0)).collect()
+        }
+    };
+    let mut lines = Vec::new();
+
+    if !bookmarks.is_empty() {
+        debug_print(verbose, &format!("Displaying {} bookmarks", bookmarks.len()));
+        let now = now_secs();
+        let entries: Vec<ListEntry> = bookmarks
+            .iter()
+            .enumerate()
+            .map(|(i, bookmark)| ListEntry {
+                index: i,
+                path: &bookmark.path,
+                label: bookmark.label.as_deref(),
+                note: bookmark
+                    .tags
+                    .iter()
+                    .map(|tag| format!("#{}", tag))
+                    .chain(bookmark.expires.map(|expires| tr(Msg::ExpiresIn, &[&format_duration(expires.saturating_sub(now))])))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+                description: bookmark.note.as_deref(),
+                missing: known_missing(&bookmark.path),
+            })
+            .collect();
+        lines.extend(render(&entries));
+    }
+
+    // History directories continue the numbering (duplicates already filtered out)
+    if !filtered_history.is_empty() {
+        // Add blank line between bookmarks and history if both exist
+        if !bookmarks.is_empty() {
+            lines.push(String::new());
+        }
+        
+        debug_print(verbose, &format!("Displaying {} history entries (after filtering duplicates)", filtered_history.len()));
+        let start_index = bookmarks.len();
+        let entries: Vec<ListEntry> = filtered_history
+            .iter()
+            .enumerate()
+            .map(|(i, hist_dir)| ListEntry {
+                index: start_index + i,
+                path: hist_dir,
+                label: None,
+                note: String::new(),
+                description: None,
+                missing: known_missing(hist_dir),
+            })
+            // Only show entries that have a letter
+            .filter(|entry| entry.index < SLOT_COUNT)
+            .collect();
+        lines.extend(render(&entries));
+    }
+
+    lines
+}
+
+fn format_entry(entry: &ListEntry, shown_path: &str, indent: usize) -> String {
+    let mut line = format!("{}{} ", " ".repeat(indent), format!("[{}]", get_prefix_char(entry.index)).bright_cyan().bold());
+    // Visible width so far, for fitting the description on the line
+    let mut width = indent + 4;
+    if let Some(label) = entry.label {
+        line.push_str(&format!("{} {} ", label.bright_yellow().bold(), "→".dimmed()));
+        width += label.chars().count() + 3;
+    }
+    if entry.missing {
+        let suffix = tr(Msg::MissingSuffix, &[]);
+        line.push_str(&format!("{} {}", shown_path.red(), suffix.red()));
+        width += shown_path.chars().count() + suffix.chars().count() + 1;
+    } else {
+        line.push_str(&shown_path.bright_white().to_string());
+        width += shown_path.chars().count();
+    }
+    if !entry.note.is_empty() {
+        line.push_str(&format!(" {}", entry.note.dimmed()));
+        width += entry.note.chars().count() + 1;
+    }
+    if let Some(description) = entry.description {
+        let available = terminal_width().map(|columns| columns.saturating_sub(width + 1));
+        let description = truncate_text(description, available);
+        if !description.is_empty() {
+            line.push_str(&format!(" {}", description.dimmed()));
+        }
+    }
+    line
+}
+
+// Columns of the terminal stdout is connected to
+fn terminal_width() -> Option<usize> {
+    if !io::stdout().is_terminal() {
+        return None;
+    }
+    crossterm::terminal::size().ok().map(|(columns, _)| columns as usize)
+}
+
+// `text` shortened to `max` characters (with an ellipsis), if there is a limit
+fn truncate_text(text: &str, max: Option<usize>) -> String {
+    match max {
+        Some(max) if text.chars().count() > max => {
+            if max == 0 {
+                return String::new();
+            }
+            let mut short: String = text.chars().take(max - 1).collect();
+            short.push('…');
+            short
+        }
+        _ => text.to_string(),
+    }
+}
+
+// Group each entry under the deepest ancestor (other than the root) that it
+// shares with another entry of the section. The shared prefix is printed once
+// where the group's first member would have been, with the members' remaining
+// path components indented beneath it; entries sharing nothing stay as they are.
+fn render_grouped_section(entries: &[ListEntry]) -> Vec<String> {
+    let group_of = |entry: &ListEntry| -> Option<PathBuf> {
+        entry
+            .path
+            .ancestors()
+            .skip(1)
+            .filter(|ancestor| ancestor.parent().is_some())
+            .find(|ancestor| {
+                entries
+                    .iter()
+                    .any(|other| other.index != entry.index && other.path.starts_with(ancestor))
+            })
+            .map(Path::to_path_buf)
+    };
+    let groups: Vec<Option<PathBuf>> = entries.iter().map(group_of).collect();
+
+    let mut lines = Vec::new();
+    let mut printed: Vec<&PathBuf> = Vec::new();
+    for (entry, group) in entries.iter().zip(&groups) {
+        let Some(group) = group else {
+            lines.push(format_entry(entry, &entry.path.display().to_string(), 0));
+            continue;
+        };
+        if printed.contains(&group) {
+            continue;
+        }
+        printed.push(group);
+
+        let members: Vec<&ListEntry> = entries
+            .iter()
+            .zip(&groups)
+            .filter(|(_, g)| g.as_ref() == Some(group))
+            .map(|(e, _)| e)
+            .collect();
+        if members.len() == 1 {
+            lines.push(format_entry(entry, &entry.path.display().to_string(), 0));
+            continue;
+        }
+
+        lines.push(format!("{}{}", group.display(), std::path::MAIN_SEPARATOR).bright_white().bold().to_string());
+        for member in members {
+            let suffix = member.path.strip_prefix(group).unwrap_or(member.path);
+            lines.push(format_entry(member, &suffix.display().to_string(), 2));
+        }
+    }
+    lines
+}
+
+// The letters that select `count` entries, for the prompt
+fn slot_range(count: usize) -> &'static str {
+    if count > 36 {
+        "0-9, a-z, A-Z"
+    } else {
+        "0-9, a-z"
+    }
+}
+
+// Show a selection menu and read the user's choice. With a terminal on stdin
+// the menu and prompt go to stdout as usual; when stdin is piped they go to
+// stderr so scripts get a clean stdout, and exactly one whitespace-separated
+// token is read. Running out of input counts as a cancel.
+fn prompt_selection(lines: &[String], count: usize, verbose: bool) -> io::Result<String> {
+    let interactive = io::stdin().is_terminal();
+    debug_print(verbose, &format!("Reading selection ({} stdin)", if interactive { "terminal" } else { "piped" }));
+
+    let prompt = tr(Msg::SelectPrompt, &[&slot_range(count)]).bright_yellow();
+    if interactive {
+        let mut stdout = io::stdout();
+        for line in lines {
+            writeln!(stdout, "{}", line)?;
+        }
+        write!(stdout, "{}", prompt)?;
+        stdout.flush()?;
+    } else {
+        let mut stderr = io::stderr();
+        for line in lines {
+            writeln!(stderr, "{}", line)?;
+        }
+        write!(stderr, "{}", prompt)?;
+        stderr.flush()?;
+    }
+
+    let mut input = String::new();
+    let read = io::stdin().read_line(&mut input)?;
+    debug_print(verbose, &format!("User input: '{}'", input.trim()));
+
+    if read == 0 {
+        debug_print(verbose, "End of input, treating as cancel");
+        if !interactive {
+            eprintln!();
+        }
+        std::process::exit(EXIT_CANCELLED);
+    }
+    if !interactive {
+        eprintln!();
+    }
+
+    Ok(input.split_whitespace().next().unwrap_or("").to_string())
+}
+
+// Keeps the terminal in raw mode for as long as it is alive
+struct RawMode;
+
+impl RawMode {
+    fn enable() -> Option<RawMode> {
+        crossterm::terminal::enable_raw_mode().ok().map(|_| RawMode)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        let _ = crossterm::terminal::disable_raw_mode();
+    }
+}
+
+// Columns `line` takes up on screen, not counting colour escape sequences
+fn visible_width(line: &str) -> usize {
+    let mut width = 0;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+// The lines under a page of a paged letter prompt saying where it is and
+// how to get to the other pages; none if everything fits on one page
+fn page_footer(page: usize, total: usize) -> Vec<String> {
+    let pages = total.div_ceil(PAGE_SIZE);
+    if pages <= 1 {
+        return Vec::new();
+    }
+    let mut lines = vec![String::new(), tr(Msg::PageOf, &[&(page + 1), &pages]).dimmed().to_string()];
+    if page + 1 < pages {
+        let remaining = total - (page + 1) * PAGE_SIZE;
+        lines.push(format!("{} {}", "[>]".bright_cyan().bold(), tr(Msg::NextPage, &[&remaining])));
+    }
+    if page > 0 {
+        lines.push(format!("{} {}", "[<]".bright_cyan().bold(), tr(Msg::PreviousPage, &[])));
+    }
+    lines
+}
+
+// One entry of the picker: the line shown for it, the text the filter is
+// matched against, its slot letter if it has one, and where choosing it goes
+struct PickItem<'a> {
+    line: String,
+    text: String,
+    slot: Option<char>,
+    path: &'a Path,
+}
+
+// What a key press does in the picker. Plain letters always go to the filter,
+// so moving takes the arrows or Ctrl-P/Ctrl-N (Ctrl-K/Ctrl-J)
+#[derive(Debug, PartialEq)]
+enum PickKey {
+    Up,
+    Down,
+    First,
+    Last,
+    Select,
+    Cancel,
+    Erase,
+    Type(char),
+    Ignore,
+}
+
+fn pick_key(code: crossterm::event::KeyCode, modifiers: crossterm::event::KeyModifiers) -> PickKey {
+    use crossterm::event::{KeyCode, KeyModifiers};
+
+    let control = modifiers.contains(KeyModifiers::CONTROL);
+    match code {
+        KeyCode::Up => PickKey::Up,
+        KeyCode::Char('k' | 'p') if control => PickKey::Up,
+        KeyCode::Down => PickKey::Down,
+        KeyCode::Char('j' | 'n') if control => PickKey::Down,
+        KeyCode::Home => PickKey::First,
+        KeyCode::End => PickKey::Last,
+        KeyCode::Enter => PickKey::Select,
+        KeyCode::Esc => PickKey::Cancel,
+        // Raw mode turns these into key presses instead of signals
+        KeyCode::Char('c' | 'd') if control => PickKey::Cancel,
+        KeyCode::Backspace => PickKey::Erase,
+        KeyCode::Char(c) if !control => PickKey::Type(c),
+        _ => PickKey::Ignore,
+    }
+}
+
+// The entry Enter picks: the one whose slot letter is the whole query, when
+// exactly one has it, so a letter and Enter does what the letter does at the
+// prompt; otherwise the highlighted one
+fn picked_entry(items: &[PickItem], shown: &[usize], selected: usize, query: &str) -> Option<usize> {
+    let mut chars = query.chars();
+    if let (Some(letter), None) = (chars.next(), chars.next()) {
+        let mut slotted = items.iter().enumerate().filter(|(_, item)| item.slot == Some(letter));
+        if let (Some((index, _)), None) = (slotted.next(), slotted.next()) {
+            return Some(index);
+        }
+    }
+    shown.get(selected).copied()
+}
+
+// Let the user pick one of `items` in a terminal: the arrow keys move a
+// highlight, typing narrows the list with a fuzzy filter and Enter picks the
+// highlighted entry, or the entry whose slot letter was typed. Esc cancels.
+// None if this is not a terminal or it won't go into raw mode, so the caller
+// can use the letter prompt instead.
+fn pick(items: &[PickItem], initial: usize, verbose: bool) -> io::Result<Option<PathBuf>> {
+    use crossterm::event::{self, Event, KeyEventKind};
+    use crossterm::{cursor, queue, terminal};
+
+    if items.is_empty() || !io::stdin().is_terminal() || !io::stderr().is_terminal() {
+        return Ok(None);
+    }
+    let Some(raw_mode) = RawMode::enable() else {
+        debug_print(verbose, "Terminal does not support raw mode, using the plain prompt");
+        return Ok(None);
+    };
+    let (columns, height) = terminal::size().map(|(c, r)| (c as usize, r as usize)).unwrap_or((80, 24));
+    let columns = columns.max(1);
+    let rows_of = |width: usize| width.div_ceil(columns).max(1);
+    let preview = selection_options().preview;
+    // Rows left for entries once the help line, the prompt and a preview fit
+    let window = height.saturating_sub(2 + if preview { PREVIEW_DIRS + 1 } else { 0 }).max(1);
+    let prompt = tr(Msg::FilterPrompt, &[]);
+    let help = tr(Msg::MenuHelp, &[]);
+
+    let mut stderr = io::stderr();
+    let mut query = String::new();
+    // Indices into `items` that match the query, best first
+    let mut shown: Vec<usize> = (0..items.len()).collect();
+    let mut selected = initial.min(items.len() - 1);
+    let mut top = 0;
+    // Screen rows above the cursor from the last redraw
+    let mut drawn = 0;
+    let chosen = loop {
+        if selected < top {
+            top = selected;
+        } else if selected >= top + window {
+            top = selected + 1 - window;
+        }
+        if drawn > 0 {
+            queue!(stderr, cursor::MoveUp(drawn as u16))?;
+        }
+        queue!(stderr, cursor::MoveToColumn(0), terminal::Clear(terminal::ClearType::FromCursorDown))?;
+        drawn = 0;
+        for (position, &index) in shown.iter().enumerate().skip(top).take(window) {
+            let line = &items[index].line;
+            let marker = if position == selected { "❯ ".bright_yellow().bold().to_string() } else { "  ".to_string() };
+            write!(stderr, "{}{}\r\n", marker, line)?;
+            drawn += rows_of(visible_width(line) + 2);
+            if preview && position == selected {
+                for preview in preview_lines(items[index].path) {
+                    write!(stderr, "{}\r\n", preview.dimmed())?;
+                    drawn += rows_of(visible_width(&preview));
+                }
+            }
+        }
+        let status = format!("{}/{}  {}", shown.len(), items.len(), help);
+        write!(stderr, "{}\r\n", status.dimmed())?;
+        drawn += rows_of(visible_width(&status));
+        write!(stderr, "{}{}", prompt.bright_yellow(), query)?;
+        drawn += rows_of(visible_width(&prompt) + query.chars().count()) - 1;
+        stderr.flush()?;
+
+        let Event::Key(key) = event::read()? else {
+            continue;
+        };
+        if key.kind != KeyEventKind::Press {
+            continue;
+        }
+        let count = shown.len().max(1);
+        match pick_key(key.code, key.modifiers) {
+            PickKey::Up => selected = selected.checked_sub(1).unwrap_or(count - 1),
+            PickKey::Down => selected = (selected + 1) % count,
+            PickKey::First => selected = 0,
+            PickKey::Last => selected = count - 1,
+            PickKey::Select => {
+                if let Some(index) = picked_entry(items, &shown, selected, &query) {
+                    break index;
+                }
+            }
+            PickKey::Cancel => {
+                drop(raw_mode);
+                eprintln!();
+                debug_print(verbose, "Picker cancelled");
+                std::process::exit(EXIT_CANCELLED);
+            }
+            PickKey::Erase if !query.is_empty() => {
+                query.pop();
+                shown = filter_items(items, &query);
+                selected = 0;
+            }
+            PickKey::Type(c) => {
+                query.push(c);
+                shown = filter_items(items, &query);
+                selected = 0;
+            }
+            PickKey::Erase | PickKey::Ignore => {}
+        }
+    };
+    drop(raw_mode);
+    eprintln!();
+
+    let item = &items[chosen];
+    debug_print(verbose, &format!("Picked {} (filter '{}')", item.path.display(), query));
+    Ok(Some(item.path.to_path_buf()))
+}
+
+// Positions of the items matching `query`, best match first (ties keep the
+// list order)
+fn filter_items(items: &[PickItem], query: &str) -> Vec<usize> {
+    if query.is_empty() {
+        return (0..items.len()).collect();
+    }
+    let mut scored: Vec<(usize, i64)> = items
+        .iter()
+        .enumerate()
+        .filter_map(|(index, item)| fuzzy_score(query, &item.text).map(|score| (index, score)))
+        .collect();
+    scored.sort_by_key(|&(_, score)| std::cmp::Reverse(score));
+    scored.into_iter().map(|(index, _)| index).collect()
+}
+
+const PREVIEW_DIRS: usize = 5;
+
+// Where a directory named `name` goes among its siblings when they are shown:
+// by name ignoring case (exact spelling only breaks ties), with the
+// dot-directories after the rest
+fn subdirectory_sort_key(name: &str) -> (bool, String, String) {
+    (name.starts_with('.'), name.to_lowercase(), name.to_string())
+}
+
+fn sort_subdirectories(dirs: &mut [PathBuf]) {
+    dirs.sort_by_cached_key(|dir| subdirectory_sort_key(&dir.file_name().unwrap_or_default().to_string_lossy()));
+}
+
+// Up to PREVIEW_DIRS names of the directories in `path`, indented to go
+// under its menu entry
+fn preview_lines(path: &Path) -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(path)
+        .map(|entries| {
+            entries
+                .flatten()
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| !name.starts_with('.'))
+                .collect()
+        })
+        .unwrap_or_default();
+    if names.is_empty() {
+        return vec![format!("      {}", tr(Msg::NoSubdirectories, &[]))];
+    }
+    names.sort_by_cached_key(|name| subdirectory_sort_key(name));
+    let more = names.len() > PREVIEW_DIRS;
+    let mut lines: Vec<String> = names.iter().take(PREVIEW_DIRS).map(|name| format!("      {}/", name)).collect();
+    if more {
+        lines.push("      …".to_string());
+    }
+    lines
+}
+
+// Print the lines of a pure listing command, paging them through $PAGER when
+// stdout is a terminal and the output would not fit on one screen. Never use
+// this for anything that prompts afterwards.
+fn print_listing(lines: &[String], no_pager: bool, verbose: bool) -> io::Result<()> {
+    let mut output = lines.join("\n");
+    output.push('\n');
+
+    if !no_pager && io::stdout().is_terminal() {
+        let rows = crossterm::terminal::size().map(|(_, rows)| rows as usize).unwrap_or(0);
+        debug_print(verbose, &format!("Listing has {} lines, terminal has {} rows", lines.len(), rows));
+        if rows > 0 && lines.len() >= rows {
+            match page_output(&output, verbose) {
+                Ok(()) => return Ok(()),
+                Err(e) => debug_print(verbose, &format!("Pager could not be started, printing directly: {}", e)),
+            }
+        }
+    }
+
+    let mut stdout = io::stdout();
+    stdout.write_all(output.as_bytes())?;
+    stdout.flush()
+}
+
+// Show `output` through the pager. Only a pager that can't be started is an
+// error, for the caller to print the output instead; one that started has
+// shown it, so it is always waited for, and Ctrl-C meanwhile is left to it.
+fn page_output(output: &str, verbose: bool) -> io::Result<()> {
+    let pager = std::env::var("PAGER")
+        .ok()
+        .filter(|p| !p.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+    debug_print(verbose, &format!("Paging output through: {}", pager));
+
+    let mut parts = pager.split_whitespace();
+    let program = parts.next().unwrap_or(DEFAULT_PAGER);
+    PAGER_RUNNING.store(true, Ordering::SeqCst);
+    let mut child = match std::process::Command::new(program)
+        .args(parts)
+        .stdin(std::process::Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            PAGER_RUNNING.store(false, Ordering::SeqCst);
+            return Err(e);
+        }
+    };
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The user quitting the pager early closes the pipe; that is not an error
+        match stdin.write_all(output.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => {
+                debug_print(verbose, &format!("Writing to the pager failed: {}", e))
+            }
+            _ => {}
+        }
+    }
+    if let Err(e) = child.wait() {
+        debug_print(verbose, &format!("Waiting for the pager failed: {}", e));
+    }
+    PAGER_RUNNING.store(false, Ordering::SeqCst);
+    Ok(())
+}
+
+fn get_prefix_char(index: usize) -> char {
+    if index < 10 {
+        (b'0' + index as u8) as char
+    } else if index < 36 {
+        (b'a' + (index - 10) as u8) as char
+    } else if index < SLOT_COUNT {
+        (b'A' + (index - 36) as u8) as char
+    } else {
+        '?'
+    }
+}
+
+fn get_index_from_char(ch: char) -> Option<usize> {
+    match ch {
+        '0'..='9' => Some(ch as usize - '0' as usize),
+        'a'..='z' => Some(10 + (ch as usize - 'a' as usize)),
+        'A'..='Z' => Some(36 + (ch as usize - 'A' as usize)),
+        _ => None,
+    }
+}
+
+// Expand a leading `~` and resolve relative paths against `cwd`
+fn expand_path(arg: &str, cwd: &Path) -> PathBuf {
+    let home = || dirs::home_dir().expect("Could not find home directory");
+    if arg == "~" {
+        home()
+    } else if let Some(rest) = arg.strip_prefix("~/") {
+        home().join(rest)
+    } else {
+        cwd.join(arg)
+    }
+}
+
+// `path` with symlinks and `..` resolved, or as given if that fails (for
+// example because the directory no longer exists)
+fn canonical_or_literal(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+// `path` in the form it is stored in bookmarks and history
+fn stored_path(path: &Path) -> PathBuf {
+    if selection_options().literal_paths {
+        // Still without any "." components
+        path.components().collect()
+    } else {
+        canonical_or_literal(path)
+    }
+}
+
+// `path` as written to the store. In portable mode a path below the home
+// directory is written as `~/...`, so a store synced between machines works
+// whatever the home directory is called on each.
+fn store_form(path: &Path) -> PathBuf {
+    if !selection_options().portable {
+        return path.to_path_buf();
+    }
+    match dirs::home_dir().and_then(|home| path.strip_prefix(home).ok().map(Path::to_path_buf)) {
+        Some(rest) if rest.as_os_str().is_empty() => PathBuf::from("~"),
+        Some(rest) => Path::new("~").join(rest),
+        None => path.to_path_buf(),
+    }
+}
+
+// A path read from the store, with a leading `~` turned back into this
+// machine's home directory. Done whatever the mode, so turning portable mode
+// off simply writes the full paths again on the next save.
+fn expand_home(path: PathBuf) -> PathBuf {
+    let Ok(rest) = path.strip_prefix("~") else {
+        return path;
+    };
+    match dirs::home_dir() {
+        Some(home) if rest.as_os_str().is_empty() => home,
+        Some(home) => home.join(rest),
+        None => path,
+    }
+}
+
+// Whether two stored paths name the same directory. Besides the canonical
+// forms, the directories themselves are compared, since on a case-insensitive
+// filesystem (as on macOS) `Foo` and `foo` canonicalize differently but are
+// one directory. Two different directories never share an identity, so
+// nothing is merged wrongly on a case-sensitive one.
+fn same_directory(a: &Path, b: &Path) -> bool {
+    a == b || canonical_or_literal(a) == canonical_or_literal(b) || same_file(a, b)
+}
+
+#[cfg(unix)]
+fn same_file(a: &Path, b: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+// Windows canonicalization already settles the case of each component
+#[cfg(not(unix))]
+fn same_file(_a: &Path, _b: &Path) -> bool {
+    false
+}
+
+// `dir` as "./<path below base>", for a bookmark that moves with the tree it
+// is in
+fn relative_bookmark_path(dir: &Path, base: &str) -> io::Result<PathBuf> {
+    let expanded = expand_path(base, &std::env::current_dir()?);
+    let base = match fs::canonicalize(&expanded) {
+        Ok(base) if base.is_dir() => base,
+        _ => fail(1, tr(Msg::DirectoryNotFound, &[&expanded.display()]).red()),
+    };
+    match dir.strip_prefix(&base) {
+        Ok(relative) => Ok(Path::new(".").join(relative)),
+        Err(_) => fail(1, tr(Msg::NotUnderBase, &[&dir.display(), &base.display()]).red()),
+    }
+}
+
+// Bookmark `path` (the current directory if None). Paths are stored
+// canonicalized so the same directory reached through a symlink or `..`
+// is not bookmarked twice.
+fn bookmark_directory(
+    path: Option<&str>,
+    temp: Option<&str>,
+    label: Option<&str>,
+    tags: &[String],
+    note: Option<&str>,
+    relative_to: Option<&str>,
+    verbose: bool,
+) -> io::Result<()> {
+    let note = note.and_then(clean_note);
+    if let Some(label) = label {
+        if !is_valid_label(label) {
+            fail(1, tr(Msg::InvalidLabel, &[&label]).red());
+        }
+    }
+    if let Some(tag) = tags.iter().find(|tag| !is_valid_tag(tag)) {
+        fail(1, tr(Msg::InvalidTag, &[&tag]).red());
+    }
+    let temp_secs = temp.map(|duration| match parse_duration(duration) {
+        Some(secs) => secs,
+        None => fail(1, tr(Msg::InvalidDuration, &[&duration]).red()),
+    });
+    let current_dir = match path {
+        Some(arg) => {
+            let expanded = expand_path(arg, &std::env::current_dir()?);
+            if !expanded.is_dir() {
+                fail(1, tr(Msg::DirectoryNotFound, &[&expanded.display()]).red());
+            }
+            stored_path(&expanded)
+        }
+        None => stored_path(&std::env::current_dir()?),
+    };
+    let current_dir = match relative_to {
+        Some(base) => relative_bookmark_path(&current_dir, base)?,
+        None => current_dir,
+    };
+    debug_print(verbose, &format!("Bookmarking directory: {}", current_dir.display()));
+    let mut bookmarks = load_bookmarks(verbose);
+
+    if let Some(label) = label {
+        if let Some(other) = find_bookmark_by_label(&bookmarks, label) {
+            if !same_directory(&other.path, &current_dir) {
+                fail(1, tr(Msg::LabelInUse, &[&label, &other.path.display()]).red());
+            }
+        }
+    }
+
+    // Naming, tagging or annotating an existing bookmark just updates it
+    if label.is_some() || !tags.is_empty() || note.is_some() {
+        if let Some(existing) = bookmarks.iter_mut().find(|b| same_directory(&b.path, &current_dir)) {
+            if note.is_some() {
+                existing.note = note.clone();
+            }
+            if let Some(label) = label {
+                debug_print(verbose, &format!("Labelling existing bookmark as '{}'", label));
+                existing.label = Some(label.to_string());
+            }
+            for tag in tags {
+                if !existing.tags.contains(tag) {
+                    existing.tags.push(tag.clone());
+                }
+            }
+            save_bookmarks(&bookmarks, verbose)?;
+            if let Some(label) = label {
+                println!("{}", tr(Msg::Labelled, &[&current_dir.display(), &label]).green());
+            }
+            if !tags.is_empty() {
+                println!("{}", tr(Msg::Tagged, &[&current_dir.display(), &tags.join(", ")]).green());
+            }
+            if note.is_some() {
+                println!("{}", tr(Msg::Annotated, &[&current_dir.display()]).green());
+            }
+            return Ok(());
+        }
+    }
+
+    if bookmarks.iter().any(|b| same_directory(&b.path, &current_dir)) {
+        debug_print(verbose, "Directory already bookmarked");
+        match path {
+            Some(_) => eprintln!("{}", tr(Msg::PathAlreadyBookmarked, &[&current_dir.display()]).yellow()),
+            None => eprintln!("{}", tr(Msg::AlreadyBookmarked, &[]).yellow()),
+        }
+        return Ok(());
+    }
+
+    debug_print(verbose, &format!("Current bookmark count: {}", bookmarks.len()));
+    if bookmarks.len() >= max_bookmarks() {
+        fail(1, tr(Msg::MaxBookmarksReached, &[&max_bookmarks()]).red().bold());
+    }
+
+    let mut bookmark = Bookmark::new(current_dir.clone());
+    bookmark.label = label.map(str::to_string);
+    bookmark.note = note;
+    for tag in tags {
+        if !bookmark.tags.contains(tag) {
+            bookmark.tags.push(tag.clone());
+        }
+    }
+    if let Some(secs) = temp_secs {
+        debug_print(verbose, &format!("Temporary bookmark, expires in {} seconds", secs));
+        bookmark.expires = Some(now_secs() + secs);
+    }
+
+    bookmarks.push(bookmark);
+    save_bookmarks(&bookmarks, verbose)?;
+    match temp {
+        Some(duration) => println!("{}", tr(Msg::BookmarkedTemp, &[&current_dir.display(), &duration]).green()),
+        None => println!("{}", tr(Msg::Bookmarked, &[&current_dir.display()]).green()),
+    }
+    Ok(())
+}
+
+// Make a bookmark permanent: clears any expiry and marks it pinned
+fn pin_bookmark(letter: Option<&str>, verbose: bool) -> io::Result<()> {
+    let mut bookmarks = load_bookmarks(verbose);
+
+    let index = match letter {
+        Some(letter) => {
+            debug_print(verbose, &format!("Pinning bookmark by letter: '{}'", letter));
+            let mut chars = letter.chars();
+            match (chars.next().and_then(get_index_from_char), chars.next()) {
+                (Some(index), None) if index < bookmarks.len() => lettered_order(&bookmarks, verbose)[index],
+                _ => fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidBookmarkLetter, &[&letter]).red()),
+            }
+        }
+        None => {
+            let current_dir = canonical_or_literal(&std::env::current_dir()?);
+            debug_print(verbose, &format!("Pinning current directory: {}", current_dir.display()));
+            match bookmarks.iter().position(|b| same_directory(&b.path, &current_dir)) {
+                Some(index) => index,
+                None => fail(1, tr(Msg::NotBookmarked, &[]).yellow()),
+            }
+        }
+    };
+
+    let bookmark = &mut bookmarks[index];
+    bookmark.expires = None;
+    bookmark.pinned = true;
+    let pinned_path = bookmark.path.clone();
+    save_bookmarks(&bookmarks, verbose)?;
+    println!("{}", tr(Msg::Pinned, &[&pinned_path.display()]).green());
+    Ok(())
+}
+
+fn forget_current(verbose: bool) -> io::Result<()> {
+    let current_dir = canonical_or_literal(&std::env::current_dir()?);
+    debug_print(verbose, &format!("Forgetting current directory: {}", current_dir.display()));
+    let mut bookmarks = load_bookmarks(verbose);
+
+    let initial_len = bookmarks.len();
+    bookmarks.retain(|b| !same_directory(&b.path, &current_dir));
+
+    if bookmarks.len() < initial_len {
+        debug_print(verbose, "Directory was bookmarked, removing it");
+        save_bookmarks(&bookmarks, verbose)?;
+        println!("{}", tr(Msg::RemovedBookmark, &[&current_dir.display()]).green());
+    } else {
+        debug_print(verbose, "Directory was not bookmarked");
+    }
+    Ok(())
+}
+
+// Forget the current directory if it is bookmarked, bookmark it otherwise
+fn toggle_bookmark(verbose: bool) -> io::Result<()> {
+    let current_dir = canonical_or_literal(&std::env::current_dir()?);
+    if load_bookmarks(verbose).iter().any(|b| same_directory(&b.path, &current_dir)) {
+        debug_print(verbose, "Toggle: directory is bookmarked, forgetting it");
+        forget_current(verbose)
+    } else {
+        debug_print(verbose, "Toggle: directory is not bookmarked, bookmarking it");
+        bookmark_directory(None, None, None, &[], None, None, verbose)
+    }
+}
+
+// The stored positions of the bookmarks in the order they are lettered: the
+// --sort order if one is given, as in the listing, otherwise as stored
+fn lettered_order(bookmarks: &[Bookmark], verbose: bool) -> Vec<usize> {
+    let Some(order) = selection_options().bookmark_order else {
+        return (0..bookmarks.len()).collect();
+    };
+    let mut sorted = bookmarks.to_vec();
+    sort_bookmarks(&mut sorted, order, verbose);
+    sorted
+        .iter()
+        .filter_map(|s| bookmarks.iter().position(|b| b.path == s.path))
+        .collect()
+}
+
+// The position of the bookmark shown at `letter` (or labelled `letter`).
+// Letters past the bookmarks belong to history entries, which get their own
+// error so it is clear why they cannot be used here.
+fn bookmark_index(bookmarks: &[Bookmark], letter: &str, verbose: bool) -> usize {
+    if letter.chars().count() > 1 {
+        return match bookmarks.iter().position(|b| b.label.as_deref() == Some(letter)) {
+            Some(index) => index,
+            None => fail(EXIT_INVALID_SELECTION, tr(Msg::UnknownLabel, &[&letter]).red()),
+        };
+    }
+    match letter.chars().next().and_then(get_index_from_char) {
+        Some(index) if index < bookmarks.len() => lettered_order(bookmarks, verbose)[index],
+        Some(index) if index < bookmarks.len() + displayed_history(bookmarks, verbose).len() => {
+            fail(EXIT_INVALID_SELECTION, tr(Msg::LetterIsHistory, &[&letter]).red())
+        }
+        _ => fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidBookmarkLetter, &[&letter]).red()),
+    }
+}
+
+// Bookmark the history entry at `letter` in the combined list. A letter (or
+// label) that already refers to a bookmark changes nothing.
+fn promote_history(letter: &str, verbose: bool) -> io::Result<()> {
+    let mut bookmarks = load_bookmarks(verbose);
+    let history = displayed_history(&bookmarks, verbose);
+    let index = if letter.chars().count() > 1 {
+        match bookmarks.iter().position(|b| b.label.as_deref() == Some(letter)) {
+            Some(index) => index,
+            None => fail(EXIT_INVALID_SELECTION, tr(Msg::UnknownLabel, &[&letter]).red()),
+        }
+    } else {
+        match letter.chars().next().and_then(get_index_from_char) {
+            Some(index) if index < bookmarks.len() => lettered_order(&bookmarks, verbose)[index],
+            Some(index) if index < bookmarks.len() + history.len() => index,
+            _ => fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidBookmarkLetter, &[&letter]).red()),
+        }
+    };
+
+    if index < bookmarks.len() {
+        println!("{}", tr(Msg::PathAlreadyBookmarked, &[&bookmarks[index].path.display()]).yellow());
+        return Ok(());
+    }
+    if bookmarks.len() >= max_bookmarks() {
+        fail(1, tr(Msg::MaxBookmarksReached, &[&max_bookmarks()]).red().bold());
+    }
+    let path = history[index - bookmarks.len()].clone();
+    debug_print(verbose, &format!("Promoting history entry {} to a bookmark", path.display()));
+    bookmarks.push(Bookmark::new(path.clone()));
+    save_bookmarks(&bookmarks, verbose)?;
+    println!("{}", tr(Msg::Bookmarked, &[&path.display()]).green());
+    Ok(())
+}
+
+// Move the bookmark at `from` to position `to`, shifting those in between. A
+// `to` past the whole list means the end; a history letter is rejected.
+fn move_bookmark(from: &str, to: &str, verbose: bool) -> io::Result<()> {
+    let mut bookmarks = load_bookmarks(verbose);
+    let from_index = bookmark_index(&bookmarks, from, verbose);
+    let shown = bookmarks.len() + displayed_history(&bookmarks, verbose).len();
+    let to_index = match to.chars().next().and_then(get_index_from_char) {
+        Some(index) if to.chars().count() == 1 && index >= shown => {
+            debug_print(verbose, &format!("Clamping destination {} to {}", index, bookmarks.len() - 1));
+            bookmarks.len() - 1
+        }
+        _ => bookmark_index(&bookmarks, to, verbose),
+    };
+    debug_print(verbose, &format!("Moving bookmark {} to {}", from_index, to_index));
+
+    if from_index != to_index {
+        let bookmark = bookmarks.remove(from_index);
+        bookmarks.insert(to_index, bookmark);
+        save_bookmarks(&bookmarks, verbose)?;
+    }
+    list_bookmarks(false, true, verbose)
+}
+
+// Set (or with empty text, clear) the note of the bookmark at `letter`
+fn annotate_bookmark(letter: &str, text: &str, verbose: bool) -> io::Result<()> {
+    let mut bookmarks = load_bookmarks(verbose);
+    let index = bookmark_index(&bookmarks, letter, verbose);
+    let bookmark = &mut bookmarks[index];
+    bookmark.note = clean_note(text);
+    debug_print(verbose, &format!("Note for {}: {:?}", bookmark.path.display(), bookmark.note));
+    let message = match bookmark.note {
+        Some(_) => tr(Msg::Annotated, &[&bookmark.path.display()]),
+        None => tr(Msg::NoteCleared, &[&bookmark.path.display()]),
+    };
+    save_bookmarks(&bookmarks, verbose)?;
+    println!("{}", message.green());
+    Ok(())
+}
+
+fn remove_bookmark(letter: &str, verbose: bool) -> io::Result<()> {
+    debug_print(verbose, &format!("Removing bookmark: '{}'", letter));
+    let mut bookmarks = load_bookmarks(verbose);
+    let index = bookmark_index(&bookmarks, letter, verbose);
+    let removed = bookmarks.remove(index);
+    save_bookmarks(&bookmarks, verbose)?;
+    println!("{}", tr(Msg::RemovedBookmark, &[&removed.path.display()]).green());
+    Ok(())
+}
+
+// Whether `path` is known not to exist. A path whose existence can't be
+// checked (an unreachable network mount, say) is not treated as missing.
+fn is_missing(path: &Path, verbose: bool) -> bool {
+    // Whether a relative bookmark exists depends on where it is used from
+    let Some(path) = resolve_bookmark_path(path) else {
+        debug_print(verbose, &format!("Keeping relative bookmark that does not resolve here: {}", path.display()));
+        return false;
+    };
+    let path = path.as_path();
+    match path.try_exists() {
+        Ok(exists) => !exists,
+        Err(e) => {
+            debug_print(verbose, &format!("Cannot check {}: {}", path.display(), e));
+            eprintln!("{}", tr(Msg::SkippedUncheckable, &[&path.display(), &e]).yellow());
+            false
+        }
+    }
+}
+
+// Drop bookmarks (and unless `bookmarks_only`, history entries) whose
+// directory no longer exists
+fn clean_missing(dry_run: bool, bookmarks_only: bool, verbose: bool) -> io::Result<()> {
+    let bookmarks = load_bookmarks(verbose);
+    let history = if bookmarks_only { Vec::new() } else { load_history(verbose) };
+
+    let (kept_bookmarks, missing_bookmarks): (Vec<Bookmark>, Vec<Bookmark>) = bookmarks
+        .into_iter()
+        .partition(|b| !is_missing(&b.path, verbose));
+    let (kept_history, missing_history): (Vec<PathBuf>, Vec<PathBuf>) = history
+        .into_iter()
+        .partition(|p| !is_missing(p, verbose));
+    debug_print(verbose, &format!("Missing: {} bookmarks, {} history entries", missing_bookmarks.len(), missing_history.len()));
+
+    for path in missing_bookmarks.iter().map(|b| &b.path).chain(&missing_history) {
+        println!("  {}", path.display().to_string().dimmed());
+    }
+
+    if dry_run {
+        let message = if bookmarks_only {
+            tr(Msg::PruneDryRun, &[&missing_bookmarks.len()])
+        } else {
+            tr(Msg::CleanDryRun, &[&missing_bookmarks.len(), &missing_history.len()])
+        };
+        println!("{}", message.yellow());
+        return Ok(());
+    }
+    if !missing_bookmarks.is_empty() {
+        save_bookmarks(&kept_bookmarks, verbose)?;
+    }
+    if !missing_history.is_empty() {
+        save_history(&kept_history, verbose)?;
+    }
+    let message = if bookmarks_only {
+        tr(Msg::PruneDone, &[&missing_bookmarks.len()])
+    } else {
+        tr(Msg::CleanDone, &[&missing_bookmarks.len(), &missing_history.len()])
+    };
+    println!("{}", message.green());
+    Ok(())
+}
+
+// Drop history entries last visited more than `max_age` seconds ago. Entries
+// from older versions have no visit time and are kept.
+fn prune_history(max_age: u64, dry_run: bool, verbose: bool) -> io::Result<()> {
+    let history = load_timed_history(verbose);
+    let untimed = history.iter().filter(|(_, visited)| visited.is_none()).count();
+    if !history.is_empty() && untimed == history.len() {
+        eprintln!("{}", tr(Msg::HistoryUntimed, &[]).yellow());
+        return Ok(());
+    }
+
+    let cutoff = now_secs().saturating_sub(max_age);
+    let (kept, old): (Vec<_>, Vec<_>) = history
+        .into_iter()
+        .partition(|(_, visited)| visited.is_none_or(|visited| visited >= cutoff));
+    debug_print(verbose, &format!("{} history entries older than {}s, {} without a time", old.len(), max_age, untimed));
+
+    for (path, _) in &old {
+        println!("  {}", path.display().to_string().dimmed());
+    }
+    if untimed > 0 {
+        eprintln!("{}", tr(Msg::UntimedKept, &[&untimed]).yellow());
+    }
+    if dry_run {
+        println!("{}", tr(Msg::PruneHistoryDryRun, &[&old.len()]).yellow());
+        return Ok(());
+    }
+    if !old.is_empty() {
+        save_timed_history(&kept, verbose)?;
+    }
+    println!("{}", tr(Msg::PruneHistoryDone, &[&old.len()]).green());
+    Ok(())
+}
+
+// Returns false if the user declined, so a combined --clear-history stops too
+fn forget_all(yes: bool, verbose: bool) -> io::Result<bool> {
+    debug_print(verbose, "Forgetting all bookmarks");
+    let mut store = load_store(verbose);
+    
+    if !store.bookmarks.is_empty() {
+        if !yes && !confirm(&tr(Msg::ConfirmForgetAll, &[&store.bookmarks.len()]), verbose)? {
+            println!("{}", tr(Msg::NothingRemoved, &[]).yellow());
+            return Ok(false);
+        }
+        store.bookmarks.clear();
+        save_store(&store, verbose)?;
+        println!("{}", tr(Msg::AllBookmarksRemoved, &[]).green());
+    } else {
+        debug_print(verbose, "No bookmarks stored");
+        println!("{}", tr(Msg::NoBookmarksToRemove, &[]).yellow());
+    }
+    Ok(true)
+}
+
+fn clear_history(verbose: bool) -> io::Result<()> {
+    debug_print(verbose, "Clearing history");
+    let mut store = load_store(verbose);
+
+    if !store.history.is_empty() {
+        store.history.clear();
+        save_store(&store, verbose)?;
+        println!("{}", tr(Msg::HistoryCleared, &[]).green());
+    } else {
+        debug_print(verbose, "No history stored");
+        println!("{}", tr(Msg::NoHistoryToClear, &[]).yellow());
+    }
+    Ok(())
+}
+
+// Ask a yes/no question on the terminal. Anything but "y"/"yes" declines,
+// and without a terminal on stdin the answer is always no.
+fn confirm(question: &str, verbose: bool) -> io::Result<bool> {
+    if !io::stdin().is_terminal() {
+        debug_print(verbose, "Stdin is not a terminal, declining confirmation");
+        return Ok(false);
+    }
+
+    print!("{}", question.bright_yellow());
+    io::stdout().flush()?;
+    let mut input = String::new();
+    io::stdin().read_line(&mut input)?;
+    let answer = input.trim().to_lowercase();
+    debug_print(verbose, &format!("Confirmation answer: '{}'", answer));
+    Ok(answer == "y" || answer == "yes" || answer == tr(Msg::YesAnswer, &[]))
+}
+
+fn list_stale_bookmarks(days: u64, prune: bool, yes: bool, verbose: bool) -> io::Result<()> {
+    debug_print(verbose, &format!("Looking for bookmarks not visited in {} days", days));
+    let bookmarks = load_bookmarks(verbose);
+    let visits = load_visits(verbose);
+    let cutoff = now_secs().saturating_sub(days.saturating_mul(24 * 60 * 60));
+
+    // Never-visited bookmarks first, then the longest unvisited
+    let mut stale: Vec<(usize, Option<u64>)> = bookmark_visits(&bookmarks, &visits)
+        .into_iter()
+        .map(|(i, visit)| (i, visit.map(|v| v.last)))
+        .filter(|(_, last)| last.is_none_or(|last| last < cutoff))
+        .collect();
+    stale.sort_by_key(|(_, last)| last.unwrap_or(0));
+
+    if stale.is_empty() {
+        println!("{}", tr(Msg::NoStaleBookmarks, &[&days]).green());
+        return Ok(());
+    }
+
+    let now = now_secs();
+    for (index, last) in &stale {
+        let bookmark = &bookmarks[*index];
+        let age = match last {
+            Some(last) => tr(Msg::LastVisited, &[&format_duration(now.saturating_sub(*last))]),
+            None => tr(Msg::NeverVisited, &[]),
+        };
+        let mut line = format!("{} {} {}",
+            format!("[{}]", get_prefix_char(*index)).bright_cyan().bold(),
+            bookmark.path.display().to_string().bright_white(),
+            age.dimmed()
+        );
+        if bookmark.pinned {
+            line.push_str(&format!(" {}", tr(Msg::PinnedTag, &[]).yellow()));
+        }
+        println!("{}", line);
+    }
+
+    // Pinned bookmarks are reported above but are never removed
+    let removable: Vec<usize> = stale
+        .iter()
+        .map(|(i, _)| *i)
+        .filter(|i| !bookmarks[*i].pinned)
+        .collect();
+    if removable.is_empty() {
+        return Ok(());
+    }
+
+    let to_remove: Vec<usize> = if prune {
+        if !yes && !confirm(&tr(Msg::ConfirmRemoveStale, &[&removable.len()]), verbose)? {
+            println!("{}", tr(Msg::NothingRemoved, &[]).yellow());
+            return Ok(());
+        }
+        removable
+    } else if io::stdin().is_terminal() {
+        print!("{}", tr(Msg::StaleRemovePrompt, &[]).bright_yellow());
+        io::stdout().flush()?;
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        debug_print(verbose, &format!("User input: '{}'", input.trim()));
+
+        let mut selected = Vec::new();
+        for token in input.split_whitespace() {
+            let mut chars = token.chars();
+            match (chars.next().and_then(get_index_from_char), chars.next()) {
+                (Some(index), None) if removable.contains(&index) => selected.push(index),
+                _ => fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidBookmarkLetter, &[&token]).red()),
+            }
+        }
+        selected
+    } else {
+        Vec::new()
+    };
+
+    if to_remove.is_empty() {
+        return Ok(());
+    }
+
+    remove_bookmarks_at(bookmarks, &to_remove, verbose)
+}
+
+// Remove the bookmarks at `indices` with a single save, reporting each one
+fn remove_bookmarks_at(mut bookmarks: Vec<Bookmark>, indices: &[usize], verbose: bool) -> io::Result<()> {
+    let removed: Vec<Bookmark> = indices.iter().map(|i| bookmarks[*i].clone()).collect();
+    let mut index = 0;
+    bookmarks.retain(|_| {
+        let keep = !indices.contains(&index);
+        index += 1;
+        keep
+    });
+    save_bookmarks(&bookmarks, verbose)?;
+    for bookmark in removed {
+        println!("{}", tr(Msg::RemovedBookmark, &[&bookmark.path.display()]).green());
+    }
+    Ok(())
+}
+
+// Show the chooser listing and remove several bookmarks at once. Invalid
+// letters re-prompt instead of exiting, so one typo does not lose the rest.
+fn prune_interactive(verbose: bool) -> io::Result<()> {
+    let bookmarks = load_bookmarks(verbose);
+    if bookmarks.is_empty() {
+        println!("{}", tr(Msg::NoBookmarksToRemove, &[]).yellow());
+        return Ok(());
+    }
+    let filtered_history = displayed_history(&bookmarks, verbose);
+    let history_end = (bookmarks.len() + filtered_history.len()).min(SLOT_COUNT);
+
+    for line in render_directory_list(&bookmarks, &filtered_history, false, verbose) {
+        println!("{}", line);
+    }
+
+    let selected = loop {
+        print!("{}", tr(Msg::StaleRemovePrompt, &[]).bright_yellow());
+        io::stdout().flush()?;
+        let mut input = String::new();
+        if io::stdin().read_line(&mut input)? == 0 {
+            debug_print(verbose, "End of input, treating as cancel");
+            println!();
+            std::process::exit(EXIT_CANCELLED);
+        }
+        debug_print(verbose, &format!("User input: '{}'", input.trim()));
+
+        let mut selected: Vec<usize> = Vec::new();
+        let mut error = None;
+        for token in input.split_whitespace() {
+            let mut chars = token.chars();
+            match (chars.next().and_then(get_index_from_char), chars.next()) {
+                (Some(index), None) if index < bookmarks.len() => {
+                    if !selected.contains(&index) {
+                        selected.push(index);
+                    }
+                }
+                (Some(index), None) if index < history_end => {
+                    error = Some(tr(Msg::LetterIsHistory, &[&token]));
+                    break;
+                }
+                _ => {
+                    error = Some(tr(Msg::InvalidBookmarkLetter, &[&token]));
+                    break;
+                }
+            }
+        }
+        match error {
+            Some(message) => eprintln!("{}", message.red()),
+            None => break selected,
+        }
+    };
+
+    if selected.is_empty() {
+        println!("{}", tr(Msg::NothingRemoved, &[]).yellow());
+        return Ok(());
+    }
+    for index in &selected {
+        println!("  {} {}",
+            format!("[{}]", get_prefix_char(*index)).bright_cyan().bold(),
+            bookmarks[*index].path.display().to_string().bright_white()
+        );
+    }
+    if !confirm(&tr(Msg::ConfirmRemoveSelected, &[&selected.len()]), verbose)? {
+        println!("{}", tr(Msg::NothingRemoved, &[]).yellow());
+        return Ok(());
+    }
+    remove_bookmarks_at(bookmarks, &selected, verbose)
+}
+
+fn choose_directory_interactive(verbose: bool) -> io::Result<()> {
+    debug_print(verbose, "Interactive directory selection");
+    let (bookmarks, filtered_history) = listed_entries(verbose);
+    
+    let total_items = bookmarks.len() + filtered_history.len();
+    
+    if total_items == 0 {
+        fail(EXIT_EMPTY, tr(Msg::NoBookmarks, &[]).yellow());
+    }
+
+    if use_fzf(verbose) {
+        let entries: Vec<(&Path, Option<&str>)> = bookmarks
+            .iter()
+            .map(|b| (b.path.as_path(), b.label.as_deref()))
+            .chain(filtered_history.iter().map(|p| (p.as_path(), None)))
+            .collect();
+        if let Some(selected) = fzf_select(&entries, verbose)? {
+            return change_directory(&selected, verbose);
+        }
+    }
+
+    // The picker takes every entry at once; letters restart on each page as
+    // they do at the prompt
+    let mut items = Vec::new();
+    for page in 0..total_items.div_ceil(PAGE_SIZE) {
+        let (page_bookmarks, page_history) = page_entries(&bookmarks, &filtered_history, page);
+        let lines = render_directory_list(page_bookmarks, page_history, false, verbose);
+        let targets = page_bookmarks
+            .iter()
+            .map(|b| (b.path.as_path(), format!("{} {}", b.label.as_deref().unwrap_or(""), b.path.display())))
+            .chain(page_history.iter().map(|p| (p.as_path(), p.display().to_string())));
+        for (i, (line, (path, text))) in lines.into_iter().filter(|line| !line.is_empty()).zip(targets).enumerate() {
+            items.push(PickItem { line, text, slot: Some(get_prefix_char(i)), path });
+        }
+    }
+    let default = get_index_from_char(selection_options().default_slot.unwrap_or('0')).unwrap_or(0);
+    if let Some(selected) = pick(&items, default, verbose)? {
+        return change_directory(&selected, verbose);
+    }
+
+    // More entries than letters are shown a page at a time; ">" and "<" flip
+    // between pages
+    let pages = total_items.div_ceil(PAGE_SIZE);
+    let mut page = 0;
+    let (input, page_bookmarks, page_history) = loop {
+        let (page_bookmarks, page_history) = page_entries(&bookmarks, &filtered_history, page);
+        let mut lines = render_directory_list(page_bookmarks, page_history, false, verbose);
+        lines.extend(page_footer(page, total_items));
+        let input = prompt_selection(&lines, page_bookmarks.len() + page_history.len(), verbose)?;
+        match input.as_str() {
+            ">" if page + 1 < pages => page += 1,
+            "<" if page > 0 => page -= 1,
+            _ => break (input, page_bookmarks, page_history),
+        }
+        debug_print(verbose, &format!("Showing page {} of {}", page + 1, pages));
+    };
+    // Just pressing Enter takes the default entry
+    let input = if input.is_empty() {
+        let slot = selection_options().default_slot.unwrap_or('0');
+        debug_print(verbose, &format!("Empty input, selecting default [{}]", slot));
+        slot.to_string()
+    } else {
+        input
+    };
+
+    // More than one character is a bookmark label or else a filter on all
+    // the entries, not just this page
+    if input.chars().count() > 1 {
+        if let Some(bookmark) = find_bookmark_by_label(&bookmarks, &input) {
+            debug_print(verbose, &format!("Selected bookmark '{}': {}", input, bookmark.path.display()));
+            return change_directory(&bookmark.path, verbose);
+        }
+        let displayed: Vec<PathBuf> = bookmarks
+            .iter()
+            .map(|b| b.path.clone())
+            .chain(filtered_history)
+            .collect();
+        return choose_filtered(&displayed, &input, verbose);
+    }
+
+    let ch = input.chars().next();
+    if let Some(ch) = ch {
+        if let Some(index) = get_index_from_char(ch) {
+            debug_print(verbose, &format!("Parsed index: {}", index));
+            let page_items = page_bookmarks.len() + page_history.len();
+            // Check if index is in bookmarks
+            if index < page_bookmarks.len() {
+                let selected = &page_bookmarks[index].path;
+                debug_print(verbose, &format!("Selected directory: {}", selected.display()));
+                change_directory(selected, verbose)?;
+                return Ok(());
+            } 
+            // Check if index is in filtered history (accounting for bookmark offset)
+            else if index < page_items {
+                let selected = &page_history[index - page_bookmarks.len()];
+                debug_print(verbose, &format!("Selected directory: {}", selected.display()));
+                change_directory(selected, verbose)?;
+                return Ok(());
+            } else {
+                debug_print(verbose, &format!("Index {} out of range (max: {})", index, page_items));
+            }
+        } else {
+            debug_print(verbose, &format!("Invalid character: '{}'", ch));
+        }
+    }
+    
+    fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidSelection, &[]).red());
+}
+
+fn choose_directory_by_letter(letter: &str, verbose: bool) -> io::Result<()> {
+    debug_print(verbose, &format!("Choosing directory by letter: '{}'", letter));
+    let (bookmarks, filtered_history) = listed_entries(verbose);
+    
+    let total_items = bookmarks.len() + filtered_history.len();
+    
+    if total_items == 0 {
+        fail(EXIT_EMPTY, tr(Msg::NoBookmarks, &[]).yellow());
+    }
+
+    // Anything longer than one character is a bookmark label
+    if letter.chars().count() > 1 {
+        if let Some(bookmark) = find_bookmark_by_label(&bookmarks, letter) {
+            debug_print(verbose, &format!("Selected bookmark '{}': {}", letter, bookmark.path.display()));
+            change_directory(&bookmark.path, verbose)?;
+            return Ok(());
+        }
+        fail(EXIT_INVALID_SELECTION, tr(Msg::UnknownLabel, &[&letter]).red());
+    }
+
+    let ch = letter.chars().next();
+    // Soon after a listing, a letter means what that listing showed
+    if let Some(snapshot) = ch.and_then(|_| load_listing_snapshot(verbose)) {
+        let Some((_, path)) = snapshot.iter().find(|(slot, _)| Some(*slot) == ch) else {
+            fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidDirectoryLetter, &[&letter]).red());
+        };
+        let still_listed = bookmarks.iter().any(|b| b.path == *path) || filtered_history.contains(path);
+        if !still_listed {
+            fail(EXIT_INVALID_SELECTION, tr(Msg::EntryGone, &[&letter, &path.display()]).red());
+        }
+        debug_print(verbose, &format!("Selected directory from the last listing: {}", path.display()));
+        return change_directory(path, verbose);
+    }
+    if let Some(ch) = ch {
+        if let Some(index) = get_index_from_char(ch) {
+            debug_print(verbose, &format!("Parsed index: {}", index));
+            // Check if index is in bookmarks
+            if index < bookmarks.len() {
+                let selected = &bookmarks[index].path;
+                debug_print(verbose, &format!("Selected directory: {}", selected.display()));
+                change_directory(selected, verbose)?;
+                return Ok(());
+            } 
+            // Check if index is in filtered history (accounting for bookmark offset)
+            else if index < total_items && index < SLOT_COUNT {
+                let history_index = index - bookmarks.len();
+                if history_index < filtered_history.len() {
+                    let selected = &filtered_history[history_index];
+                    debug_print(verbose, &format!("Selected directory: {}", selected.display()));
+                    change_directory(selected, verbose)?;
+                    return Ok(());
+                }
+            } else {
+                debug_print(verbose, &format!("Index {} out of range (max: {})", index, total_items.min(SLOT_COUNT)));
+            }
+        } else {
+            debug_print(verbose, &format!("Invalid character: '{}'", ch));
+        }
+    }
+    
+    fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidDirectoryLetter, &[&letter]).red());
+}
+
+// The letters of the last listing in this session ("-l", also as JSON or
+// porcelain), so that a letter typed after it means what was shown even if
+// history has moved on in the meantime. Kept next to the session's
+// navigation stack and trusted for LISTING_MAX_AGE seconds, and only while
+// the same --tag and --sort are in effect.
+const LISTING_MAX_AGE: u64 = 5 * 60;
+
+fn listing_snapshot_path() -> PathBuf {
+    sessions_dir().join(format!("{}.listing", session_id()))
+}
+
+// The options besides the stored entries that decide the letters
+fn listing_view() -> String {
+    let options = selection_options();
+    format!("tags={} order={:?} frecency={}", options.tag_filter.join(","), options.bookmark_order, options.sort_frecency)
+}
+
+// A "<time>\t<view>" line, then "<letter>\t<path>" for each lettered entry
+fn save_listing_snapshot(bookmarks: &[Bookmark], history: &[PathBuf], verbose: bool) {
+    let mut lines = vec![format!("{}\t{}", now_secs(), listing_view())];
+    let paths = bookmarks.iter().map(|b| &b.path).chain(history);
+    lines.extend(paths.take(SLOT_COUNT).enumerate().map(|(i, path)| format!("{}\t{}", get_prefix_char(i), path_field(path))));
+    let path = listing_snapshot_path();
+    let saved = fs::create_dir_all(sessions_dir()).and_then(|_| write_file_atomic(&path, lines.join("\n").as_bytes()));
+    match saved {
+        Ok(()) => debug_print(verbose, &format!("Saved listing snapshot to: {}", path.display())),
+        Err(e) => debug_print(verbose, &format!("Could not save listing snapshot: {}", e)),
+    }
+}
+
+fn load_listing_snapshot(verbose: bool) -> Option<Vec<(char, PathBuf)>> {
+    let content = fs::read_to_string(listing_snapshot_path()).ok()?;
+    let mut lines = content.lines();
+    let (time, view) = lines.next()?.split_once('\t')?;
+    let age = now_secs().saturating_sub(time.parse().ok()?);
+    if age > LISTING_MAX_AGE || view != listing_view() {
+        debug_print(verbose, &format!("Ignoring listing snapshot ({}s old, view {})", age, view));
+        return None;
+    }
+    debug_print(verbose, &format!("Using listing snapshot from {}s ago", age));
+    Some(
+        lines
+            .filter_map(|line| line.split_once('\t'))
+            .filter_map(|(slot, path)| Some((slot.chars().next()?, parse_path_field(path))))
+            .collect(),
+    )
+}
+
+// Each shell session keeps its own back and forward stacks, in a file in
+// SESSIONS_DIR (or a profile's "sessions" directory). The session is named by CHANGEDIR_SESSION if
+// the wrapper sets it, and otherwise by the parent process (the shell).
+const NAV_STACK_MAX: usize = 50;
+const SESSION_MAX_AGE: u64 = 7 * 24 * 60 * 60;
+
+#[derive(Default)]
+struct NavStack {
+    // Most recent first
+    back: Vec<PathBuf>,
+    forward: Vec<PathBuf>,
+}
+
+fn session_id() -> String {
+    if let Ok(id) = std::env::var("CHANGEDIR_SESSION") {
+        let id: String = id.chars().filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_')).collect();
+        if !id.is_empty() {
+            return id;
+        }
+    }
+    #[cfg(unix)]
+    return std::os::unix::process::parent_id().to_string();
+    #[cfg(not(unix))]
+    return "default".to_string();
+}
+
+fn sessions_dir() -> PathBuf {
+    match active_profile() {
+        Some(name) => profiles_dir().join(name).join("sessions"),
+        None => xdg_data_home().join(SESSIONS_DIR),
+    }
+}
+
+// Lines are "back<TAB>path" or "forward<TAB>path", top of each stack first
+fn load_nav_stack(verbose: bool) -> NavStack {
+    let path = sessions_dir().join(session_id());
+    debug_print(verbose, &format!("Loading navigation stack from: {}", path.display()));
+    let mut stack = NavStack::default();
+    for line in fs::read_to_string(&path).unwrap_or_default().lines() {
+        match line.split_once('\t') {
+            Some(("back", dir)) => stack.back.push(parse_path_field(dir)),
+            Some(("forward", dir)) => stack.forward.push(parse_path_field(dir)),
+            _ => debug_print(verbose, &format!("Ignoring navigation stack line: {}", line)),
+        }
+    }
+    stack
+}
+
+fn save_nav_stack(stack: &NavStack, verbose: bool) -> io::Result<()> {
+    let dir = sessions_dir();
+    fs::create_dir_all(&dir)?;
+    remove_old_sessions(&dir, verbose);
+    let lines: Vec<String> = stack
+        .back
+        .iter()
+        .take(NAV_STACK_MAX)
+        .map(|p| format!("back\t{}", path_field(p)))
+        .chain(stack.forward.iter().take(NAV_STACK_MAX).map(|p| format!("forward\t{}", path_field(p))))
+        .collect();
+    write_file_atomic(&dir.join(session_id()), lines.join("\n").as_bytes())
+}
+
+// Shells exit without telling us, so stacks nobody has touched for a while
+// are dropped
+fn remove_old_sessions(dir: &Path, verbose: bool) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let now = std::time::SystemTime::now();
+    for entry in entries.flatten() {
+        let age = entry
+            .metadata()
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|modified| now.duration_since(modified).ok());
+        if age.is_some_and(|age| age.as_secs() > SESSION_MAX_AGE) {
+            debug_print(verbose, &format!("Removing old session: {}", entry.path().display()));
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+// Remember the directory being left, for -b. A new jump starts a new branch,
+// so whatever could be replayed with --forward is dropped.
+fn push_back_stack(leaving: &Path, destination: &Path, verbose: bool) -> io::Result<()> {
+    if same_directory(leaving, destination) {
+        return Ok(());
+    }
+    let mut stack = load_nav_stack(verbose);
+    if stack.back.first().map(PathBuf::as_path) != Some(leaving) {
+        stack.back.insert(0, leaving.to_path_buf());
+    }
+    stack.forward.clear();
+    save_nav_stack(&stack, verbose)
+}
+
+// Take `steps` directories off the forward or back stack (dropping, with a
+// note, entries that are gone, and the current directory), moving the
+// current directory and the ones stepped over onto the other stack. None if
+// the stack is empty. For deliveries that don't move the shell (--print,
+// --copy, --open) the step is only looked at, and the stacks are not saved.
+fn step_nav_stack(forward: bool, steps: usize, verbose: bool) -> io::Result<Option<PathBuf>> {
+    let Ok(current) = std::env::current_dir() else {
+        return Ok(None);
+    };
+    let moves = matches!(selection_options().delivery, Delivery::TargetFile | Delivery::Eval);
+    let mut stack = load_nav_stack(verbose);
+    let (from, to) = if forward {
+        (&mut stack.forward, &mut stack.back)
+    } else {
+        (&mut stack.back, &mut stack.forward)
+    };
+    let before = from.len();
+    from.retain(|p| {
+        if !p.is_dir() {
+            eprintln!("{}", tr(Msg::SkippedMissing, &[&p.display()]).yellow());
+            return false;
+        }
+        !same_directory(p, &current)
+    });
+    let available = from.len();
+    if available < steps {
+        if moves && available != before {
+            save_nav_stack(&stack, verbose)?;
+        }
+        return match available {
+            0 => Ok(None),
+            available => fail(EXIT_INVALID_SELECTION, tr(Msg::StepsOutOfRange, &[&steps, &available]).red()),
+        };
+    }
+    let mut passed: Vec<PathBuf> = from.drain(..steps).collect();
+    let target = passed.pop().unwrap();
+    let mut pushed: Vec<PathBuf> = passed.into_iter().rev().collect();
+    pushed.push(current);
+    pushed.append(to);
+    *to = pushed;
+    if moves {
+        save_nav_stack(&stack, verbose)?;
+    }
+    debug_print(verbose, &format!("Navigation stack step to: {}", target.display()));
+    Ok(Some(target))
+}
+
+// Go to a directory taken off the navigation stack. It is recorded like any
+// other selection; the stacks themselves were updated by the step.
+fn follow_nav_step(path: &Path, verbose: bool) -> io::Result<()> {
+    let _guard = CriticalSection::enter();
+    record_selection(path, verbose)?;
+    deliver_selection(path, verbose)
+}
+
+fn change_to_next(verbose: bool) -> io::Result<()> {
+    debug_print(verbose, "Changing to next directory");
+    match step_nav_stack(true, 1, verbose)? {
+        Some(next) => follow_nav_step(&next, verbose),
+        None => fail(EXIT_EMPTY, tr(Msg::NothingForward, &[]).yellow()),
+    }
+}
+
+// Go back `steps` jumps in this session, or without any, to the `steps`-th
+// usable history entry
+fn change_to_previous(steps: usize, verbose: bool) -> io::Result<()> {
+    debug_print(verbose, &format!("Changing back {} step(s)", steps));
+    if let Some(previous) = step_nav_stack(false, steps, verbose)? {
+        return follow_nav_step(&previous, verbose);
+    }
+
+    // Nothing on this session's stack: fall back to the shared history
+    if history_max() == 0 {
+        fail(1, tr(Msg::HistoryDisabled, &[]).red());
+    }
+    let history = load_history(verbose);
+    let current = std::env::current_dir().ok();
+    let is_current = |path: &Path| current.as_deref().is_some_and(|current| same_directory(path, current));
+
+    // Entries that still exist and aren't where we already are, noting the
+    // ones skipped because they are gone
+    let mut usable = Vec::new();
+    for path in &history {
+        if usable.len() == steps {
+            break;
+        }
+        if is_current(path) {
+            continue;
+        }
+        if !path.exists() {
+            eprintln!("{}", tr(Msg::SkippedMissing, &[&path.display()]).yellow());
+            continue;
+        }
+        usable.push(path);
+    }
+
+    if usable.len() == steps {
+        let previous = usable[steps - 1];
+        debug_print(verbose, &format!("Previous directory: {}", previous.display()));
+        // Recording it moves the entry to the front of history
+        return change_directory(previous, verbose);
+    }
+    if steps > 1 {
+        fail(EXIT_INVALID_SELECTION, tr(Msg::StepsOutOfRange, &[&steps, &usable.len()]).red());
+    }
+
+    // Nothing usable in history; the shell still knows where we came from
+    debug_print(verbose, "No usable history entry, trying $OLDPWD");
+    if let Some(oldpwd) = std::env::var_os("OLDPWD").filter(|v| !v.is_empty()).map(PathBuf::from) {
+        if oldpwd.is_dir() && !is_current(&oldpwd) {
+            debug_print(verbose, &format!("Falling back to $OLDPWD: {}", oldpwd.display()));
+            deliver_selection(&oldpwd, verbose)?;
+            return Ok(());
+        }
+        debug_print(verbose, &format!("$OLDPWD is not usable: {}", oldpwd.display()));
+    }
+
+    match history.first() {
+        Some(previous) if !previous.exists() => {
+            debug_print(verbose, "Previous directory no longer exists");
+            fail(EXIT_DIRECTORY_MISSING, tr(Msg::PreviousMissing, &[&previous.display()]).red())
+        }
+        _ => fail(EXIT_EMPTY, tr(Msg::NoHistory, &[]).yellow()),
+    }
+}
+
+// Change `levels` directories up, stopping at the root if there are fewer.
+// Only the final directory is recorded in history.
+fn change_up_levels(levels: usize, verbose: bool) -> io::Result<()> {
+    let current = std::env::current_dir()?;
+    debug_print(verbose, &format!("Current directory: {}", current.display()));
+
+    let mut target = current.as_path();
+    let mut climbed = 0;
+    while climbed < levels {
+        match target.parent() {
+            Some(parent) => target = parent,
+            None => break,
+        }
+        climbed += 1;
+    }
+    if climbed == 0 {
+        debug_print(verbose, "Already at root directory");
+        eprintln!("{}", tr(Msg::AlreadyAtRoot, &[]).yellow());
+    } else if climbed < levels {
+        eprintln!("{}", tr(Msg::StoppedAtRoot, &[&climbed, &levels]).yellow());
+    }
+    debug_print(verbose, &format!("Up {} levels: {}", climbed, target.display()));
+    change_directory(target, verbose)
+}
+
+// Enter the subdirectory `name` of the current directory without prompting
+fn change_down(name: &str, verbose: bool) -> io::Result<()> {
+    let current = std::env::current_dir()?;
+    let target = current.join(name);
+    debug_print(verbose, &format!("Entering subdirectory: {}", target.display()));
+    if !target.is_dir() {
+        fail(EXIT_NOT_FOUND, tr(Msg::NoSuchSubdirectory, &[&name, &current.display()]).red());
+    }
+    change_directory(&target, verbose)
+}
+
+// Change to the nearest directory above the current one named `name`,
+// preferring the exact spelling over a match ignoring case
+fn change_up_to(name: &str, verbose: bool) -> io::Result<()> {
+    let current = std::env::current_dir()?;
+    debug_print(verbose, &format!("Looking for '{}' above {}", name, current.display()));
+    for tier in [NameMatch::Exact, NameMatch::IgnoreCase] {
+        let found = current
+            .ancestors()
+            .skip(1)
+            .find(|dir| dir.file_name().is_some_and(|n| tier.matches(name, &n.to_string_lossy())));
+        if let Some(dir) = found {
+            debug_print(verbose, &format!("Found ancestor ({:?}): {}", tier, dir.display()));
+            return change_directory(dir, verbose);
+        }
+    }
+    fail(EXIT_NOT_FOUND, tr(Msg::NoSuchAncestor, &[&name]).red())
+}
+
+const DEFAULT_ROOT_MARKERS: &str = ".git";
+
+// Change to the nearest of the current directory and its parents holding one
+// of the project root markers: `.git` unless $CHANGEDIR_ROOT_MARKERS lists
+// others (comma-separated, e.g. ".git,Cargo.toml,package.json"). A marker
+// may be a file or a directory, so the `.git` file of a worktree counts.
+fn change_to_root(verbose: bool) -> io::Result<()> {
+    let current = std::env::current_dir()?;
+    let markers = std::env::var("CHANGEDIR_ROOT_MARKERS")
+        .ok()
+        .filter(|v| !v.trim().is_empty())
+        .unwrap_or_else(|| DEFAULT_ROOT_MARKERS.to_string());
+    let markers: Vec<&str> = markers.split(',').map(str::trim).filter(|m| !m.is_empty()).collect();
+    debug_print(verbose, &format!("Looking for {:?} above {}", markers, current.display()));
+
+    for dir in current.ancestors() {
+        if let Some(marker) = markers.iter().find(|marker| dir.join(marker).exists()) {
+            debug_print(verbose, &format!("Found '{}' in {}", marker, dir.display()));
+            return change_directory(dir, verbose);
+        }
+    }
+    fail(EXIT_NOT_FOUND, tr(Msg::NotInProject, &[&markers.join(", ")]).yellow())
+}
+
+// Create `path` (with any missing parents) and change to it. An existing
+// directory is just changed to; anything else in the way is an error.
+fn make_and_change(path: &str, verbose: bool) -> io::Result<()> {
+    let target = expand_path(path, &std::env::current_dir()?);
+    debug_print(verbose, &format!("Creating directory: {}", target.display()));
+    if target.exists() && !target.is_dir() {
+        fail(1, tr(Msg::NotADirectory, &[&target.display()]).red());
+    }
+    if !target.is_dir() {
+        if let Err(e) = fs::create_dir_all(&target) {
+            fail(1, tr(Msg::CannotCreateDirectory, &[&target.display(), &e]).red());
+        }
+        // On stderr, so --stdout and --eval output stays just the path
+        eprintln!("{}", tr(Msg::CreatedDirectory, &[&target.display()]).green());
+    }
+    change_directory(&canonical_or_literal(&target), verbose)
+}
+
+fn list_subdirectories(verbose: bool) -> io::Result<()> {
+    let current = std::env::current_dir()?;
+    debug_print(verbose, &format!("Listing subdirectories of: {}", current.display()));
+    
+    let mut subdirs: Vec<PathBuf> = fs::read_dir(&current)?
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let path = entry.path();
+            if path.is_dir() {
+                Some(path)
             } else {
-                debug_print(verbose, &format!("Index {} out of range (max: {})", index, total_items.min(36)));
+                None
+            }
+        })
+        .collect();
+
+    debug_print(verbose, &format!("Found {} subdirectories", subdirs.len()));
+
+    if subdirs.is_empty() {
+        fail(EXIT_EMPTY, tr(Msg::NoSubdirectories, &[]).yellow());
+    }
+
+    sort_subdirectories(&mut subdirs);
+    debug_print(verbose, "Sorted subdirectories");
+
+    // Lettered a page at a time, as in choose_directory_interactive
+    let lines: Vec<String> = subdirs
+        .iter()
+        .enumerate()
+        .map(|(i, subdir)| {
+            let prefix = get_prefix_char(i % PAGE_SIZE);
+            let dir_name = subdir.file_name()
+                .map(|n| n.to_string_lossy())
+                .unwrap_or_default();
+            format!("{} {}", 
+                format!("[{}]", prefix).bright_cyan().bold(),
+                dir_name.bright_white()
+            )
+        })
+        .collect();
+
+    let items: Vec<PickItem> = subdirs
+        .iter()
+        .zip(&lines)
+        .enumerate()
+        .map(|(i, (subdir, line))| PickItem {
+            line: line.clone(),
+            text: subdir.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default(),
+            slot: Some(get_prefix_char(i % PAGE_SIZE)),
+            path: subdir,
+        })
+        .collect();
+    if let Some(selected) = pick(&items, 0, verbose)? {
+        return change_directory(&selected, verbose);
+    }
+
+    // ">" and "<" flip between pages
+    let mut page = 0;
+    let (input, start) = loop {
+        let start = page * PAGE_SIZE;
+        let end = (start + PAGE_SIZE).min(subdirs.len());
+        let mut page_lines = lines[start..end].to_vec();
+        page_lines.extend(page_footer(page, subdirs.len()));
+        let input = prompt_selection(&page_lines, end - start, verbose)?;
+        match input.as_str() {
+            ">" if end < subdirs.len() => page += 1,
+            "<" if page > 0 => page -= 1,
+            _ => break (input, start),
+        }
+        debug_print(verbose, &format!("Showing page {}", page + 1));
+    };
+    
+    let mut chars = input.chars();
+    match (chars.next(), chars.next()) {
+        (Some(ch), None) => match get_index_from_char(ch) {
+            Some(index) if index < PAGE_SIZE && start + index < subdirs.len() => {
+                let selected = &subdirs[start + index];
+                debug_print(verbose, &format!("Selected directory: {}", selected.display()));
+                return change_directory(selected, verbose);
+            }
+            Some(index) => debug_print(verbose, &format!("Index {} out of range on this page", index)),
+            None => debug_print(verbose, &format!("Invalid character: '{}'", ch)),
+        },
+        _ => debug_print(verbose, &format!("Invalid selection: '{}'", input)),
+    }
+    
+    fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidSelection, &[]).red());
+}
+
+const DEFAULT_TREE_DEPTH: usize = 3;
+const SKIPPED_BUILD_DIRS: &[&str] = &["target", "debug"];
+
+struct TreeItem {
+    path: PathBuf,
+    label: String,
+}
+
+impl std::fmt::Display for TreeItem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label)
+    }
+}
+
+fn is_skipped_dir(name: &str) -> bool {
+    name.starts_with('.') || SKIPPED_BUILD_DIRS.contains(&name)
+}
+
+fn collect_tree_items(
+    dir: &PathBuf,
+    prefix: &str,
+    depth: usize,
+    max_depth: usize,
+    items: &mut Vec<TreeItem>,
+    verbose: bool,
+) {
+    if depth >= max_depth {
+        return;
+    }
+
+    let mut subdirs: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| {
+                let entry = entry.ok()?;
+                let path = entry.path();
+                if !path.is_dir() {
+                    return None;
+                }
+                let name = path.file_name()?.to_string_lossy().to_string();
+                if is_skipped_dir(&name) {
+                    debug_print(verbose, &format!("Skipping directory: {}", path.display()));
+                    None
+                } else {
+                    Some(path)
+                }
+            })
+            .collect(),
+        Err(e) => {
+            debug_print(verbose, &format!("Cannot read {}: {}", dir.display(), e));
+            return;
+        }
+    };
+
+    sort_subdirectories(&mut subdirs);
+
+    let count = subdirs.len();
+    for (i, subdir) in subdirs.into_iter().enumerate() {
+        let is_last = i == count - 1;
+        let branch = if is_last { "└── " } else { "├── " };
+        let dir_name = subdir
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| "?".to_string());
+
+        items.push(TreeItem {
+            path: subdir.clone(),
+            label: format!("{}{}{}", prefix, branch, dir_name),
+        });
+
+        let child_prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
+        collect_tree_items(&subdir, &child_prefix, depth + 1, max_depth, items, verbose);
+    }
+}
+
+fn tree_select_directory(max_depth: usize, verbose: bool) -> io::Result<()> {
+    let current = std::env::current_dir()?;
+    debug_print(verbose, &format!(
+        "Building directory tree for: {} (max depth: {})",
+        current.display(),
+        max_depth
+    ));
+
+    let mut items = Vec::new();
+    collect_tree_items(&current, "", 0, max_depth, &mut items, verbose);
+
+    if items.is_empty() {
+        fail(EXIT_EMPTY, tr(Msg::NoSubdirectories, &[]).yellow());
+    }
+
+    debug_print(verbose, &format!("Found {} directories in tree", items.len()));
+
+    let prompt = format!("{}", current.display());
+    let selection = inquire::Select::new(&prompt, items)
+        .with_page_size(20)
+        .with_help_message(&tr(Msg::TreeHelp, &[]))
+        .prompt();
+
+    match selection {
+        Ok(item) => {
+            debug_print(verbose, &format!("Selected directory: {}", item.path.display()));
+            change_directory(&item.path, verbose)?;
+            Ok(())
+        }
+        Err(inquire::InquireError::OperationCanceled)
+        | Err(inquire::InquireError::OperationInterrupted) => {
+            debug_print(verbose, "Selection cancelled");
+            std::process::exit(EXIT_CANCELLED);
+        }
+        Err(e) => {
+            fail(1, tr(Msg::SelectionError, &[&e]).red());
+        }
+    }
+}
+
+const DEFAULT_NAME_DEPTH: usize = 3;
+const DEFAULT_UP_DEPTH: usize = 5;
+// Directories read at most while looking down for a name
+const DESCENT_MAX_DIRS: usize = 2000;
+
+// How far changing to a directory by name may look
+struct NameSearch {
+    // Only names spelled exactly: no case folding, prefixes or fuzzy matches
+    exact: bool,
+    // Levels below the current directory (1 is just its subdirectories)
+    depth: usize,
+    // Levels above it, each with its other subdirectories
+    up_depth: usize,
+}
+
+// Where resolve_name found a directory
+#[derive(Clone, Copy)]
+enum NameSource {
+    Label,
+    BookmarkSubpath,
+    Bookmark,
+    Subdirectory,
+    Below,
+    // Levels up from the current directory
+    Parent(usize),
+    Fuzzy,
+}
+
+impl std::fmt::Display for NameSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NameSource::Label => write!(f, "label"),
+            NameSource::BookmarkSubpath => write!(f, "bookmark subpath"),
+            NameSource::Bookmark => write!(f, "bookmark"),
+            NameSource::Subdirectory => write!(f, "subdir"),
+            NameSource::Below => write!(f, "below"),
+            NameSource::Parent(depth) => write!(f, "parent depth {}", depth),
+            NameSource::Fuzzy => write!(f, "fuzzy"),
+        }
+    }
+}
+
+fn find_directory_by_name(name: &str, search: &NameSearch, verbose: bool) -> io::Result<()> {
+    match resolve_name(name, search, verbose)? {
+        Some((NameSource::Fuzzy, found)) => {
+            eprintln!("{}", tr(Msg::FuzzyMatched, &[&name, &found[0].display()]).yellow());
+            change_directory(&found[0], verbose)
+        }
+        Some((_, found)) if found.len() == 1 => change_directory(&found[0], verbose),
+        Some((_, found)) => choose_from_paths(&found, verbose),
+        None => {
+            debug_print(verbose, "Directory not found in any location");
+            fail(EXIT_NOT_FOUND, tr(Msg::DirectoryNotFound, &[&name]).red());
+        }
+    }
+}
+
+// Print where `name` would take find_directory_by_name, and why, without
+// going there; several lines if it would offer a choice
+fn which_directory(name: &str, search: &NameSearch, verbose: bool) -> io::Result<()> {
+    match resolve_name(name, search, verbose)? {
+        Some((source, found)) => {
+            // Relative bookmarks are printed where they resolve to from here
+            for path in found {
+                let path = resolve_bookmark_path(&path).unwrap_or(path);
+                println!("{}\t{}", path.display(), source);
+            }
+            Ok(())
+        }
+        None => fail(EXIT_NOT_FOUND, tr(Msg::DirectoryNotFound, &[&name]).red()),
+    }
+}
+
+// The directories `name` refers to and where they were found, or None. More
+// than one means the user should choose between them.
+fn resolve_name(name: &str, search: &NameSearch, verbose: bool) -> io::Result<Option<(NameSource, Vec<PathBuf>)>> {
+    let exact = search.exact;
+    let current = std::env::current_dir()?;
+    debug_print(verbose, &format!("Searching for directory: '{}'", name));
+    debug_print(verbose, &format!("Current directory: {}", current.display()));
+    
+    // A bookmark label always wins, so a name keeps meaning the same place
+    // whatever directory it is typed in
+    let bookmarks = load_bookmarks(verbose);
+    if let Some(bookmark) = find_bookmark_by_label(&bookmarks, name) {
+        debug_print(verbose, &format!("Found bookmark labelled '{}': {}", name, bookmark.path.display()));
+        return Ok(Some((NameSource::Label, vec![bookmark.path.clone()])));
+    }
+
+    // "work/src/api" is src/api below the bookmark labelled (or else the
+    // bookmarked directory named) work
+    if let Some(target) = bookmark_subpath(&bookmarks, name, verbose) {
+        return Ok(Some((NameSource::BookmarkSubpath, vec![target])));
+    }
+
+    // Then the same name, in case it was typed in the wrong case; the exact
+    // spelling wins wherever it is found, so the result stays predictable
+    // when two directories differ only in case. Failing that, names starting
+    // with it and then names containing it; several of those at once are
+    // offered to choose from rather than picking one arbitrarily.
+    let tiers: &[NameMatch] = if exact {
+        &[NameMatch::Exact]
+    } else {
+        &[NameMatch::Exact, NameMatch::IgnoreCase, NameMatch::Prefix, NameMatch::Substring]
+    };
+    for &tier in tiers {
+        if let Some((source, mut found)) = find_named(name, tier, &bookmarks, &current, search, verbose) {
+            if matches!(tier, NameMatch::Exact | NameMatch::IgnoreCase) {
+                found.truncate(1);
             }
-        } else {
-            debug_print(verbose, &format!("Invalid character: '{}'", ch));
+            return Ok(Some((source, found)));
         }
     }
-    
-    eprintln!("{}", "Invalid selection.".red());
-    std::process::exit(1);
+
+    // No match by name anywhere: fall back to fuzzy matching against the
+    // bookmarks, history and subdirectories
+    if !exact {
+        let mut candidates: Vec<PathBuf> = Vec::new();
+        let subdirs = fs::read_dir(&current).map(|entries| {
+            let mut subdirs: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect();
+            subdirs.sort();
+            subdirs
+        });
+        let stored = load_bookmarks(verbose)
+            .into_iter()
+            .map(|b| b.path)
+            .chain(load_history(verbose))
+            .filter_map(|p| resolve_bookmark_path(&p));
+        for path in stored.filter(|p| p.is_dir()).chain(subdirs.unwrap_or_default()) {
+            if !candidates.contains(&path) {
+                candidates.push(path);
+            }
+        }
+
+        if let Some(best) = best_fuzzy_match(name, &candidates) {
+            debug_print(verbose, &format!("Best fuzzy match among {} candidates: {}", candidates.len(), best.display()));
+            return Ok(Some((NameSource::Fuzzy, vec![best.clone()])));
+        }
+    }
+    Ok(None)
 }
 
-fn choose_directory_by_letter(letter: &str, verbose: bool) -> io::Result<()> {
-    debug_print(verbose, &format!("Choosing directory by letter: '{}'", letter));
-    let bookmarks = load_bookmarks(verbose);
-    let history = load_history(verbose);
-    
-    // Filter out history entries that are already in bookmarks
-    let filtered_history: Vec<PathBuf> = history
+fn bookmark_subpath(bookmarks: &[Bookmark], name: &str, verbose: bool) -> Option<PathBuf> {
+    let (head, rest) = name.split_once('/')?;
+    if head.is_empty() || rest.is_empty() {
+        return None;
+    }
+    let bookmark = find_bookmark_by_label(bookmarks, head).or_else(|| {
+        bookmarks
+            .iter()
+            .find(|b| b.path.file_name().is_some_and(|n| n.to_string_lossy() == head))
+    })?;
+    let target = resolve_bookmark_path(&bookmark.path)?.join(rest);
+    debug_print(verbose, &format!("'{}' is below bookmark {}: {}", name, bookmark.path.display(), target.display()));
+    if target.is_dir() {
+        Some(target)
+    } else {
+        debug_print(verbose, "No such directory below the bookmark, searching by name instead");
+        None
+    }
+}
+
+// Directories matching `name` by `tier` from 2 to `depth` levels below
+// `root`, from the shallowest level that has any. Unlike the immediate
+// subdirectories, hidden directories and those excluded by a .gitignore
+// on the way are skipped here, and the walk gives up after reading
+// DESCENT_MAX_DIRS directories so a huge tree can't stall the jump.
+fn descend_named(root: &Path, name: &str, tier: NameMatch, depth: usize, verbose: bool) -> Vec<PathBuf> {
+    let mut read = 0;
+    // Each directory with the .gitignore patterns that apply inside it
+    let mut expand = |dir: &Path, ignored: &[Vec<char>]| -> Vec<(PathBuf, Vec<Vec<char>>)> {
+        if read >= DESCENT_MAX_DIRS {
+            return Vec::new();
+        }
+        read += 1;
+        let mut ignored = ignored.to_vec();
+        ignored.extend(gitignore_patterns(dir));
+        let mut children: Vec<PathBuf> = fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect())
+            .unwrap_or_default();
+        children.sort();
+        children
+            .into_iter()
+            .filter(|child| {
+                let child_name: Vec<char> = child.file_name().map(|n| n.to_string_lossy().chars().collect()).unwrap_or_default();
+                child_name.first() != Some(&'.') && !ignored.iter().any(|pattern| glob_matches(pattern, &child_name))
+            })
+            .map(|child| (child, ignored.clone()))
+            .collect()
+    };
+
+    let mut level = expand(root, &[]);
+    for level_depth in 2..=depth {
+        let next: Vec<(PathBuf, Vec<Vec<char>>)> = level
+            .iter()
+            .flat_map(|(dir, ignored)| expand(dir, ignored))
+            .collect();
+        let found: Vec<PathBuf> = next
+            .iter()
+            .map(|(dir, _)| dir)
+            .filter(|dir| dir.file_name().is_some_and(|n| tier.matches(name, &n.to_string_lossy())))
+            .cloned()
+            .collect();
+        if !found.is_empty() {
+            debug_print(verbose, &format!("Found at depth {} after reading {} directories", level_depth, read));
+            return found;
+        }
+        if next.is_empty() {
+            break;
+        }
+        level = next;
+    }
+    if read >= DESCENT_MAX_DIRS {
+        debug_print(verbose, &format!("Stopped looking down after {} directories", read));
+    }
+    Vec::new()
+}
+
+// Name patterns from the .gitignore in `dir`. Only patterns for plain names
+// ("target", "*.egg-info", "build/") are used; negations and patterns for
+// paths below the directory are left out.
+fn gitignore_patterns(dir: &Path) -> Vec<Vec<char>> {
+    let Ok(content) = fs::read_to_string(dir.join(".gitignore")) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with(['#', '!']))
+        .map(|line| line.trim_end_matches('/').trim_start_matches('/'))
+        .filter(|pattern| !pattern.is_empty() && !pattern.contains('/'))
+        .map(|pattern| pattern.chars().collect())
+        .collect()
+}
+
+#[derive(Clone, Copy, Debug)]
+enum NameMatch {
+    Exact,
+    IgnoreCase,
+    Prefix,
+    Substring,
+}
+
+impl NameMatch {
+    // All but the exact match ignore case
+    fn matches(self, query: &str, name: &str) -> bool {
+        let (query_lower, name_lower) = (query.to_lowercase(), name.to_lowercase());
+        match self {
+            NameMatch::Exact => name == query,
+            NameMatch::IgnoreCase => name_lower == query_lower,
+            NameMatch::Prefix => name_lower.starts_with(&query_lower),
+            NameMatch::Substring => name_lower.contains(&query_lower),
+        }
+    }
+}
+
+// Directories whose name matches `name` by `tier`, from the first place that
+// has any: bookmarked directories, then subdirectories of `current` (and
+// theirs, down to `search.depth` levels), then the parent directories (up
+// to `search.up_depth` levels) and their subdirectories, which takes in the
+// siblings of the current directory and of each parent
+fn find_named(
+    name: &str,
+    tier: NameMatch,
+    bookmarks: &[Bookmark],
+    current: &Path,
+    search: &NameSearch,
+    verbose: bool,
+) -> Option<(NameSource, Vec<PathBuf>)> {
+    debug_print(verbose, &format!("Searching for '{}' ({:?})", name, tier));
+    let named = |path: &Path| path.file_name().is_some_and(|n| tier.matches(name, &n.to_string_lossy()));
+
+    let found: Vec<PathBuf> = bookmarks
         .iter()
-        .filter(|hist_dir| !bookmarks.contains(hist_dir))
-        .cloned()
+        .map(|b| b.path.clone())
+        .filter(|p| named(p) && p.is_dir())
         .collect();
-    
-    let total_items = bookmarks.len() + filtered_history.len();
-    
-    if total_items == 0 {
-        eprintln!("{}", "No bookmarked directories.".yellow());
-        std::process::exit(1);
+    if !found.is_empty() {
+        debug_print(verbose, &format!("Found in bookmarks: {:?}", found));
+        return Some((NameSource::Bookmark, found));
     }
 
-    let ch = letter.chars().next();
-    if let Some(ch) = ch {
-        if let Some(index) = get_index_from_char(ch) {
-            debug_print(verbose, &format!("Parsed index: {}", index));
-            // Check if index is in bookmarks
-            if index < bookmarks.len() {
-                let selected = &bookmarks[index];
-                debug_print(verbose, &format!("Selected directory: {}", selected.display()));
-                add_to_history(selected.clone(), verbose)?;
-                write_target_file(selected, verbose)?;
-                return Ok(());
-            } 
-            // Check if index is in filtered history (accounting for bookmark offset)
-            else if index < total_items && index < 36 {
-                let history_index = index - bookmarks.len();
-                if history_index < filtered_history.len() {
-                    let selected = &filtered_history[history_index];
-                    debug_print(verbose, &format!("Selected directory: {}", selected.display()));
-                    add_to_history(selected.clone(), verbose)?;
-                    write_target_file(selected, verbose)?;
-                    return Ok(());
+    let subdirectories = |dir: &Path| -> Vec<PathBuf> {
+        let mut found: Vec<PathBuf> = fs::read_dir(dir)
+            .map(|entries| entries.flatten().map(|e| e.path()).filter(|p| named(p) && p.is_dir()).collect())
+            .unwrap_or_default();
+        found.sort();
+        found
+    };
+    let found = subdirectories(current);
+    if !found.is_empty() {
+        debug_print(verbose, &format!("Found in subdirectories: {:?}", found));
+        return Some((NameSource::Subdirectory, found));
+    }
+    let found = descend_named(current, name, tier, search.depth, verbose);
+    if !found.is_empty() {
+        debug_print(verbose, &format!("Found further down: {:?}", found));
+        return Some((NameSource::Below, found));
+    }
+
+    for (depth, parent) in current.ancestors().skip(1).take(search.up_depth).enumerate() {
+        debug_print(verbose, &format!("Checking at depth {}: {}", depth + 1, parent.display()));
+        // A name with a separator in it ("src/bin") can only match exactly
+        let candidate = parent.join(name);
+        if matches!(tier, NameMatch::Exact) && candidate.is_dir() {
+            debug_print(verbose, &format!("Found in parent directories: {}", candidate.display()));
+            return Some((NameSource::Parent(depth + 1), vec![candidate]));
+        }
+        let found = subdirectories(parent);
+        if !found.is_empty() {
+            debug_print(verbose, &format!("Found in parent directories: {:?}", found));
+            return Some((NameSource::Parent(depth + 1), found));
+        }
+    }
+    None
+}
+
+fn is_glob_pattern(text: &str) -> bool {
+    text.contains(['*', '?', '['])
+}
+
+// Shell-style matching of a whole name: `*` is any run of characters, `?`
+// any single one, and `[abc]`, `[a-z]` or `[!abc]` one from (or not from) a
+// set. A `[` without a closing `]` is an ordinary character.
+fn glob_matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|skip| glob_matches(&pattern[1..], &name[skip..])),
+        Some('?') => !name.is_empty() && glob_matches(&pattern[1..], &name[1..]),
+        Some('[') if glob_set_end(pattern).is_some() => {
+            let Some(&c) = name.first() else {
+                return false;
+            };
+            let end = glob_set_end(pattern).unwrap_or_default();
+            let negated = matches!(pattern.get(1), Some('!' | '^'));
+            let set = &pattern[if negated { 2 } else { 1 }..end];
+            let mut in_set = false;
+            let mut i = 0;
+            while i < set.len() {
+                if i + 2 < set.len() && set[i + 1] == '-' {
+                    in_set |= (set[i]..=set[i + 2]).contains(&c);
+                    i += 3;
+                } else {
+                    in_set |= set[i] == c;
+                    i += 1;
                 }
-            } else {
-                debug_print(verbose, &format!("Index {} out of range (max: {})", index, total_items.min(36)));
             }
-        } else {
-            debug_print(verbose, &format!("Invalid character: '{}'", ch));
+            in_set != negated && glob_matches(&pattern[end + 1..], &name[1..])
         }
+        Some(&p) => name.first() == Some(&p) && glob_matches(&pattern[1..], &name[1..]),
     }
-    
-    eprintln!("{}", format!("Invalid directory letter: {}", letter).red());
-    std::process::exit(1);
 }
 
-fn change_to_previous(verbose: bool) -> io::Result<()> {
-    debug_print(verbose, "Changing to previous directory");
-    let history = load_history(verbose);
-    
-    if history.is_empty() {
-        eprintln!("{}", "No directory history.".yellow());
-        std::process::exit(1);
+// Position of the `]` closing the set that `pattern` starts with. A `]`
+// straight after the opening bracket (or its `!`) is part of the set.
+fn glob_set_end(pattern: &[char]) -> Option<usize> {
+    let start = if matches!(pattern.get(1), Some('!' | '^')) { 2 } else { 1 };
+    let rest = pattern.get(start + 1..)?;
+    rest.iter().position(|&c| c == ']').map(|offset| start + 1 + offset)
+}
+
+// Change to the directory whose name matches the glob `pattern`, looking at
+// bookmarks, then subdirectories of the current directory, then history.
+// Several matches are offered to choose from.
+fn change_to_glob(pattern: &str, verbose: bool) -> io::Result<()> {
+    debug_print(verbose, &format!("Expanding glob pattern: '{}'", pattern));
+    let pattern_chars: Vec<char> = pattern.chars().collect();
+    let bookmarks = load_bookmarks(verbose);
+    let mut subdirectories: Vec<PathBuf> = fs::read_dir(std::env::current_dir()?)?
+        .flatten()
+        .map(|entry| entry.path())
+        .collect();
+    subdirectories.sort();
+
+    let candidates = bookmarks
+        .iter()
+        .filter_map(|b| resolve_bookmark_path(&b.path))
+        .chain(subdirectories)
+        .chain(load_history(verbose));
+    let mut found: Vec<PathBuf> = Vec::new();
+    for path in candidates {
+        let name: Vec<char> = match path.file_name() {
+            Some(name) => name.to_string_lossy().chars().collect(),
+            None => continue,
+        };
+        if glob_matches(&pattern_chars, &name) && path.is_dir() && !found.iter().any(|f| same_directory(f, &path)) {
+            found.push(path);
+        }
     }
+    debug_print(verbose, &format!("Pattern matches {} directories", found.len()));
 
-    // Get the first entry (most recent)
-    let previous = &history[0];
-    debug_print(verbose, &format!("Previous directory: {}", previous.display()));
-    
-    if !previous.exists() {
-        debug_print(verbose, "Previous directory no longer exists");
-        eprintln!("{}", format!("Previous directory no longer exists: {}", previous.display()).red());
-        std::process::exit(1);
+    match found.len() {
+        0 => fail(EXIT_NOT_FOUND, tr(Msg::NoGlobMatches, &[&pattern]).red()),
+        1 => change_directory(&found[0], verbose),
+        _ => choose_from_paths(&found, verbose),
     }
+}
 
-    write_target_file(previous, verbose)?;
-    Ok(())
+// The candidate whose directory name matches `query` best, or None if no name
+// contains its characters in order. Earlier candidates win ties.
+fn best_fuzzy_match<'a>(query: &str, candidates: &'a [PathBuf]) -> Option<&'a PathBuf> {
+    let mut best: Option<(i64, &PathBuf)> = None;
+    for path in candidates {
+        let Some(name) = path.file_name() else {
+            continue;
+        };
+        if let Some(score) = fuzzy_score(query, &name.to_string_lossy()) {
+            if best.is_none_or(|(best_score, _)| score > best_score) {
+                best = Some((score, path));
+            }
+        }
+    }
+    best.map(|(_, path)| path)
 }
 
-fn change_up_one_level(verbose: bool) -> io::Result<()> {
-    let current = std::env::current_dir()?;
-    debug_print(verbose, &format!("Current directory: {}", current.display()));
-    
-    if let Some(parent) = current.parent() {
-        let parent_path = parent.to_path_buf();
-        debug_print(verbose, &format!("Parent directory: {}", parent_path.display()));
-        add_to_history(parent_path.clone(), verbose)?;
-        write_target_file(&parent_path, verbose)?;
-        Ok(())
-    } else {
-        debug_print(verbose, "Already at root directory");
-        eprintln!("{}", "Already at root directory.".yellow());
-        std::process::exit(1)
+// Skim-style score for `query` as a subsequence of `name`, ignoring case:
+// points for every matched character, more for runs of consecutive matches
+// and for matches at the start of a word ("changeDir" has words "change"
+// and "Dir"), less for every character skipped
+fn fuzzy_score(query: &str, name: &str) -> Option<i64> {
+    let name: Vec<char> = name.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.chars().flat_map(char::to_lowercase) {
+        let found = (position..name.len()).find(|&i| name[i].to_lowercase().eq(std::iter::once(q)))?;
+        score += 16;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 8;
+        }
+        let word_start = found == 0
+            || !name[found - 1].is_alphanumeric()
+            || (name[found - 1].is_lowercase() && name[found].is_uppercase());
+        if word_start {
+            score += 8;
+        }
+        score -= (found - position) as i64;
+        previous = Some(found);
+        position = found + 1;
     }
+    // Characters left over after the last match count a little against it
+    Some(score - (name.len() - position) as i64 / 4)
 }
 
-fn list_subdirectories(verbose: bool) -> io::Result<()> {
-    let current = std::env::current_dir()?;
-    debug_print(verbose, &format!("Listing subdirectories of: {}", current.display()));
-    
-    let mut subdirs: Vec<PathBuf> = fs::read_dir(&current)?
-        .filter_map(|entry| {
-            let entry = entry.ok()?;
-            let path = entry.path();
-            if path.is_dir() {
-                Some(path)
-            } else {
-                None
-            }
+// --fzf forces fzf and --no-fzf rules it out; otherwise it is used when it
+// is installed and the chooser is run from a terminal
+fn use_fzf(verbose: bool) -> bool {
+    // fzf is never used with piped input, which the letter prompt reads
+    let terminal = io::stdin().is_terminal();
+    match selection_options().fzf {
+        Some(true) if !terminal => {
+            eprintln!("{}", tr(Msg::FzfNoTerminal, &[]).yellow());
+            false
+        }
+        Some(explicit) => explicit,
+        None => {
+            let installed = std::env::var_os("PATH")
+                .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join("fzf").is_file()));
+            debug_print(verbose, &format!("fzf {}", if installed { "found on PATH" } else { "not found on PATH" }));
+            installed && terminal
+        }
+    }
+}
+
+// Let the user pick one of `entries` (paths with their labels) in fzf.
+// None if fzf can't be started, so the built-in chooser can take over;
+// leaving fzf without a choice (exit status 1 or 130) cancels, and any other
+// failure is reported.
+fn fzf_select(entries: &[(&Path, Option<&str>)], verbose: bool) -> io::Result<Option<PathBuf>> {
+    use std::process::{Command, Stdio};
+
+    // Each line starts with its position, which fzf is told not to show
+    let input: String = entries
+        .iter()
+        .enumerate()
+        .map(|(i, (path, label))| match label {
+            Some(label) => format!("{}\t{} → {}\n", i, label, path.display()),
+            None => format!("{}\t{}\n", i, path.display()),
         })
         .collect();
+    let child = Command::new("fzf")
+        .args(["--delimiter=\t", "--with-nth=2..", "--height=40%", "--reverse", "--no-multi"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn();
+    let mut child = match child {
+        Ok(child) => child,
+        Err(e) => {
+            debug_print(verbose, &format!("Cannot run fzf: {}", e));
+            eprintln!("{}", tr(Msg::FzfUnavailable, &[&e]).yellow());
+            return Ok(None);
+        }
+    };
+    if let Some(mut stdin) = child.stdin.take() {
+        match stdin.write_all(input.as_bytes()) {
+            Err(e) if e.kind() != io::ErrorKind::BrokenPipe => return Err(e),
+            _ => {}
+        }
+    }
+    let output = child.wait_with_output()?;
+    match output.status.code() {
+        Some(0) => {}
+        Some(1 | 130) => {
+            debug_print(verbose, &format!("fzf exited with {}, nothing selected", output.status));
+            std::process::exit(EXIT_CANCELLED);
+        }
+        _ => fail(1, tr(Msg::FzfFailed, &[&output.status]).red()),
+    }
 
-    debug_print(verbose, &format!("Found {} subdirectories", subdirs.len()));
+    let selected = String::from_utf8_lossy(&output.stdout);
+    let index = selected.split('\t').next().and_then(|i| i.trim().parse::<usize>().ok());
+    match index.and_then(|i| entries.get(i)) {
+        Some((path, _)) => {
+            debug_print(verbose, &format!("Selected in fzf: {}", path.display()));
+            Ok(Some(path.to_path_buf()))
+        }
+        None => fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidSelection, &[]).red()),
+    }
+}
 
-    if subdirs.is_empty() {
-        eprintln!("{}", "No subdirectories found.".yellow());
-        std::process::exit(1);
+// Narrow `paths` to those containing `filter` (ignoring case): a single match
+// is used directly, several are offered again with fresh letters
+fn choose_filtered(paths: &[PathBuf], filter: &str, verbose: bool) -> io::Result<()> {
+    let filter = filter.to_lowercase();
+    let matching: Vec<PathBuf> = paths
+        .iter()
+        .filter(|p| p.to_string_lossy().to_lowercase().contains(&filter))
+        .cloned()
+        .collect();
+    debug_print(verbose, &format!("Filter '{}' matches {} entries", filter, matching.len()));
+    match matching.len() {
+        0 => fail(EXIT_NOT_FOUND, tr(Msg::NoFilterMatches, &[&filter]).red()),
+        1 => change_directory(&matching[0], verbose),
+        _ => choose_from_paths(&matching, verbose),
     }
+}
 
-    subdirs.sort();
-    debug_print(verbose, "Sorted subdirectories");
+// History on its own, lettered from [0]. Unlike the history section of -c it
+// keeps the entries that are also bookmarks.
+fn choose_history(letter: Option<&str>, verbose: bool) -> io::Result<()> {
+    let mut history = load_history(verbose);
+    history.truncate(history_max());
+    let history = order_history(history, verbose);
+    if history.is_empty() {
+        fail(EXIT_EMPTY, tr(Msg::NoHistory, &[]).yellow());
+    }
+    let Some(letter) = letter else {
+        return choose_from_paths(&history, verbose);
+    };
+    let mut chars = letter.chars();
+    match (chars.next().and_then(get_index_from_char), chars.next()) {
+        (Some(index), None) if index < history.len() => {
+            debug_print(verbose, &format!("Selected history entry: {}", history[index].display()));
+            change_directory(&history[index], verbose)
+        }
+        _ => fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidDirectoryLetter, &[&letter]).red()),
+    }
+}
 
-    for (i, subdir) in subdirs.iter().enumerate() {
-        if i >= 36 {
-            break;
+// Let the user pick one of `paths` with the usual letter prompt; typing more
+// than one character filters the list further
+fn choose_from_paths(paths: &[PathBuf], verbose: bool) -> io::Result<()> {
+    let items: Vec<PickItem> = paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let shown = path.display().to_string();
+            let line = if i < SLOT_COUNT {
+                format!("{} {}", format!("[{}]", get_prefix_char(i)).bright_cyan().bold(), shown.bright_white())
+            } else {
+                format!("    {}", shown.bright_white())
+            };
+            PickItem { line, text: shown, slot: (i < SLOT_COUNT).then(|| get_prefix_char(i)), path }
+        })
+        .collect();
+    if let Some(selected) = pick(&items, 0, verbose)? {
+        return change_directory(&selected, verbose);
+    }
+
+    let mut lines: Vec<String> = paths
+        .iter()
+        .take(SLOT_COUNT)
+        .enumerate()
+        .map(|(i, path)| format!("{} {}",
+            format!("[{}]", get_prefix_char(i)).bright_cyan().bold(),
+            path.display().to_string().bright_white()
+        ))
+        .collect();
+    if paths.len() > SLOT_COUNT {
+        lines.push(tr(Msg::MoreMatches, &[&(paths.len() - SLOT_COUNT)]).yellow().to_string());
+    }
+
+    let input = prompt_selection(&lines, paths.len(), verbose)?;
+    if input.chars().count() > 1 {
+        return choose_filtered(paths, &input, verbose);
+    }
+    let mut chars = input.chars();
+    match (chars.next().and_then(get_index_from_char), chars.next()) {
+        (Some(index), None) if index < paths.len().min(SLOT_COUNT) => {
+            debug_print(verbose, &format!("Selected directory: {}", paths[index].display()));
+            change_directory(&paths[index], verbose)
         }
-        let prefix = get_prefix_char(i);
-        let dir_name = subdir.file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("?");
-        println!("{} {}", 
-            format!("[{}]", prefix).bright_cyan().bold(),
-            dir_name.bright_white()
-        );
+        _ => fail(EXIT_INVALID_SELECTION, tr(Msg::InvalidSelection, &[]).red()),
     }
+}
 
-    print!("{}", "Select directory (0-9, a-z): ".bright_yellow());
-    io::stdout().flush()?;
+// Directories below `root`, at most `max_depth` levels down, whose name
+// contains `term` (ignoring case). Hidden directories are skipped unless
+// `hidden`. Each directory is entered once by its canonical path, so symlinks
+// pointing back up the tree can't make the walk loop.
+fn search_directories(root: &Path, term: &str, max_depth: usize, hidden: bool, verbose: bool) -> Vec<PathBuf> {
+    let term = term.to_lowercase();
+    let mut visited = std::collections::HashSet::new();
+    if let Ok(canonical) = fs::canonicalize(root) {
+        visited.insert(canonical);
+    }
+    let mut queue = std::collections::VecDeque::from([(root.to_path_buf(), 0)]);
+    let mut found = Vec::new();
 
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    debug_print(verbose, &format!("User input: '{}'", input.trim()));
-    
-    let ch = input.trim().chars().next();
-    if let Some(ch) = ch {
-        if let Some(index) = get_index_from_char(ch) {
-            debug_print(verbose, &format!("Parsed index: {}", index));
-            if index < subdirs.len() && index < 36 {
-                let selected = &subdirs[index];
-                debug_print(verbose, &format!("Selected directory: {}", selected.display()));
-                add_to_history(selected.clone(), verbose)?;
-                write_target_file(selected, verbose)?;
-                return Ok(());
-            } else {
-                debug_print(verbose, &format!("Index {} out of range (max: {})", index, subdirs.len().min(36)));
+    while let Some((dir, depth)) = queue.pop_front() {
+        if depth >= max_depth {
+            continue;
+        }
+        let entries = match fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                debug_print(verbose, &format!("Cannot read {}: {}", dir.display(), e));
+                continue;
             }
-        } else {
-            debug_print(verbose, &format!("Invalid character: '{}'", ch));
+        };
+        let mut children: Vec<PathBuf> = entries.flatten().map(|e| e.path()).filter(|p| p.is_dir()).collect();
+        children.sort();
+        for child in children {
+            let name = child.file_name().map(|n| n.to_string_lossy().to_lowercase()).unwrap_or_default();
+            if !hidden && name.starts_with('.') {
+                continue;
+            }
+            let Ok(canonical) = fs::canonicalize(&child) else {
+                continue;
+            };
+            if !visited.insert(canonical) {
+                debug_print(verbose, &format!("Already visited: {}", child.display()));
+                continue;
+            }
+            if name.contains(&term) {
+                found.push(child.clone());
+            }
+            queue.push_back((child, depth + 1));
         }
     }
-    
-    eprintln!("{}", "Invalid selection.".red());
-    std::process::exit(1);
+    found
 }
 
-const DEFAULT_TREE_DEPTH: usize = 3;
-const SKIPPED_BUILD_DIRS: &[&str] = &["target", "debug"];
+fn search_command(term: &str, max_depth: usize, hidden: bool, verbose: bool) -> io::Result<()> {
+    let current = std::env::current_dir()?;
+    debug_print(verbose, &format!("Searching for '{}' below {} (depth {})", term, current.display(), max_depth));
+    let found = search_directories(&current, term, max_depth, hidden, verbose);
+    debug_print(verbose, &format!("Found {} matching directories", found.len()));
 
-struct TreeItem {
-    path: PathBuf,
-    label: String,
+    match found.len() {
+        0 => fail(EXIT_NOT_FOUND, tr(Msg::NoSearchMatches, &[&term, &max_depth]).yellow()),
+        1 => change_directory(&found[0], verbose),
+        _ => choose_from_paths(&found, verbose),
+    }
 }
 
-impl std::fmt::Display for TreeItem {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.label)
+// JSON fields describing `path`. A path that is not valid UTF-8 is written
+// lossily for reading, flagged, and carried exactly in base64 for import.
+fn path_json_fields(path: &Path) -> Vec<(&'static str, String)> {
+    let mut fields = vec![("path", json::string(&path.to_string_lossy()))];
+    if path.to_str().is_none() {
+        fields.push(("lossy", "true".to_string()));
+        fields.push(("path_base64", json::string(&base64::encode(&path_bytes(path)))));
     }
+    fields
 }
 
-fn is_skipped_dir(name: &str) -> bool {
-    name.starts_with('.') || SKIPPED_BUILD_DIRS.contains(&name)
+#[cfg(unix)]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().to_vec()
 }
 
-fn collect_tree_items(
-    dir: &PathBuf,
-    prefix: &str,
-    depth: usize,
-    max_depth: usize,
-    items: &mut Vec<TreeItem>,
-    verbose: bool,
-) {
-    if depth >= max_depth {
-        return;
+#[cfg(not(unix))]
+fn path_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().as_bytes().to_vec()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::os::unix::ffi::OsStringExt;
+    PathBuf::from(std::ffi::OsString::from_vec(bytes))
+}
+
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(String::from_utf8_lossy(&bytes).to_string())
+}
+
+// Marks a path in a plain-text state file (the visit log, navigation stacks)
+// that is stored in base64 because it is not valid UTF-8. Stored paths are
+// absolute, so a real path can't start with it.
+const BASE64_PATH_PREFIX: &str = "base64:";
+
+// `path` as one field of a plain-text state file
+fn path_field(path: &Path) -> String {
+    match path.to_str() {
+        Some(text) => text.to_string(),
+        None => format!("{}{}", BASE64_PATH_PREFIX, base64::encode(&path_bytes(path))),
+    }
+}
+
+fn parse_path_field(field: &str) -> PathBuf {
+    field
+        .strip_prefix(BASE64_PATH_PREFIX)
+        .and_then(base64::decode)
+        .map(path_from_bytes)
+        .unwrap_or_else(|| PathBuf::from(field))
+}
+
+fn path_from_json(entry: &json::Value) -> Option<PathBuf> {
+    match entry.get("path_base64").and_then(json::Value::as_str) {
+        Some(encoded) => base64::decode(encoded).map(path_from_bytes),
+        None => entry.get("path").and_then(json::Value::as_str).map(PathBuf::from),
+    }
+}
+
+// Write all bookmarks (with their attributes) and the history as one JSON
+// document that --import reads back
+fn export_data(file: Option<&str>, with_history: bool, verbose: bool) -> io::Result<()> {
+    let store = Store {
+        bookmarks: load_bookmarks(verbose),
+        history: if with_history { load_timed_history(verbose) } else { Vec::new() },
+    };
+    debug_print(verbose, &format!("Exporting {} bookmarks and {} history entries", store.bookmarks.len(), store.history.len()));
+    match file {
+        None | Some("-") => print!("{}", store.to_json()),
+        Some(file) => {
+            if let Err(e) = fs::write(file, store.to_json()) {
+                fail(1, tr(Msg::CannotWriteFile, &[&file, &e]).red());
+            }
+            println!("{}", tr(Msg::Exported, &[&store.bookmarks.len(), &store.history.len(), &file]).green());
+        }
+    }
+    Ok(())
+}
+
+// The bookmarks (and, for an --export document, history) in an import file.
+// Anything not starting with "{" is read as the native bookmark file format.
+fn parse_import(file: &str, content: &str) -> Store {
+    if !content.trim_start().starts_with('{') {
+        return Store {
+            bookmarks: content.lines().filter_map(Bookmark::parse).collect(),
+            history: Vec::new(),
+        };
+    }
+    match Store::parse(content) {
+        Ok(store) => store,
+        Err(e) => fail(1, tr(Msg::InvalidExport, &[&file, &e]).red()),
+    }
+}
+
+// Add the bookmarks (and history) from an --export document or a bookmark
+// file to the current ones, skipping duplicates. With `replace` the current
+// data is overwritten instead. `-` reads standard input.
+fn import_data(file: &str, replace: bool, yes: bool, verbose: bool) -> io::Result<()> {
+    let content = if file == "-" {
+        let mut content = String::new();
+        io::Read::read_to_string(&mut io::stdin(), &mut content)?;
+        content
+    } else {
+        match fs::read_to_string(file) {
+            Ok(content) => content,
+            Err(e) => fail(1, tr(Msg::CannotReadFile, &[&file, &e]).red()),
+        }
+    };
+    let imported = parse_import(file, &content);
+    debug_print(verbose, &format!("Read {} bookmarks and {} history entries", imported.bookmarks.len(), imported.history.len()));
+
+    let (mut bookmarks, mut history) = if replace {
+        let existing = load_bookmarks(verbose).len() + load_history(verbose).len();
+        if existing > 0 && !yes && !confirm(&tr(Msg::ConfirmImport, &[]), verbose)? {
+            println!("{}", tr(Msg::NothingImported, &[]).yellow());
+            return Ok(());
+        }
+        (Vec::new(), Vec::new())
+    } else {
+        (load_bookmarks(verbose), load_timed_history(verbose))
+    };
+
+    let mut added = 0;
+    let mut skipped = 0;
+    let mut no_room = Vec::new();
+    // Imported paths are stored as if bookmarked here, so a trailing slash or
+    // a symlink doesn't make a second entry for the same directory. Relative
+    // bookmarks stay relative.
+    let normalised = |path: PathBuf| if path.is_absolute() { stored_path(&path) } else { path };
+    for mut bookmark in imported.bookmarks {
+        bookmark.path = normalised(bookmark.path);
+        if bookmarks.iter().any(|b| same_directory(&b.path, &bookmark.path)) {
+            debug_print(verbose, &format!("Already bookmarked: {}", bookmark.path.display()));
+            skipped += 1;
+            continue;
+        }
+        if bookmarks.len() >= max_bookmarks() {
+            no_room.push(bookmark.path.display().to_string());
+            continue;
+        }
+        // A label already used here stays with the existing bookmark
+        if let Some(label) = bookmark.label.as_deref() {
+            if find_bookmark_by_label(&bookmarks, label).is_some() {
+                debug_print(verbose, &format!("Dropping label '{}' already in use", label));
+                bookmark.label = None;
+            }
+        }
+        bookmarks.push(bookmark);
+        added += 1;
+    }
+    for (path, visited) in imported.history {
+        if history.len() >= history_max() {
+            break;
+        }
+        let path = normalised(path);
+        if !history.iter().any(|(p, _)| same_directory(p, &path)) {
+            history.push((path, visited));
+        }
     }
 
-    let mut subdirs: Vec<PathBuf> = match fs::read_dir(dir) {
+    save_store(&Store { bookmarks, history }, verbose)?;
+    println!("{}", tr(Msg::ImportedBookmarks, &[&added, &skipped]).green());
+    if !no_room.is_empty() {
+        fail(1, tr(Msg::ImportNoRoom, &[&no_room.len(), &max_bookmarks(), &no_room.join(", ")]).red());
+    }
+    Ok(())
+}
+
+fn list_profiles(verbose: bool) -> io::Result<()> {
+    let profiles_dir = profiles_dir();
+    debug_print(verbose, &format!("Listing profiles in: {}", profiles_dir.display()));
+
+    let mut names: Vec<String> = match fs::read_dir(&profiles_dir) {
         Ok(entries) => entries
-            .filter_map(|entry| {
-                let entry = entry.ok()?;
-                let path = entry.path();
-                if !path.is_dir() {
-                    return None;
-                }
-                let name = path.file_name()?.to_string_lossy().to_string();
-                if is_skipped_dir(&name) {
-                    debug_print(verbose, &format!("Skipping directory: {}", path.display()));
-                    None
-                } else {
-                    Some(path)
-                }
-            })
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
             .collect(),
-        Err(e) => {
-            debug_print(verbose, &format!("Cannot read {}: {}", dir.display(), e));
-            return;
-        }
+        Err(_) => Vec::new(),
     };
+    names.sort();
 
-    subdirs.sort();
+    let profiles = std::iter::once(None).chain(names.iter().map(|n| Some(n.as_str())));
+    for profile in profiles {
+        let store = load_profile_store(profile, verbose);
+        let (bookmarks, history) = (store.bookmarks.len(), store.history.len());
+        let mut line = format!("{} {}",
+            profile.unwrap_or(DEFAULT_PROFILE).bright_white().bold(),
+            tr(Msg::ProfileCounts, &[&bookmarks, &history]).dimmed()
+        );
+        if profile == active_profile() {
+            line.push_str(&format!(" {}", tr(Msg::ActiveTag, &[]).green()));
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
 
-    let count = subdirs.len();
-    for (i, subdir) in subdirs.into_iter().enumerate() {
-        let is_last = i == count - 1;
-        let branch = if is_last { "└── " } else { "├── " };
-        let dir_name = subdir
-            .file_name()
-            .map(|n| n.to_string_lossy().to_string())
-            .unwrap_or_else(|| "?".to_string());
+fn copy_profile(from: &str, to: &str, verbose: bool) -> io::Result<()> {
+    let as_profile = |name: &str| -> Option<String> {
+        if name == DEFAULT_PROFILE {
+            None
+        } else if is_valid_profile_name(name) {
+            Some(name.to_string())
+        } else {
+            fail(1, tr(Msg::InvalidProfileName, &[&name]).red())
+        }
+    };
+    let source = as_profile(from);
+    let dest = as_profile(to);
+    debug_print(verbose, &format!("Copying profile {} to {}", from, to));
 
-        items.push(TreeItem {
-            path: subdir.clone(),
-            label: format!("{}{}{}", prefix, branch, dir_name),
-        });
+    if source == dest {
+        fail(1, tr(Msg::ProfileSame, &[]).red());
+    }
+    if STATE_FILES.iter().all(|f| !state_path(source.as_deref(), *f).exists()) {
+        fail(1, tr(Msg::ProfileNotFound, &[&from]).red());
+    }
+    // Copying only seeds a new profile; it never overwrites one in use
+    if STATE_FILES.iter().any(|f| state_path(dest.as_deref(), *f).exists()) {
+        fail(1, tr(Msg::ProfileHasData, &[&to]).red());
+    }
 
-        let child_prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
-        collect_tree_items(&subdir, &child_prefix, depth + 1, max_depth, items, verbose);
+    for file in STATE_FILES {
+        let src = state_path(source.as_deref(), file);
+        if !src.exists() {
+            continue;
+        }
+        let dst = state_path(dest.as_deref(), file);
+        debug_print(verbose, &format!("Copying {} to {}", src.display(), dst.display()));
+        if let Some(parent) = dst.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_file_atomic(&dst, &fs::read(&src)?)?;
     }
+
+    println!("{}", tr(Msg::ProfileCopied, &[&from, &to]).green());
+    Ok(())
 }
 
-fn tree_select_directory(max_depth: usize, verbose: bool) -> io::Result<()> {
-    let current = std::env::current_dir()?;
-    debug_print(verbose, &format!(
-        "Building directory tree for: {} (max depth: {})",
-        current.display(),
-        max_depth
-    ));
+// Resolve a `cd` argument from shell history to an existing directory.
+// Relative arguments are tried against the home and current directories and
+// skipped if they resolve in neither or to different places in each.
+fn resolve_cd_target(arg: &str, home: &Path, cwd: &Path) -> Option<PathBuf> {
+    let candidates: Vec<PathBuf> = if arg == "~" {
+        vec![home.to_path_buf()]
+    } else if let Some(rest) = arg.strip_prefix("~/") {
+        vec![home.join(rest)]
+    } else if arg.starts_with('~') {
+        // ~otheruser
+        return None;
+    } else if Path::new(arg).is_absolute() {
+        vec![PathBuf::from(arg)]
+    } else {
+        vec![home.join(arg), cwd.join(arg)]
+    };
 
-    let mut items = Vec::new();
-    collect_tree_items(&current, "", 0, max_depth, &mut items, verbose);
+    let mut resolved: Vec<PathBuf> = candidates
+        .iter()
+        .filter(|p| p.is_dir())
+        .filter_map(|p| fs::canonicalize(p).ok())
+        .collect();
+    resolved.dedup();
+    match resolved.len() {
+        1 => resolved.pop(),
+        _ => None,
+    }
+}
 
-    if items.is_empty() {
-        eprintln!("{}", "No subdirectories found.".yellow());
-        std::process::exit(1);
+// The shell named on the command line, or the login shell from $SHELL
+fn detect_shell(shell: Option<&str>) -> shell_history::Shell {
+    let shell_name = shell
+        .map(str::to_string)
+        .or_else(|| std::env::var("SHELL").ok())
+        .unwrap_or_default();
+    match shell_history::Shell::from_name(&shell_name) {
+        Some(shell) => shell,
+        None => fail(1, tr(Msg::UnknownShell, &[&shell_name]).red()),
     }
+}
 
-    debug_print(verbose, &format!("Found {} directories in tree", items.len()));
+fn import_shell_history(shell: Option<&str>, file: Option<&str>, verbose: bool) -> io::Result<()> {
+    let shell = detect_shell(shell);
+    let home = dirs::home_dir().expect("Could not find home directory");
+    let cwd = std::env::current_dir()?;
+    let file = match file {
+        Some(file) => PathBuf::from(file),
+        None => home.join(shell.default_history_file()),
+    };
+    debug_print(verbose, &format!("Importing {} history from: {}", shell.name(), file.display()));
 
-    let prompt = format!("{}", current.display());
-    let selection = inquire::Select::new(&prompt, items)
-        .with_page_size(20)
-        .with_help_message("↑↓ to move, enter to select, type to filter, esc to cancel")
-        .prompt();
+    let content = match fs::read(&file) {
+        Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
+        Err(e) => fail(1, tr(Msg::CannotReadFile, &[&file.display(), &e]).red()),
+    };
 
-    match selection {
-        Ok(item) => {
-            debug_print(verbose, &format!("Selected directory: {}", item.path.display()));
-            add_to_history(item.path.clone(), verbose)?;
-            write_target_file(&item.path, verbose)?;
-            Ok(())
+    let targets = shell_history::cd_targets(shell, &content);
+    debug_print(verbose, &format!("Found {} cd commands", targets.len()));
+
+    // Walk the history newest first so the result is most-recent-first
+    let mut imported: Vec<PathBuf> = Vec::new();
+    let mut skipped = 0;
+    for arg in targets.iter().rev() {
+        match resolve_cd_target(arg, &home, &cwd) {
+            Some(dir) if !imported.contains(&dir) => imported.push(dir),
+            Some(_) => {}
+            None => {
+                debug_print(verbose, &format!("Skipping unresolvable cd target: {}", arg));
+                skipped += 1;
+            }
         }
-        Err(inquire::InquireError::OperationCanceled)
-        | Err(inquire::InquireError::OperationInterrupted) => {
-            debug_print(verbose, "Selection cancelled");
-            std::process::exit(1);
+    }
+
+    // Existing history stays in front; imported entries fill the remaining slots
+    let mut history = load_history(verbose);
+    let before = history.len();
+    for dir in imported {
+        if history.len() >= history_max() {
+            break;
         }
-        Err(e) => {
-            eprintln!("{}", format!("Selection error: {}", e).red());
-            std::process::exit(1);
+        if !history.contains(&dir) {
+            history.push(dir);
         }
     }
+    let added = history.len() - before;
+    save_history(&history, verbose)?;
+
+    println!("{}", tr(Msg::ImportedHistory, &[&added, &shell.name(), &skipped]).green());
+    Ok(())
 }
 
-fn find_directory_by_name(name: &str, verbose: bool) -> io::Result<()> {
-    let current = std::env::current_dir()?;
-    debug_print(verbose, &format!("Searching for directory: '{}'", name));
-    debug_print(verbose, &format!("Current directory: {}", current.display()));
-    
-    // First, check bookmarks
-    debug_print(verbose, "Searching in bookmarks");
-    let bookmarks = load_bookmarks(verbose);
-    for bookmark in bookmarks {
-        if let Some(dir_name) = bookmark.file_name() {
-            if dir_name.to_string_lossy() == name {
-                debug_print(verbose, &format!("Found in bookmarks: {}", bookmark.display()));
-                if bookmark.exists() {
-                    add_to_history(bookmark.clone(), verbose)?;
-                    write_target_file(&bookmark, verbose)?;
-                    return Ok(());
-                } else {
-                    debug_print(verbose, "Bookmark exists but directory does not");
-                }
-            }
+fn print_init_script(shell: Option<&str>, verbose: bool) -> io::Result<()> {
+    let shell = detect_shell(shell);
+    debug_print(verbose, &format!("Printing {} integration", shell.name()));
+    print!("{}", shell_init::init_script(shell));
+    Ok(())
+}
+
+// Add (or with `remove`, strip) the block loading the wrapper from the shell's
+// rc file. The file is backed up first and rewritten in place, so rc files
+// that are symlinks into a dotfiles repository stay symlinks.
+fn install_integration(shell: Option<&str>, remove: bool, verbose: bool) -> io::Result<()> {
+    let shell = detect_shell(shell);
+    let rc_path = dirs::home_dir()
+        .expect("Could not find home directory")
+        .join(shell_init::rc_file(shell));
+    debug_print(verbose, &format!("Shell: {}, rc file: {}", shell.name(), rc_path.display()));
+
+    let content = match fs::read_to_string(&rc_path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => fail(1, tr(Msg::CannotReadFile, &[&rc_path.display(), &e]).red()),
+    };
+
+    let new_content = if remove {
+        match shell_init::strip_block(&content) {
+            Some(stripped) => stripped,
+            None => fail(1, tr(Msg::IntegrationNotInstalled, &[&rc_path.display()]).red()),
         }
-    }
-    
-    // Then check subdirectories of current directory
-    debug_print(verbose, "Searching in current directory subdirectories");
-    if let Ok(entries) = fs::read_dir(&current) {
-        for entry in entries {
-            if let Ok(entry) = entry {
-                let path = entry.path();
-                if path.is_dir() {
-                    if let Some(dir_name) = path.file_name() {
-                        if dir_name.to_string_lossy() == name {
-                            debug_print(verbose, &format!("Found in subdirectories: {}", path.display()));
-                            add_to_history(path.clone(), verbose)?;
-                            write_target_file(&path, verbose)?;
-                            return Ok(());
-                        }
-                    }
-                }
+    } else {
+        if shell_init::has_block(&content) {
+            fail(1, tr(Msg::IntegrationAlreadyInstalled, &[&rc_path.display()]).red());
+        }
+        let mut new_content = content.clone();
+        if !new_content.is_empty() {
+            if !new_content.ends_with('\n') {
+                new_content.push('\n');
             }
+            new_content.push('\n');
         }
+        new_content.push_str(&shell_init::install_block(shell));
+        new_content
+    };
+
+    if rc_path.exists() {
+        let mut backup = rc_path.clone().into_os_string();
+        backup.push(".changedir-backup");
+        debug_print(verbose, &format!("Backing up to: {}", PathBuf::from(&backup).display()));
+        fs::copy(&rc_path, &backup)?;
+    } else if let Some(parent) = rc_path.parent() {
+        fs::create_dir_all(parent)?;
     }
-    
-    // Check parent directories recursively (limited depth)
-    debug_print(verbose, "Searching in parent directories");
-    let mut search_path = current.clone();
-    for depth in 0..5 {
-        if let Some(parent) = search_path.parent() {
-            search_path = parent.to_path_buf();
-            let candidate = search_path.join(name);
-            debug_print(verbose, &format!("Checking at depth {}: {}", depth + 1, candidate.display()));
-            if candidate.exists() && candidate.is_dir() {
-                debug_print(verbose, &format!("Found in parent directories: {}", candidate.display()));
-                add_to_history(candidate.clone(), verbose)?;
-                write_target_file(&candidate, verbose)?;
-                return Ok(());
+    fs::write(&rc_path, new_content)?;
+
+    if remove {
+        println!("{}", tr(Msg::IntegrationRemoved, &[&rc_path.display()]).green());
+    } else {
+        println!("{}", tr(Msg::IntegrationInstalled, &[&rc_path.display()]).green());
+    }
+    println!("{}", tr(Msg::RestartShell, &[]));
+    Ok(())
+}
+
+// Report on the setup: shell integration and the state files in use
+fn doctor(verbose: bool) -> io::Result<()> {
+    let home = dirs::home_dir().expect("Could not find home directory");
+    let ok = "✓".green().bold();
+    let bad = "✗".red().bold();
+
+    let shell_name = std::env::var("SHELL").unwrap_or_default();
+    match shell_history::Shell::from_name(&shell_name) {
+        Some(shell) => {
+            let rc_path = home.join(shell_init::rc_file(shell));
+            debug_print(verbose, &format!("Checking rc file: {}", rc_path.display()));
+            let installed = fs::read_to_string(&rc_path).is_ok_and(|content| shell_init::has_block(&content));
+            if installed {
+                println!("{} {}", ok, tr(Msg::DoctorIntegrationInstalled, &[&shell.name(), &rc_path.display()]));
+            } else {
+                println!("{} {}", bad, tr(Msg::DoctorIntegrationMissing, &[&shell.name(), &rc_path.display()]));
             }
-        } else {
-            debug_print(verbose, "Reached root directory");
-            break;
         }
+        None => println!("{} {}", bad, tr(Msg::UnknownShell, &[&shell_name])),
     }
-    
-    debug_print(verbose, "Directory not found in any location");
-    eprintln!("{}", format!("Directory not found: {}", name).red());
-    std::process::exit(1);
+
+    let store = load_store(verbose);
+    let (bookmarks, history) = (store.bookmarks.len(), store.history.len());
+    println!("{} {} {}",
+        ok,
+        active_profile().unwrap_or(DEFAULT_PROFILE).bright_white().bold(),
+        tr(Msg::ProfileCounts, &[&bookmarks, &history]).dimmed()
+    );
+
+    let target_dir = get_target_path().parent().map(Path::to_path_buf).unwrap_or_default();
+    let writable = fs::create_dir_all(&target_dir).is_ok()
+        && fs::metadata(&target_dir).is_ok_and(|m| !m.permissions().readonly());
+    if writable {
+        println!("{} {}", ok, tr(Msg::DoctorTargetWritable, &[&target_dir.display()]));
+    } else {
+        println!("{} {}", bad, tr(Msg::DoctorTargetNotWritable, &[&target_dir.display()]));
+    }
+    Ok(())
 }
 
 fn print_current_directory(verbose: bool) {
@@ -755,25 +4769,76 @@ fn print_current_directory(verbose: bool) {
         },
         Err(e) => {
             debug_print(verbose, &format!("Error getting current directory: {}", e));
-            eprintln!("{}", format!("Error getting current directory: {}", e).red());
-            std::process::exit(1);
+            fail(1, tr(Msg::CurrentDirError, &[&e]).red());
         }
     }
 }
 
+// Colour is off with --no-color or NO_COLOR, and when stdout is not a
+// terminal unless CLICOLOR_FORCE asks for it. `color = false` in the config
+// file turns it off too, short of CLICOLOR_FORCE. Decided before anything is
+// printed, so it is read straight from the arguments like --profile.
+fn configure_color(verbose: bool) {
+    // NO_COLOR counts with any value, CLICOLOR_FORCE with any but 0
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+    let force = std::env::var_os("CLICOLOR_FORCE").is_some_and(|v| !v.is_empty() && v != "0");
+    let enabled = if std::env::args().any(|arg| arg == "--no-color") || no_color {
+        false
+    } else {
+        force || (settings().color != Some(false) && io::stdout().is_terminal())
+    };
+    debug_print(verbose, &format!("Colour output {}", if enabled { "enabled" } else { "disabled" }));
+    colored::control::set_override(enabled);
+}
+
 fn main() {
+    // A wrapper passes its own arguments along so a --profile among them is
+    // taken into account. Answered before anything else, since the config
+    // (and its warnings) is read again by the run that follows.
+    if std::env::args().nth(1).as_deref() == Some("--print-target-path") {
+        let _ = PROFILE.set(early_profile());
+        if let Err(e) = print_target_path() {
+            eprintln!("{}", tr(Msg::Error, &[&e]).red());
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Delete target file on startup if it exists
     // Check for verbose flag early to pass to delete_target_file
     let early_verbose = std::env::args().any(|arg| arg == "-v" || arg == "--verbose");
+    let _ = CONFIG.set(load_config(early_verbose));
+    messages::init_language(settings().language);
+    configure_color(early_verbose);
+    let profile = early_profile();
+    if let Some(name) = &profile {
+        if !is_valid_profile_name(name) {
+            eprintln!("{}", tr(Msg::InvalidProfileName, &[name]).red());
+            std::process::exit(1);
+        }
+        debug_print(early_verbose, &format!("Using profile: {}", name));
+    }
+    let _ = PROFILE.set(profile);
+    // CHANGEDIR_HISTORY_MAX is the older name for the history size
+    let history_var = if std::env::var_os("CHANGEDIR_HISTORY_SIZE").is_some() {
+        "CHANGEDIR_HISTORY_SIZE"
+    } else {
+        "CHANGEDIR_HISTORY_MAX"
+    };
+    let history_default = settings().history_max.map_or(DEFAULT_HISTORY_MAX, |n| n.min(SLOT_COUNT));
+    let _ = HISTORY_MAX.set(limit_from_env(history_var, history_default, 0, early_verbose));
+    let _ = MAX_BOOKMARKS.set(limit_from_env("CHANGEDIR_MAX_BOOKMARKS", DEFAULT_MAX_BOOKMARKS, 1, early_verbose));
     if let Err(e) = delete_target_file(early_verbose) {
-        eprintln!("Warning: Could not delete target file: {}", e);
+        eprintln!("{}", tr(Msg::TargetDeleteWarning, &[&e]));
     }
+    install_signal_handlers(early_verbose);
     
     // Build the command definition
     let cmd = Command::new("changeDir")
         .version(env!("CARGO_PKG_VERSION"))
         .author(env!("CARGO_PKG_AUTHORS"))
         .about("Intelligent directory bookmarking and navigation")
+        .after_help(EXIT_CODES_HELP)
         .long_about(format!(
             "Intelligent directory bookmarking and navigation\n\nAuthor: {}\nVersion: {}",
             env!("CARGO_PKG_AUTHORS"),
@@ -784,59 +4849,417 @@ fn main() {
             .long("list")
             .action(clap::ArgAction::SetTrue)
             .help("List all bookmarked directories"))
+        .arg(Arg::new("recent")
+            .long("recent")
+            .action(clap::ArgAction::SetTrue)
+            .help("List history by time of visit, with how long ago each was"))
+        .arg(Arg::new("stats")
+            .long("stats")
+            .value_name("N")
+            .num_args(0..=1)
+            .default_missing_value("10")
+            .value_parser(clap::value_parser!(usize))
+            .help("Summarize bookmarks and history, then show the N most visited directories (default 10)"))
+        .arg(Arg::new("porcelain")
+            .long("porcelain")
+            .action(clap::ArgAction::SetTrue)
+            .requires("list")
+            .conflicts_with("json")
+            .help("With --list: print one tab-separated line per entry (slot, kind, path) for scripts"))
+        .arg(Arg::new("json")
+            .long("json")
+            .action(clap::ArgAction::SetTrue)
+            .requires("json-output")
+            .help("With --list or --stats: print the entries as JSON for scripts"))
+        .group(clap::ArgGroup::new("json-output")
+            .args(["list", "stats"])
+            .multiple(true))
         .arg(Arg::new("bookmark")
             .long("bookmark")
-            .action(clap::ArgAction::SetTrue)
-            .help("Bookmark the current directory"))
+            .num_args(0..=1)
+            .value_name("NAME|PATH")
+            .help("Bookmark the current directory, or PATH (e.g. ~/src/foo or ./foo); a NAME labels it like --name"))
+        .arg(Arg::new("temp")
+            .long("temp")
+            .num_args(0..=1)
+            .value_name("DURATION")
+            .default_missing_value(DEFAULT_TEMP_DURATION)
+            .requires("bookmark")
+            .help("With --bookmark: expire the bookmark after DURATION (e.g. 90m, 8h, 3d; default 24h)"))
+        .arg(Arg::new("relative-to")
+            .long("relative-to")
+            .value_name("BASE")
+            .requires("bookmark")
+            .help("With --bookmark: store the path relative to BASE, resolved again wherever it is used"))
+        .arg(Arg::new("name")
+            .long("name")
+            .value_name("LABEL")
+            .requires("bookmark")
+            .help("With --bookmark: label the bookmark so it can be chosen by name"))
+        .arg(Arg::new("note")
+            .long("note")
+            .value_name("TEXT")
+            .requires("bookmark")
+            .help("With --bookmark: a short description shown in listings"))
+        .arg(Arg::new("promote")
+            .long("promote")
+            .value_name("LETTER")
+            .help("Bookmark the history entry at LETTER"))
+        .arg(Arg::new("annotate")
+            .long("annotate")
+            .num_args(2)
+            .value_names(["LETTER", "TEXT"])
+            .help("Set the description of a bookmark (empty TEXT clears it)"))
+        .arg(Arg::new("tag")
+            .long("tag")
+            .value_name("TAG")
+            .action(clap::ArgAction::Append)
+            .help("With --bookmark: tag the bookmark (repeatable); with -l or -c: only show bookmarks with this tag"))
+        .arg(Arg::new("pin")
+            .long("pin")
+            .num_args(0..=1)
+            .value_name("LETTER")
+            .help("Make a temporary bookmark permanent (current directory, or by letter)"))
         .arg(Arg::new("forget")
             .short('f')
             .long("forget")
+            .num_args(0..=1)
+            .value_name("LETTER")
+            .help("Forget the current directory if bookmarked, or the bookmark with this letter (same as --remove)"))
+        .arg(Arg::new("toggle")
+            .long("toggle")
             .action(clap::ArgAction::SetTrue)
-            .help("Forget the current directory if bookmarked"))
+            .help("Bookmark the current directory, or forget it if it is already bookmarked"))
+        .arg(Arg::new("move")
+            .long("move")
+            .num_args(2)
+            .value_names(["FROM", "TO"])
+            .help("Move a bookmark to another position (e.g. --move k 0)"))
+        .arg(Arg::new("remove")
+            .long("remove")
+            .value_name("LETTER")
+            .help("Remove the bookmark with this letter (or label)"))
+        .arg(Arg::new("prune-interactive")
+            .long("prune-interactive")
+            .action(clap::ArgAction::SetTrue)
+            .help("Pick several bookmarks from the list and remove them at once"))
+        .arg(Arg::new("clean")
+            .long("clean")
+            .action(clap::ArgAction::SetTrue)
+            .help("Remove bookmarks and history entries whose directory no longer exists"))
+        .arg(Arg::new("dry-run")
+            .long("dry-run")
+            .action(clap::ArgAction::SetTrue)
+            .requires("missing-cleanup")
+            .conflicts_with("stale")
+            .help("With --clean, --prune or --prune-history: only report what would be removed"))
         .arg(Arg::new("forget-all")
             .short('F')
             .long("forget-all")
             .action(clap::ArgAction::SetTrue)
             .help("Forget all bookmarked directories"))
+        .arg(Arg::new("clear-history")
+            .long("clear-history")
+            .action(clap::ArgAction::SetTrue)
+            .help("Forget the directory history (with -F: reset everything)"))
+        .arg(Arg::new("stale")
+            .long("stale")
+            .num_args(0..=1)
+            .value_name("DAYS")
+            .default_missing_value(DEFAULT_STALE_DAYS)
+            .help("List bookmarks not visited in DAYS days (default 30) and offer to remove them"))
+        .arg(Arg::new("prune")
+            .long("prune")
+            .action(clap::ArgAction::SetTrue)
+            .help("Remove bookmarks whose directory no longer exists; with --stale: remove all stale bookmarks except pinned ones"))
+        .arg(Arg::new("prune-history")
+            .long("prune-history")
+            .action(clap::ArgAction::SetTrue)
+            .help("Remove history entries not visited for a while (see --older-than)"))
+        .arg(Arg::new("older-than")
+            .long("older-than")
+            .value_name("DURATION")
+            .requires("prune-history")
+            .help("With --prune-history: how long since the last visit, e.g. 36h, 30d, 8w (default 30d)"))
+        .group(clap::ArgGroup::new("missing-cleanup")
+            .args(["clean", "prune", "prune-history"])
+            .multiple(true))
+        .arg(Arg::new("init_shell")
+            .long("init")
+            .value_name("SHELL")
+            .value_parser(["bash", "zsh", "fish", "powershell"])
+            .help("Print the shell wrapper function (same as the init subcommand)"))
+        .arg(Arg::new("print-target-path")
+            .long("print-target-path")
+            .action(clap::ArgAction::SetTrue)
+            .help("Print where the directory for the shell wrapper is written"))
+        .arg(Arg::new("export")
+            .long("export")
+            .value_name("FILE")
+            .num_args(0..=1)
+            .help("Write bookmarks and history as JSON to FILE (default or - for stdout)"))
+        .arg(Arg::new("bookmarks-only")
+            .long("bookmarks-only")
+            .action(clap::ArgAction::SetTrue)
+            .requires("export")
+            .help("With --export: leave the history out"))
+        .arg(Arg::new("import")
+            .long("import")
+            .value_name("FILE")
+            .help("Add the bookmarks from an --export file or a bookmark file (- for stdin)"))
+        .arg(Arg::new("replace")
+            .long("replace")
+            .action(clap::ArgAction::SetTrue)
+            .requires("import")
+            .help("With --import: replace the current bookmarks and history instead of merging"))
+        .arg(Arg::new("yes")
+            .short('y')
+            .long("yes")
+            .action(clap::ArgAction::SetTrue)
+            .help("Assume yes for confirmation prompts"))
+        .arg(Arg::new("boost")
+            .long("boost")
+            .value_name("LETTER|PATH")
+            .conflicts_with("demote")
+            .help("Raise an entry's frecency rank (doubles its score, up to x8)"))
+        .arg(Arg::new("demote")
+            .long("demote")
+            .value_name("LETTER|PATH")
+            .help("Lower an entry's frecency rank (halves its score, down to x1/8)"))
+        .arg(Arg::new("reset")
+            .long("reset")
+            .action(clap::ArgAction::SetTrue)
+            .requires("boost")
+            .help("With --boost: clear the manual rank adjustment of the entry"))
+        .arg(Arg::new("sort")
+            .long("sort")
+            .value_name("ORDER")
+            .value_parser(["recent", "frecency", "name", "path"])
+            .help("Order of listings and choosers: recent or frecency for history (recent also orders bookmarks by last visit), name or path for bookmarks"))
+        .arg(Arg::new("persist")
+            .long("persist")
+            .action(clap::ArgAction::SetTrue)
+            .requires("sort")
+            .help("With --sort: store the bookmarks in that order"))
         .arg(Arg::new("choose")
             .short('c')
             .long("choose")
             .num_args(0..=1)
-            .help("Choose a directory from bookmarks (with optional letter)"))
+            .help("Choose a directory from bookmarks (with optional letter or bookmark label)"))
+        .arg(Arg::new("fzf")
+            .long("fzf")
+            .action(clap::ArgAction::SetTrue)
+            .overrides_with("no-fzf")
+            .help("With -c: choose in fzf (the default when fzf is installed and stdin is a terminal)"))
+        .arg(Arg::new("no-fzf")
+            .long("no-fzf")
+            .action(clap::ArgAction::SetTrue)
+            .overrides_with("fzf")
+            .help("With -c: use the built-in chooser even if fzf is installed"))
+        .arg(Arg::new("preview")
+            .long("preview")
+            .action(clap::ArgAction::SetTrue)
+            .help("With -c: list the subdirectories of the highlighted entry in the menu"))
+        .arg(Arg::new("default")
+            .long("default")
+            .value_name("LETTER")
+            .help("With -c: the entry chosen by pressing Enter at the prompt (default 0)"))
+        .arg(Arg::new("history")
+            .short('H')
+            .long("history")
+            .num_args(0..=1)
+            .value_name("LETTER")
+            .help("Choose a directory from history only (with optional letter)"))
         .arg(Arg::new("back")
             .short('b')
             .long("back")
+            .value_name("N")
+            .num_args(0..=1)
+            .default_missing_value("1")
+            .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+            .help("Go back N jumps in this shell (default 1), or to the Nth history entry"))
+        .arg(Arg::new("forward")
+            .long("forward")
             .action(clap::ArgAction::SetTrue)
-            .help("Change to the previous directory"))
+            .help("Go forward again after -b"))
         .arg(Arg::new("up")
             .short('u')
             .long("up")
+            .value_name("N|NAME")
+            .num_args(0..=1)
+            .default_missing_value("1")
+            .help("Change up N directory levels (default 1), or up to the nearest parent named NAME"))
+        .arg(Arg::new("mkdir")
+            .long("mkdir")
+            .value_name("PATH")
+            .help("Create a directory (and its parents) and change to it"))
+        .arg(Arg::new("root")
+            .short('r')
+            .long("root")
             .action(clap::ArgAction::SetTrue)
-            .help("Change up one directory level"))
+            .help("Change to the root of the enclosing git repository (see CHANGEDIR_ROOT_MARKERS)"))
         .arg(Arg::new("down")
             .short('d')
             .long("down")
+            .value_name("NAME")
+            .num_args(0..=1)
+            .help("List and select a subdirectory, or enter subdirectory NAME directly"))
+        .arg(Arg::new("search")
+            .long("search")
+            .value_name("TERM")
+            .help("Find directories below the current one whose name contains TERM"))
+        .arg(Arg::new("max-depth")
+            .long("max-depth")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize))
+            .help("With --search: how many levels down to look (default 5)"))
+        .arg(Arg::new("hidden")
+            .long("hidden")
             .action(clap::ArgAction::SetTrue)
-            .help("List and select a subdirectory"))
+            .requires("search")
+            .help("With --search: also look inside hidden directories"))
         .arg(Arg::new("tree")
             .short('t')
             .long("tree")
             .num_args(0..=1)
             .value_name("DEPTH")
-            .help("Show a directory tree and select one interactively (optional max depth, default 3)"))
+            .help("Show a directory tree and select one interactively (optional max depth, default 3); with --list, group entries by shared parent"))
+        .arg(Arg::new("copy")
+            .long("copy")
+            .action(clap::ArgAction::SetTrue)
+            .help("Copy the selected directory to the clipboard instead of changing to it"))
+        .arg(Arg::new("open")
+            .long("open")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with_all(["copy", "stdout", "eval"])
+            .help("Open the selected directory in the file manager instead of changing to it"))
+        .arg(Arg::new("stdout")
+            .long("stdout")
+            .visible_alias("print")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with_all(["copy", "eval"])
+            .help("Print the selected directory instead of writing the target file"))
+        .arg(Arg::new("eval")
+            .long("eval")
+            .action(clap::ArgAction::SetTrue)
+            .conflicts_with("copy")
+            .help("Print a cd command for the selected directory, for use with eval"))
+        .arg(Arg::new("absolute")
+            .long("absolute")
+            .action(clap::ArgAction::SetTrue)
+            .help("Always emit absolute paths (never shorten to a relative path)"))
+        .arg(Arg::new("target-format")
+            .long("target-format")
+            .value_name("FORMAT")
+            .value_parser(["unix", "native"])
+            .default_value("native")
+            .help("Path separators in the target file: unix (forward slashes) or native"))
+        .arg(Arg::new("no-history")
+            .long("no-history")
+            .action(clap::ArgAction::SetTrue)
+            .help("Do not record the selected directory in history"))
+        .arg(Arg::new("no-pager")
+            .long("no-pager")
+            .action(clap::ArgAction::SetTrue)
+            .help("Never page long listings through $PAGER"))
         .arg(Arg::new("verbose")
             .short('v')
             .long("verbose")
             .action(clap::ArgAction::SetTrue)
+            .global(true)
             .help("Enable verbose/debug output"))
         .arg(Arg::new("change-dir")
             .short('D')
             .value_name("DIR")
             .num_args(1)
             .help("Change to directory by name"))
+        .arg(Arg::new("which")
+            .long("which")
+            .value_name("NAME")
+            .help("Print where NAME would change to, and where it was found, without changing directory"))
+        .arg(Arg::new("exact")
+            .long("exact")
+            .action(clap::ArgAction::SetTrue)
+            .help("When changing to a directory by name, only accept names spelled exactly (same case, no fuzzy matching)"))
+        .arg(Arg::new("depth")
+            .long("depth")
+            .value_name("N")
+            .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+            .help("When changing to a directory by name: how many levels down to look (default 3)"))
+        .arg(Arg::new("up-depth")
+            .long("up-depth")
+            .value_name("N")
+            .value_parser(clap::value_parser!(usize))
+            .help("When changing to a directory by name: how many parent levels to look through (default 5)"))
+        .arg(Arg::new("canonicalize")
+            .long("canonicalize")
+            .action(clap::ArgAction::SetTrue)
+            .overrides_with("no-canonicalize")
+            .help("Store bookmark and history paths with symlinks and .. resolved (the default)"))
+        .arg(Arg::new("no-canonicalize")
+            .long("no-canonicalize")
+            .action(clap::ArgAction::SetTrue)
+            .overrides_with("canonicalize")
+            .help("Store bookmark and history paths as given"))
+        .arg(Arg::new("portable")
+            .long("portable")
+            .action(clap::ArgAction::SetTrue)
+            .overrides_with("no-portable")
+            .help("Store paths below the home directory as ~/..., for a store synced between machines"))
+        .arg(Arg::new("no-portable")
+            .long("no-portable")
+            .action(clap::ArgAction::SetTrue)
+            .overrides_with("portable")
+            .help("Store full paths (the default)"))
+        .arg(Arg::new("no-color")
+            .long("no-color")
+            .action(clap::ArgAction::SetTrue)
+            .global(true)
+            .help("Disable coloured output (also NO_COLOR; off automatically when stdout is not a terminal)"))
+        .arg(Arg::new("profile")
+            .long("profile")
+            .value_name("NAME")
+            .global(true)
+            .help("Use a separate set of bookmarks and history (also CHANGEDIR_PROFILE)"))
         .arg(Arg::new("directory")
-            .help("Single letter: stored directory to change to; word: directory name to change to")
-            .index(1));
+            .help("Single letter: stored directory to change to; word: directory name to change to; pattern (e.g. 'proj*'): directory name to match")
+            .index(1))
+        .subcommand(Command::new("profile")
+            .about("Manage profiles")
+            .subcommand_required(true)
+            .subcommand(Command::new("list")
+                .about("List profiles with their bookmark and history counts"))
+            .subcommand(Command::new("copy")
+                .about("Seed a new profile from an existing one")
+                .arg(Arg::new("from").required(true).value_name("FROM"))
+                .arg(Arg::new("to").required(true).value_name("TO"))))
+        .subcommand(Command::new("import")
+            .about("Import data from other tools")
+            .subcommand_required(true)
+            .subcommand(Command::new("shell-history")
+                .about("Seed history from the cd commands in a shell history file")
+                .arg(Arg::new("shell")
+                    .value_parser(["bash", "zsh", "fish", "powershell"])
+                    .help("Shell whose history to read (default: from $SHELL)"))
+                .arg(Arg::new("file")
+                    .value_name("PATH")
+                    .help("History file (default: the shell's usual location)"))))
+        .subcommand(Command::new("init")
+            .about("Print the shell wrapper function")
+            .arg(Arg::new("shell")
+                .value_parser(["bash", "zsh", "fish", "powershell"])
+                .help("Shell to generate the function for (default: from $SHELL)")))
+        .subcommand(Command::new("install")
+            .about("Load the shell wrapper from your shell's rc file")
+            .arg(Arg::new("shell")
+                .value_parser(["bash", "zsh", "fish", "powershell"])
+                .help("Shell to install for (default: from $SHELL)"))
+            .arg(Arg::new("remove")
+                .long("remove")
+                .action(clap::ArgAction::SetTrue)
+                .help("Remove the block added by install")))
+        .subcommand(Command::new("doctor")
+            .about("Check the shell integration and state files"));
 
     // Check for -? help flag
     let args: Vec<String> = std::env::args().collect();
@@ -847,58 +5270,275 @@ fn main() {
 
     let matches = cmd.get_matches();
     let verbose = matches.get_flag("verbose");
+    let delivery = if matches.get_flag("copy") {
+        Delivery::Copy
+    } else if matches.get_flag("open") {
+        Delivery::Open
+    } else if matches.get_flag("stdout") {
+        Delivery::Stdout
+    } else if matches.get_flag("eval") {
+        Delivery::Eval
+    } else {
+        Delivery::TargetFile
+    };
+    let tags: Vec<String> = matches
+        .get_many::<String>("tag")
+        .map(|tags| tags.cloned().collect())
+        .unwrap_or_default();
+    let default_slot = match matches.get_one::<String>("default") {
+        Some(letter) => {
+            let mut chars = letter.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) if get_index_from_char(ch).is_some() => Some(ch),
+                _ => fail(1, tr(Msg::InvalidDirectoryLetter, &[&letter]).red()),
+            }
+        }
+        None => settings().default_slot.filter(|&ch| get_index_from_char(ch).is_some()),
+    };
+    let literal_paths = if matches.get_flag("no-canonicalize") {
+        true
+    } else if matches.get_flag("canonicalize") {
+        false
+    } else {
+        settings().canonicalize == Some(false)
+    };
+    let portable = if matches.get_flag("portable") {
+        true
+    } else if matches.get_flag("no-portable") {
+        false
+    } else {
+        settings().portable == Some(true)
+    };
+    let _ = SELECTION_OPTIONS.set(SelectionOptions {
+        delivery,
+        sort_frecency: matches.get_one::<String>("sort").is_some_and(|s| s == "frecency"),
+        bookmark_order: match matches.get_one::<String>("sort").map(String::as_str) {
+            Some("name") => Some(BookmarkOrder::Name),
+            Some("path") => Some(BookmarkOrder::Path),
+            Some("recent") => Some(BookmarkOrder::Recent),
+            _ => None,
+        },
+        no_history: matches.get_flag("no-history"),
+        absolute: matches.get_flag("absolute"),
+        tag_filter: if matches.contains_id("bookmark") { Vec::new() } else { tags.clone() },
+        unix_target: matches.get_one::<String>("target-format").is_some_and(|f| f == "unix"),
+        default_slot,
+        literal_paths,
+        portable,
+        preview: matches.get_flag("preview"),
+        fzf: if matches.get_flag("fzf") {
+            Some(true)
+        } else if matches.get_flag("no-fzf") {
+            Some(false)
+        } else {
+            None
+        },
+    });
 
     if verbose {
         debug_print(verbose, "Verbose mode enabled");
         debug_print(verbose, &format!("Command arguments: {:?}", std::env::args().collect::<Vec<_>>()));
     }
 
-    let result = if matches.get_flag("list") {
-        list_bookmarks(verbose)
-    } else if matches.get_flag("bookmark") {
-        bookmark_current(verbose)
-    } else if matches.get_flag("forget") {
-        forget_current(verbose)
+    let name_search = NameSearch {
+        exact: matches.get_flag("exact"),
+        depth: matches.get_one::<usize>("depth").copied().unwrap_or(DEFAULT_NAME_DEPTH),
+        up_depth: matches
+            .get_one::<usize>("up-depth")
+            .copied()
+            .or(settings().up_depth)
+            .unwrap_or(DEFAULT_UP_DEPTH),
+    };
+    let result = if let Some(("profile", profile_matches)) = matches.subcommand() {
+        match profile_matches.subcommand() {
+            Some(("copy", copy_matches)) => copy_profile(
+                copy_matches.get_one::<String>("from").unwrap(),
+                copy_matches.get_one::<String>("to").unwrap(),
+                verbose,
+            ),
+            _ => list_profiles(verbose),
+        }
+    } else if let Some(("import", import_matches)) = matches.subcommand() {
+        match import_matches.subcommand() {
+            Some(("shell-history", history_matches)) => import_shell_history(
+                history_matches.get_one::<String>("shell").map(String::as_str),
+                history_matches.get_one::<String>("file").map(String::as_str),
+                verbose,
+            ),
+            _ => unreachable!("import requires a subcommand"),
+        }
+    } else if let Some(("init", init_matches)) = matches.subcommand() {
+        print_init_script(init_matches.get_one::<String>("shell").map(String::as_str), verbose)
+    } else if let Some(shell) = matches.get_one::<String>("init_shell") {
+        print_init_script(Some(shell), verbose)
+    } else if matches.get_flag("print-target-path") {
+        print_target_path()
+    } else if let Some(("install", install_matches)) = matches.subcommand() {
+        install_integration(
+            install_matches.get_one::<String>("shell").map(String::as_str),
+            install_matches.get_flag("remove"),
+            verbose,
+        )
+    } else if let Some(("doctor", _)) = matches.subcommand() {
+        doctor(verbose)
+    } else if matches.get_flag("persist") {
+        persist_bookmark_order(verbose)
+    } else if matches.contains_id("export") {
+        export_data(
+            matches.get_one::<String>("export").map(String::as_str),
+            !matches.get_flag("bookmarks-only"),
+            verbose,
+        )
+    } else if let Some(file) = matches.get_one::<String>("import") {
+        import_data(file, matches.get_flag("replace"), matches.get_flag("yes"), verbose)
+    } else if matches.get_flag("recent") {
+        list_recent(verbose)
+    } else if let Some(&limit) = matches.get_one::<usize>("stats") {
+        list_stats(limit, matches.get_flag("json"), verbose)
+    } else if matches.get_flag("list") {
+        if matches.get_flag("json") {
+            list_bookmarks_json(verbose)
+        } else if matches.get_flag("porcelain") {
+            list_bookmarks_porcelain(verbose)
+        } else {
+            list_bookmarks(matches.contains_id("tree"), matches.get_flag("no-pager"), verbose)
+        }
+    } else if matches.contains_id("bookmark") {
+        // Labels can never contain '/' or start with '~' or '.', so anything
+        // that looks like a path is one
+        let value = matches.get_one::<String>("bookmark").map(String::as_str);
+        let (path, label) = match value {
+            Some(v) if v.contains('/') || v.starts_with(['~', '.']) => (Some(v), None),
+            other => (None, other),
+        };
+        let label = match (label, matches.get_one::<String>("name").map(String::as_str)) {
+            (Some(a), Some(b)) if a != b => fail(1, tr(Msg::ConflictingLabels, &[&a, &b]).red()),
+            (label, name) => label.or(name),
+        };
+        bookmark_directory(
+            path,
+            matches.get_one::<String>("temp").map(String::as_str),
+            label,
+            &tags,
+            matches.get_one::<String>("note").map(String::as_str),
+            matches.get_one::<String>("relative-to").map(String::as_str),
+            verbose,
+        )
+    } else if matches.contains_id("pin") {
+        pin_bookmark(matches.get_one::<String>("pin").map(String::as_str), verbose)
+    } else if let Some(letter) = matches.get_one::<String>("promote") {
+        promote_history(letter, verbose)
+    } else if let Some(mut values) = matches.get_many::<String>("annotate") {
+        let letter = values.next().unwrap();
+        let text = values.next().unwrap();
+        annotate_bookmark(letter, text, verbose)
+    } else if let Some(mut letters) = matches.get_many::<String>("move") {
+        let from = letters.next().unwrap();
+        let to = letters.next().unwrap();
+        move_bookmark(from, to, verbose)
+    } else if let Some(letter) = matches.get_one::<String>("remove") {
+        remove_bookmark(letter, verbose)
+    } else if matches.contains_id("forget") {
+        match matches.get_one::<String>("forget") {
+            Some(letter) => remove_bookmark(letter, verbose),
+            None => forget_current(verbose),
+        }
+    } else if matches.get_flag("toggle") {
+        toggle_bookmark(verbose)
+    } else if matches.get_flag("prune-history") {
+        let duration = matches.get_one::<String>("older-than").map_or(DEFAULT_HISTORY_AGE, String::as_str);
+        let Some(max_age) = parse_duration(duration) else {
+            fail(1, tr(Msg::InvalidDuration, &[&duration]).red());
+        };
+        prune_history(max_age, matches.get_flag("dry-run"), verbose)
+    } else if matches.get_flag("clean") {
+        clean_missing(matches.get_flag("dry-run"), false, verbose)
+    } else if matches.get_flag("prune") && !matches.contains_id("stale") {
+        clean_missing(matches.get_flag("dry-run"), true, verbose)
+    } else if matches.get_flag("prune-interactive") {
+        prune_interactive(verbose)
+    } else if matches.get_flag("forget-all") && matches.get_flag("clear-history") {
+        match forget_all(matches.get_flag("yes"), verbose) {
+            Ok(true) => clear_history(verbose),
+            result => result.map(|_| ()),
+        }
     } else if matches.get_flag("forget-all") {
-        forget_all(verbose)
+        forget_all(matches.get_flag("yes"), verbose).map(|_| ())
+    } else if matches.get_flag("clear-history") {
+        clear_history(verbose)
+    } else if let Some(target) = matches.get_one::<String>("boost") {
+        adjust_boost(target, 1, matches.get_flag("reset"), verbose)
+    } else if let Some(target) = matches.get_one::<String>("demote") {
+        adjust_boost(target, -1, false, verbose)
+    } else if let Some(days) = matches.get_one::<String>("stale") {
+        let days = match days.parse::<u64>() {
+            Ok(d) => d,
+            Err(_) => fail(1, tr(Msg::InvalidDays, &[&days]).red()),
+        };
+        list_stale_bookmarks(days, matches.get_flag("prune"), matches.get_flag("yes"), verbose)
     } else if matches.contains_id("choose") {
         if let Some(letter) = matches.get_one::<String>("choose") {
             choose_directory_by_letter(letter, verbose)
         } else {
             choose_directory_interactive(verbose)
         }
-    } else if matches.get_flag("back") {
-        change_to_previous(verbose)
-    } else if matches.get_flag("up") {
-        change_up_one_level(verbose)
-    } else if matches.get_flag("down") {
-        list_subdirectories(verbose)
+    } else if matches.contains_id("history") {
+        choose_history(matches.get_one::<String>("history").map(String::as_str), verbose)
+    } else if let Some(&steps) = matches.get_one::<usize>("back") {
+        change_to_previous(steps, verbose)
+    } else if matches.get_flag("forward") {
+        change_to_next(verbose)
+    } else if let Some(up) = matches.get_one::<String>("up") {
+        match up.parse::<usize>() {
+            Ok(0) => fail(1, tr(Msg::InvalidLevels, &[&up]).red()),
+            Ok(levels) => change_up_levels(levels, verbose),
+            Err(_) => change_up_to(up, verbose),
+        }
+    } else if matches.get_flag("root") {
+        change_to_root(verbose)
+    } else if let Some(path) = matches.get_one::<String>("mkdir") {
+        make_and_change(path, verbose)
+    } else if let Some(term) = matches.get_one::<String>("search") {
+        let max_depth = matches
+            .get_one::<usize>("max-depth")
+            .copied()
+            .or(settings().search_depth)
+            .unwrap_or(DEFAULT_SEARCH_DEPTH);
+        search_command(term, max_depth, matches.get_flag("hidden"), verbose)
+    } else if matches.contains_id("down") {
+        match matches.get_one::<String>("down") {
+            Some(name) => change_down(name, verbose),
+            None => list_subdirectories(verbose),
+        }
     } else if matches.contains_id("tree") {
         let max_depth = match matches.get_one::<String>("tree") {
             Some(value) => match value.parse::<usize>() {
                 Ok(d) if d > 0 => d,
                 _ => {
-                    eprintln!("{}", format!("Invalid tree depth: {}", value).red());
-                    std::process::exit(1);
+                    fail(1, tr(Msg::InvalidTreeDepth, &[&value]).red());
                 }
             },
             None => DEFAULT_TREE_DEPTH,
         };
         tree_select_directory(max_depth, verbose)
+    } else if let Some(name) = matches.get_one::<String>("which") {
+        which_directory(name, &name_search, verbose)
     } else if let Some(dir_name) = matches.get_one::<String>("change-dir") {
-        find_directory_by_name(dir_name, verbose)
+        find_directory_by_name(dir_name, &name_search, verbose)
     } else if let Some(dir_name) = matches.get_one::<String>("directory") {
         // A single letter refers to a stored directory (as with -c);
-        // a longer word is treated as a directory name (as with -D).
+        // a longer word is treated as a directory name (as with -D),
+        // or a pattern to match names against if it has glob characters.
         let mut chars = dir_name.chars();
         match (chars.next(), chars.next()) {
             (Some(ch), None) if get_index_from_char(ch).is_some() => {
                 debug_print(verbose, &format!("Single letter '{}', selecting stored directory", ch));
                 choose_directory_by_letter(dir_name, verbose)
             }
+            _ if is_glob_pattern(dir_name) => change_to_glob(dir_name, verbose),
             _ => {
                 debug_print(verbose, &format!("Word '{}', searching for directory by name", dir_name));
-                find_directory_by_name(dir_name, verbose)
+                find_directory_by_name(dir_name, &name_search, verbose)
             }
         }
     } else {
@@ -907,8 +5547,115 @@ fn main() {
     };
 
     if let Err(e) = result {
-        eprintln!("{}", format!("Error: {}", e).red());
-        std::process::exit(1);
+        fail(1, tr(Msg::Error, &[&e]).red());
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+    const HOUR: u64 = 60 * 60;
+    const DAY: u64 = 24 * HOUR;
+
+    fn visit(count: u64, last: u64) -> Visit {
+        Visit { path: PathBuf::from("/tmp/x"), count, first: last, last, boost: 0 }
+    }
+
+    #[test]
+    fn frecency_prefers_recent_visits() {
+        let recent = visit(1, NOW - 10 * 60);
+        let old = visit(1, NOW - 30 * DAY);
+        assert!(frecency(&recent, NOW) > frecency(&old, NOW));
+    }
+
+    #[test]
+    fn frecency_orders_equal_times_by_count() {
+        let often = visit(5, NOW - 2 * HOUR);
+        let rarely = visit(2, NOW - 2 * HOUR);
+        assert!(frecency(&often, NOW) > frecency(&rarely, NOW));
+    }
+
+    #[test]
+    fn frecency_decays_with_age() {
+        let score = |age| frecency(&visit(4, NOW - age), NOW);
+        assert_eq!(score(0), 16.0);
+        assert_eq!(score(HOUR), 8.0);
+        assert_eq!(score(DAY), 2.0);
+        assert_eq!(score(7 * DAY), 1.0);
+        assert_eq!(score(365 * DAY), 1.0);
+    }
+
+    #[test]
+    fn frecency_counts_unvisited_entries_once() {
+        assert_eq!(frecency(&visit(0, NOW), NOW), frecency(&visit(1, NOW), NOW));
+        assert_eq!(frecency(&visit(0, 0), NOW), 0.25);
+        let boosted = Visit { boost: 2, ..visit(0, 0) };
+        assert_eq!(frecency(&boosted, NOW), 1.0);
+    }
+
+    #[test]
+    fn frecency_ignores_visits_in_the_future() {
+        assert_eq!(frecency(&visit(1, NOW + DAY), NOW), 4.0);
+    }
+
+    fn items<'a>(paths: &'a [PathBuf]) -> Vec<PickItem<'a>> {
+        paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| PickItem {
+                line: path.display().to_string(),
+                text: path.display().to_string(),
+                slot: Some(get_prefix_char(i)),
+                path,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn picker_letters_go_to_the_filter() {
+        use crossterm::event::{KeyCode, KeyModifiers};
+
+        for c in ['j', 'k', 'p', 'n', 'a', '3'] {
+            assert_eq!(pick_key(KeyCode::Char(c), KeyModifiers::NONE), PickKey::Type(c));
+        }
+        assert_eq!(pick_key(KeyCode::Char('p'), KeyModifiers::CONTROL), PickKey::Up);
+        assert_eq!(pick_key(KeyCode::Char('k'), KeyModifiers::CONTROL), PickKey::Up);
+        assert_eq!(pick_key(KeyCode::Char('n'), KeyModifiers::CONTROL), PickKey::Down);
+        assert_eq!(pick_key(KeyCode::Char('j'), KeyModifiers::CONTROL), PickKey::Down);
+        assert_eq!(pick_key(KeyCode::Up, KeyModifiers::NONE), PickKey::Up);
+        assert_eq!(pick_key(KeyCode::Down, KeyModifiers::NONE), PickKey::Down);
+        assert_eq!(pick_key(KeyCode::Char('c'), KeyModifiers::CONTROL), PickKey::Cancel);
+    }
+
+    #[test]
+    fn picker_selects_by_slot_letter() {
+        let paths: Vec<PathBuf> = ["/work/api", "/work/web", "/notes"].iter().map(PathBuf::from).collect();
+        let items = items(&paths);
+        // "2" matches none of the paths, but is the slot of /notes
+        let shown = filter_items(&items, "2");
+        assert_eq!(picked_entry(&items, &shown, 0, "2"), Some(2));
+        assert_eq!(picked_entry(&items, &filter_items(&items, "1"), 0, "1"), Some(1));
+    }
+
+    #[test]
+    fn picker_enter_takes_the_highlight_otherwise() {
+        let paths: Vec<PathBuf> = ["/work/api", "/work/web", "/notes"].iter().map(PathBuf::from).collect();
+        let items = items(&paths);
+        let shown = filter_items(&items, "web");
+        assert_eq!(picked_entry(&items, &shown, 0, "web"), Some(1));
+        assert_eq!(picked_entry(&items, &filter_items(&items, ""), 2, ""), Some(2));
+        assert_eq!(picked_entry(&items, &filter_items(&items, "zz"), 0, "zz"), None);
+    }
+
+    #[test]
+    fn picker_ignores_a_slot_letter_on_several_pages() {
+        let paths: Vec<PathBuf> = ["/a", "/b"].iter().map(PathBuf::from).collect();
+        let mut items = items(&paths);
+        items[1].slot = Some('0');
+        let shown = filter_items(&items, "0");
+        assert_eq!(picked_entry(&items, &shown, 0, "0"), shown.first().copied());
+    }
+}
@@ -0,0 +1,784 @@
+// User-facing message catalog.
+//
+// Every prompt, confirmation and error shown to the user has an ID here. Each
+// language is an exhaustive match over the IDs, so adding a message without
+// translating it for every catalog fails to compile rather than drifting out
+// of sync. Templates use "{}" placeholders filled in order by `tr`.
+
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Language {
+    En,
+    De,
+    Fr,
+}
+
+impl Language {
+    // Accepts "de", "de_DE.UTF-8", "fr-CA" and similar
+    pub fn from_code(code: &str) -> Option<Self> {
+        let lang = code
+            .split(['_', '-', '.', '@'])
+            .next()
+            .unwrap_or("")
+            .to_ascii_lowercase();
+        match lang.as_str() {
+            "en" => Some(Language::En),
+            "de" => Some(Language::De),
+            "fr" => Some(Language::Fr),
+            _ => None,
+        }
+    }
+
+    // Standard locale precedence: LC_ALL, then LC_MESSAGES, then LANG. None
+    // if the locale isn't one with a catalog (such as C or POSIX).
+    pub fn from_env() -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Language::from_code(&value))
+    }
+}
+
+static LANGUAGE: OnceLock<Language> = OnceLock::new();
+
+// Settle the language once the config file has been read
+pub fn init_language(configured: Option<Language>) {
+    let _ = LANGUAGE.set(choose_language(configured, Language::from_env()));
+}
+
+// An explicit ui.language wins over the locale, which wins over English
+fn choose_language(configured: Option<Language>, locale: Option<Language>) -> Language {
+    configured.or(locale).unwrap_or(Language::En)
+}
+
+// Messages printed while the config file is read come out in the locale's
+// language, as the config can't have been applied yet
+fn language() -> Language {
+    LANGUAGE
+        .get()
+        .copied()
+        .unwrap_or_else(|| Language::from_env().unwrap_or(Language::En))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Msg {
+    NoBookmarks,
+    SelectPrompt,
+    InvalidSelection,
+    InvalidDirectoryLetter,
+    InvalidBookmarkLetter,
+    InvalidDuration,
+    AlreadyBookmarked,
+    NotBookmarked,
+    MaxBookmarksReached,
+    Bookmarked,
+    BookmarkedTemp,
+    ExpiresIn,
+    Pinned,
+    RemovedBookmark,
+    AllBookmarksRemoved,
+    NoBookmarksToRemove,
+    NoHistory,
+    PreviousMissing,
+    AlreadyAtRoot,
+    NoSubdirectories,
+    TreeHelp,
+    SelectionError,
+    DirectoryNotFound,
+    CurrentDirError,
+    InvalidTreeDepth,
+    Error,
+    TargetDeleteWarning,
+    CopiedToClipboard,
+    ClipboardFailed,
+    NoStaleBookmarks,
+    LastVisited,
+    NeverVisited,
+    PinnedTag,
+    StaleRemovePrompt,
+    ConfirmRemoveStale,
+    NothingRemoved,
+    InvalidDays,
+    YesAnswer,
+    ProfileCounts,
+    ActiveTag,
+    InvalidProfileName,
+    ProfileSame,
+    ProfileNotFound,
+    ProfileHasData,
+    ProfileCopied,
+    UnknownShell,
+    CannotReadFile,
+    ImportedHistory,
+    BoostSet,
+    BoostLimit,
+    BoostReset,
+    IntegrationInstalled,
+    IntegrationAlreadyInstalled,
+    IntegrationRemoved,
+    IntegrationNotInstalled,
+    RestartShell,
+    DoctorIntegrationInstalled,
+    DoctorIntegrationMissing,
+    DoctorTargetWritable,
+    DoctorTargetNotWritable,
+    InvalidLabel,
+    LabelInUse,
+    Labelled,
+    UnknownLabel,
+    ConflictingLabels,
+    PathAlreadyBookmarked,
+    InvalidLimit,
+    LetterIsHistory,
+    ConfirmRemoveSelected,
+    CleanDryRun,
+    CleanDone,
+    InvalidTag,
+    Tagged,
+    NoFilterMatches,
+    Annotated,
+    NoteCleared,
+    InvalidExport,
+    ConfirmImport,
+    NothingImported,
+    ImportedBookmarks,
+    ImportNoRoom,
+    JustNow,
+    MinuteAgo,
+    MinutesAgo,
+    HourAgo,
+    HoursAgo,
+    DayAgo,
+    DaysAgo,
+    AgeUnknown,
+    CorruptStore,
+    PruneDryRun,
+    PruneDone,
+    SkippedUncheckable,
+    EntriesHidden,
+    PageOf,
+    NextPage,
+    PreviousPage,
+    MissingSuffix,
+    SelectedMissing,
+    MoreMatches,
+    NoSearchMatches,
+    HistoryDisabled,
+    HistoryCleared,
+    NoHistoryToClear,
+    ConfirmForgetAll,
+    NoVisits,
+    StatsHeader,
+    StatsOneVisit,
+    StatsVisits,
+    NothingForward,
+    NotUnderBase,
+    RelativeNotFound,
+    StepsOutOfRange,
+    SkippedMissing,
+    StatsBookmarks,
+    StatsHistory,
+    StatsShadowed,
+    StatsMissing,
+    StatsDeepest,
+    StatsShallowest,
+    FuzzyMatched,
+    NoGlobMatches,
+    StoppedAtRoot,
+    NoSuchSubdirectory,
+    NoSuchAncestor,
+    InvalidLevels,
+    MenuHelp,
+    NotInProject,
+    TargetNotDirectory,
+    CannotWriteFile,
+    Exported,
+    NotADirectory,
+    CannotCreateDirectory,
+    CreatedDirectory,
+    ConfigProblem,
+    FzfUnavailable,
+    FilterPrompt,
+    EntryGone,
+    HistoryUntimed,
+    UntimedKept,
+    PruneHistoryDryRun,
+    PruneHistoryDone,
+    OpenedDirectory,
+    OpenFailed,
+    Interrupted,
+    FzfNoTerminal,
+    FzfFailed,
+}
+
+fn english(msg: Msg) -> &'static str {
+    match msg {
+        Msg::NoBookmarks => "No bookmarked directories.",
+        Msg::SelectPrompt => "Select directory ({}): ",
+        Msg::InvalidSelection => "Invalid selection.",
+        Msg::InvalidDirectoryLetter => "Invalid directory letter: {}",
+        Msg::InvalidBookmarkLetter => "Invalid bookmark letter: {}",
+        Msg::InvalidDuration => "Invalid duration: {} (use e.g. 90m, 8h, 3d)",
+        Msg::AlreadyBookmarked => "Current directory is already bookmarked.",
+        Msg::NotBookmarked => "Current directory is not bookmarked.",
+        Msg::MaxBookmarksReached => "Error: Maximum of {} bookmarks reached. Remove a bookmark first.",
+        Msg::Bookmarked => "Bookmarked: {}",
+        Msg::BookmarkedTemp => "Bookmarked: {} (expires in {})",
+        Msg::ExpiresIn => "(expires in {})",
+        Msg::Pinned => "Pinned: {}",
+        Msg::RemovedBookmark => "Removed bookmark: {}",
+        Msg::AllBookmarksRemoved => "All bookmarks removed.",
+        Msg::NoBookmarksToRemove => "No bookmarks to remove.",
+        Msg::NoHistory => "No directory history.",
+        Msg::PreviousMissing => "Previous directory no longer exists: {}",
+        Msg::AlreadyAtRoot => "Already at root directory.",
+        Msg::NoSubdirectories => "No subdirectories found.",
+        Msg::TreeHelp => "↑↓ to move, enter to select, type to filter, esc to cancel",
+        Msg::SelectionError => "Selection error: {}",
+        Msg::DirectoryNotFound => "Directory not found: {}",
+        Msg::CurrentDirError => "Error getting current directory: {}",
+        Msg::InvalidTreeDepth => "Invalid tree depth: {}",
+        Msg::Error => "Error: {}",
+        Msg::TargetDeleteWarning => "Warning: Could not delete target file: {}",
+        Msg::CopiedToClipboard => "Copied to clipboard: {}",
+        Msg::ClipboardFailed => "Could not copy to the clipboard (tried: {})",
+        Msg::NoStaleBookmarks => "All bookmarks were visited in the last {} days.",
+        Msg::LastVisited => "last visited {} ago",
+        Msg::NeverVisited => "never visited",
+        Msg::PinnedTag => "(pinned)",
+        Msg::StaleRemovePrompt => "Remove which bookmarks? (letters separated by spaces, Enter for none): ",
+        Msg::ConfirmRemoveStale => "Remove {} stale bookmarks? [y/N] ",
+        Msg::NothingRemoved => "Nothing removed.",
+        Msg::InvalidDays => "Invalid number of days: {}",
+        Msg::YesAnswer => "yes",
+        Msg::ProfileCounts => "{} bookmarks, {} history entries",
+        Msg::ActiveTag => "(active)",
+        Msg::InvalidProfileName => "Invalid profile name: {} (use letters, digits, - and _)",
+        Msg::ProfileSame => "Source and destination profiles are the same.",
+        Msg::ProfileNotFound => "Profile not found: {}",
+        Msg::ProfileHasData => "Profile {} already has data; not overwriting it.",
+        Msg::ProfileCopied => "Copied profile {} to {}",
+        Msg::UnknownShell => "Unknown shell: {} (expected bash, zsh, fish or powershell)",
+        Msg::CannotReadFile => "Cannot read {}: {}",
+        Msg::ImportedHistory => "Imported {} directories from {} history ({} skipped).",
+        Msg::BoostSet => "Rank adjustment for {} is now {}",
+        Msg::BoostLimit => "Rank adjustment for {} is already at its limit ({})",
+        Msg::BoostReset => "Cleared rank adjustment for {}",
+        Msg::IntegrationInstalled => "Added the changedir block to {}.",
+        Msg::IntegrationAlreadyInstalled => "The changedir block is already in {}.",
+        Msg::IntegrationRemoved => "Removed the changedir block from {}.",
+        Msg::IntegrationNotInstalled => "No changedir block found in {}.",
+        Msg::RestartShell => "Restart your shell (or open a new terminal) for this to take effect.",
+        Msg::DoctorIntegrationInstalled => "{} integration installed in {}",
+        Msg::DoctorIntegrationMissing => "{} integration not found in {} (run `changedir install`)",
+        Msg::DoctorTargetWritable => "Target directory {} is writable",
+        Msg::DoctorTargetNotWritable => "Target directory {} is not writable",
+        Msg::InvalidLabel => "Invalid label: {} (use at least two characters, no spaces, '/', '\\' or '=')",
+        Msg::LabelInUse => "Label {} is already used for {}",
+        Msg::Labelled => "Labelled {} as {}",
+        Msg::UnknownLabel => "No bookmark labelled: {}",
+        Msg::ConflictingLabels => "Two different names given: {} and {}",
+        Msg::PathAlreadyBookmarked => "Already bookmarked: {}",
+        Msg::InvalidLimit => "Ignoring invalid {}={}, using {}",
+        Msg::LetterIsHistory => "[{}] is a history entry, not a bookmark",
+        Msg::ConfirmRemoveSelected => "Remove these {} bookmarks? [y/N] ",
+        Msg::CleanDryRun => "Would remove {} missing bookmarks and {} missing history entries.",
+        Msg::CleanDone => "Removed {} missing bookmarks and {} missing history entries.",
+        Msg::InvalidTag => "Invalid tag: {} (no spaces, ',' or '=')",
+        Msg::Tagged => "Tagged {}: {}",
+        Msg::NoFilterMatches => "No listed directory matches: {}",
+        Msg::Annotated => "Updated the description of {}",
+        Msg::NoteCleared => "Removed the description of {}",
+        Msg::InvalidExport => "{} is not a valid export: {}",
+        Msg::ConfirmImport => "Replace all current bookmarks and history? [y/N] ",
+        Msg::NothingImported => "Nothing imported.",
+        Msg::ImportedBookmarks => "Imported {} bookmarks ({} already bookmarked, skipped).",
+        Msg::ImportNoRoom => "{} bookmarks did not fit (maximum {}): {}",
+        Msg::JustNow => "just now",
+        Msg::MinuteAgo => "1 minute ago",
+        Msg::MinutesAgo => "{} minutes ago",
+        Msg::HourAgo => "1 hour ago",
+        Msg::HoursAgo => "{} hours ago",
+        Msg::DayAgo => "1 day ago",
+        Msg::DaysAgo => "{} days ago",
+        Msg::AgeUnknown => "time unknown",
+        Msg::CorruptStore => "Cannot read {}: {}. Fix or remove the file.",
+        Msg::PruneDryRun => "Would remove {} missing bookmarks.",
+        Msg::PruneDone => "Removed {} missing bookmarks.",
+        Msg::SkippedUncheckable => "Keeping {}: cannot check whether it exists ({})",
+        Msg::EntriesHidden => "{} more entries are not shown (only {} fit the letters 0-9, a-z, A-Z); use -c to page through them.",
+        Msg::PageOf => "Page {} of {}",
+        Msg::NextPage => "{} more",
+        Msg::PreviousPage => "previous page",
+        Msg::MissingSuffix => "(missing)",
+        Msg::SelectedMissing => "{} no longer exists. Remove it with --clean or --prune.",
+        Msg::MoreMatches => "{} more matches are not shown; type part of a path to narrow the list.",
+        Msg::NoSearchMatches => "No directory matching '{}' within {} levels.",
+        Msg::HistoryDisabled => "History is disabled (CHANGEDIR_HISTORY_SIZE=0)",
+        Msg::HistoryCleared => "Directory history cleared.",
+        Msg::NoHistoryToClear => "No history to clear.",
+        Msg::ConfirmForgetAll => "Remove all {} bookmarks? [y/N] ",
+        Msg::NoVisits => "No visits recorded yet.",
+        Msg::StatsHeader => "Top {} of {} directories ({} jumps in total):",
+        Msg::StatsOneVisit => "1 visit ({}%), {}",
+        Msg::StatsVisits => "{} visits ({}%), first {}, last {}",
+        Msg::NothingForward => "Nothing to go forward to.",
+        Msg::NotUnderBase => "{} is not inside {}",
+        Msg::RelativeNotFound => "{} is not found below the current directory or its parents (set CHANGEDIR_BASE to choose the base)",
+        Msg::StepsOutOfRange => "Cannot go back {} steps: only {} available",
+        Msg::SkippedMissing => "Skipping {} (no longer exists)",
+        Msg::StatsBookmarks => "Bookmarks",
+        Msg::StatsHistory => "History entries",
+        Msg::StatsShadowed => "Shadowed by bookmarks",
+        Msg::StatsMissing => "Missing directories",
+        Msg::StatsDeepest => "Deepest bookmark",
+        Msg::StatsShallowest => "Shallowest bookmark",
+        Msg::FuzzyMatched => "Matched '{}' to {}",
+        Msg::NoGlobMatches => "No directories match '{}'",
+        Msg::StoppedAtRoot => "Only {} of {} levels above; stopped at the root.",
+        Msg::NoSuchSubdirectory => "No subdirectory '{}' in {}",
+        Msg::NoSuchAncestor => "No parent directory named '{}'",
+        Msg::InvalidLevels => "Invalid number of levels: {} (must be at least 1)",
+        Msg::MenuHelp => "↑/↓ move, type to filter, Enter selects (a letter alone picks that entry), Esc cancels",
+        Msg::NotInProject => "Not inside a repository (no {} found above the current directory).",
+        Msg::TargetNotDirectory => "Not an existing directory, not changing to it: {}",
+        Msg::CannotWriteFile => "Cannot write {}: {}",
+        Msg::Exported => "Exported {} bookmarks and {} history entries to {}",
+        Msg::NotADirectory => "Exists but is not a directory: {}",
+        Msg::CannotCreateDirectory => "Cannot create {}: {}",
+        Msg::CreatedDirectory => "Created directory: {}",
+        Msg::ConfigProblem => "Warning: config file {}: {}",
+        Msg::FzfUnavailable => "Cannot run fzf ({}); using the built-in chooser.",
+        Msg::FilterPrompt => "Filter: ",
+        Msg::EntryGone => "[{}] was {} in the last listing, but it is no longer bookmarked or in history.",
+        Msg::HistoryUntimed => "No history entry has a visit time, so none can be pruned by age.",
+        Msg::UntimedKept => "Kept {} history entries without a visit time.",
+        Msg::PruneHistoryDryRun => "Would remove {} history entries.",
+        Msg::PruneHistoryDone => "Removed {} old history entries.",
+        Msg::OpenedDirectory => "Opened {}",
+        Msg::OpenFailed => "Could not open {} in a file manager (tried: {})",
+        Msg::Interrupted => "Interrupted.",
+        Msg::FzfNoTerminal => "fzf needs a terminal; using the built-in chooser.",
+        Msg::FzfFailed => "fzf failed ({}).",
+    }
+}
+
+fn german(msg: Msg) -> &'static str {
+    match msg {
+        Msg::NoBookmarks => "Keine Verzeichnisse mit Lesezeichen.",
+        Msg::SelectPrompt => "Verzeichnis wählen ({}): ",
+        Msg::InvalidSelection => "Ungültige Auswahl.",
+        Msg::InvalidDirectoryLetter => "Ungültiger Verzeichnisbuchstabe: {}",
+        Msg::InvalidBookmarkLetter => "Ungültiger Lesezeichenbuchstabe: {}",
+        Msg::InvalidDuration => "Ungültige Dauer: {} (z. B. 90m, 8h, 3d)",
+        Msg::AlreadyBookmarked => "Das aktuelle Verzeichnis hat bereits ein Lesezeichen.",
+        Msg::NotBookmarked => "Das aktuelle Verzeichnis hat kein Lesezeichen.",
+        Msg::MaxBookmarksReached => "Fehler: Maximal {} Lesezeichen erreicht. Entfernen Sie zuerst ein Lesezeichen.",
+        Msg::Bookmarked => "Lesezeichen gesetzt: {}",
+        Msg::BookmarkedTemp => "Lesezeichen gesetzt: {} (läuft ab in {})",
+        Msg::ExpiresIn => "(läuft ab in {})",
+        Msg::Pinned => "Angeheftet: {}",
+        Msg::RemovedBookmark => "Lesezeichen entfernt: {}",
+        Msg::AllBookmarksRemoved => "Alle Lesezeichen entfernt.",
+        Msg::NoBookmarksToRemove => "Keine Lesezeichen zum Entfernen.",
+        Msg::NoHistory => "Kein Verzeichnisverlauf.",
+        Msg::PreviousMissing => "Das vorherige Verzeichnis existiert nicht mehr: {}",
+        Msg::AlreadyAtRoot => "Bereits im Wurzelverzeichnis.",
+        Msg::NoSubdirectories => "Keine Unterverzeichnisse gefunden.",
+        Msg::TreeHelp => "↑↓ bewegen, Enter auswählen, tippen zum Filtern, Esc abbrechen",
+        Msg::SelectionError => "Auswahlfehler: {}",
+        Msg::DirectoryNotFound => "Verzeichnis nicht gefunden: {}",
+        Msg::CurrentDirError => "Fehler beim Ermitteln des aktuellen Verzeichnisses: {}",
+        Msg::InvalidTreeDepth => "Ungültige Baumtiefe: {}",
+        Msg::Error => "Fehler: {}",
+        Msg::TargetDeleteWarning => "Warnung: Zieldatei konnte nicht gelöscht werden: {}",
+        Msg::CopiedToClipboard => "In die Zwischenablage kopiert: {}",
+        Msg::ClipboardFailed => "Kopieren in die Zwischenablage fehlgeschlagen (versucht: {})",
+        Msg::NoStaleBookmarks => "Alle Lesezeichen wurden in den letzten {} Tagen besucht.",
+        Msg::LastVisited => "zuletzt besucht vor {}",
+        Msg::NeverVisited => "nie besucht",
+        Msg::PinnedTag => "(angeheftet)",
+        Msg::StaleRemovePrompt => "Welche Lesezeichen entfernen? (Buchstaben durch Leerzeichen getrennt, Enter für keine): ",
+        Msg::ConfirmRemoveStale => "{} veraltete Lesezeichen entfernen? [j/N] ",
+        Msg::NothingRemoved => "Nichts entfernt.",
+        Msg::InvalidDays => "Ungültige Anzahl von Tagen: {}",
+        Msg::YesAnswer => "j",
+        Msg::ProfileCounts => "{} Lesezeichen, {} Verlaufseinträge",
+        Msg::ActiveTag => "(aktiv)",
+        Msg::InvalidProfileName => "Ungültiger Profilname: {} (Buchstaben, Ziffern, - und _ verwenden)",
+        Msg::ProfileSame => "Quell- und Zielprofil sind identisch.",
+        Msg::ProfileNotFound => "Profil nicht gefunden: {}",
+        Msg::ProfileHasData => "Profil {} enthält bereits Daten und wird nicht überschrieben.",
+        Msg::ProfileCopied => "Profil {} nach {} kopiert",
+        Msg::UnknownShell => "Unbekannte Shell: {} (erwartet: bash, zsh, fish oder powershell)",
+        Msg::CannotReadFile => "{} kann nicht gelesen werden: {}",
+        Msg::ImportedHistory => "{} Verzeichnisse aus dem {}-Verlauf importiert ({} übersprungen).",
+        Msg::BoostSet => "Rangänderung für {} ist jetzt {}",
+        Msg::BoostLimit => "Rangänderung für {} ist bereits am Limit ({})",
+        Msg::BoostReset => "Rangänderung für {} zurückgesetzt",
+        Msg::IntegrationInstalled => "changedir-Block zu {} hinzugefügt.",
+        Msg::IntegrationAlreadyInstalled => "Der changedir-Block ist bereits in {} vorhanden.",
+        Msg::IntegrationRemoved => "changedir-Block aus {} entfernt.",
+        Msg::IntegrationNotInstalled => "Kein changedir-Block in {} gefunden.",
+        Msg::RestartShell => "Starten Sie Ihre Shell neu (oder öffnen Sie ein neues Terminal), damit dies wirksam wird.",
+        Msg::DoctorIntegrationInstalled => "{}-Integration in {} installiert",
+        Msg::DoctorIntegrationMissing => "{}-Integration nicht in {} gefunden (`changedir install` ausführen)",
+        Msg::DoctorTargetWritable => "Zielverzeichnis {} ist beschreibbar",
+        Msg::DoctorTargetNotWritable => "Zielverzeichnis {} ist nicht beschreibbar",
+        Msg::InvalidLabel => "Ungültige Bezeichnung: {} (mindestens zwei Zeichen, keine Leerzeichen, '/', '\\' oder '=')",
+        Msg::LabelInUse => "Die Bezeichnung {} wird bereits für {} verwendet",
+        Msg::Labelled => "{} als {} bezeichnet",
+        Msg::UnknownLabel => "Kein Lesezeichen mit der Bezeichnung: {}",
+        Msg::ConflictingLabels => "Zwei verschiedene Namen angegeben: {} und {}",
+        Msg::PathAlreadyBookmarked => "Hat bereits ein Lesezeichen: {}",
+        Msg::InvalidLimit => "Ungültiges {}={} wird ignoriert, verwende {}",
+        Msg::LetterIsHistory => "[{}] ist ein Verlaufseintrag, kein Lesezeichen",
+        Msg::ConfirmRemoveSelected => "Diese {} Lesezeichen entfernen? [j/N] ",
+        Msg::CleanDryRun => "Würde {} fehlende Lesezeichen und {} fehlende Verlaufseinträge entfernen.",
+        Msg::CleanDone => "{} fehlende Lesezeichen und {} fehlende Verlaufseinträge entfernt.",
+        Msg::InvalidTag => "Ungültiges Schlagwort: {} (keine Leerzeichen, ',' oder '=')",
+        Msg::Tagged => "{} verschlagwortet: {}",
+        Msg::NoFilterMatches => "Kein aufgeführtes Verzeichnis passt zu: {}",
+        Msg::Annotated => "Beschreibung von {} aktualisiert",
+        Msg::NoteCleared => "Beschreibung von {} entfernt",
+        Msg::InvalidExport => "{} ist kein gültiger Export: {}",
+        Msg::ConfirmImport => "Alle aktuellen Lesezeichen und den Verlauf ersetzen? [j/N] ",
+        Msg::NothingImported => "Nichts importiert.",
+        Msg::ImportedBookmarks => "{} Lesezeichen importiert ({} bereits vorhanden, übersprungen).",
+        Msg::ImportNoRoom => "{} Lesezeichen hatten keinen Platz mehr (maximal {}): {}",
+        Msg::JustNow => "gerade eben",
+        Msg::MinuteAgo => "vor 1 Minute",
+        Msg::MinutesAgo => "vor {} Minuten",
+        Msg::HourAgo => "vor 1 Stunde",
+        Msg::HoursAgo => "vor {} Stunden",
+        Msg::DayAgo => "vor 1 Tag",
+        Msg::DaysAgo => "vor {} Tagen",
+        Msg::AgeUnknown => "Zeit unbekannt",
+        Msg::CorruptStore => "{} kann nicht gelesen werden: {}. Datei korrigieren oder entfernen.",
+        Msg::PruneDryRun => "Würde {} fehlende Lesezeichen entfernen.",
+        Msg::PruneDone => "{} fehlende Lesezeichen entfernt.",
+        Msg::SkippedUncheckable => "{} bleibt erhalten: Existenz kann nicht geprüft werden ({})",
+        Msg::EntriesHidden => "{} weitere Einträge werden nicht angezeigt (nur {} passen auf die Buchstaben 0-9, a-z, A-Z); mit -c kann geblättert werden.",
+        Msg::PageOf => "Seite {} von {}",
+        Msg::NextPage => "{} weitere",
+        Msg::PreviousPage => "vorherige Seite",
+        Msg::MissingSuffix => "(fehlt)",
+        Msg::SelectedMissing => "{} existiert nicht mehr. Mit --clean oder --prune entfernen.",
+        Msg::MoreMatches => "{} weitere Treffer werden nicht angezeigt; einen Teil des Pfads eingeben, um die Liste einzugrenzen.",
+        Msg::NoSearchMatches => "Kein Verzeichnis passend zu '{}' innerhalb von {} Ebenen.",
+        Msg::HistoryDisabled => "Der Verlauf ist deaktiviert (CHANGEDIR_HISTORY_SIZE=0)",
+        Msg::HistoryCleared => "Verzeichnisverlauf gelöscht.",
+        Msg::NoHistoryToClear => "Kein Verlauf zum Löschen vorhanden.",
+        Msg::ConfirmForgetAll => "Alle {} Lesezeichen entfernen? [j/N] ",
+        Msg::NoVisits => "Noch keine Besuche aufgezeichnet.",
+        Msg::StatsHeader => "Die {} meistbesuchten von {} Verzeichnissen ({} Wechsel insgesamt):",
+        Msg::StatsOneVisit => "1 Besuch ({} %), {}",
+        Msg::StatsVisits => "{} Besuche ({} %), zuerst {}, zuletzt {}",
+        Msg::NothingForward => "Kein Verzeichnis zum Vorwärtsgehen.",
+        Msg::NotUnderBase => "{} liegt nicht in {}",
+        Msg::RelativeNotFound => "{} wurde weder im aktuellen Verzeichnis noch in dessen Elternverzeichnissen gefunden (CHANGEDIR_BASE legt die Basis fest)",
+        Msg::StepsOutOfRange => "Kann nicht {} Schritte zurückgehen: nur {} vorhanden",
+        Msg::SkippedMissing => "Überspringe {} (existiert nicht mehr)",
+        Msg::StatsBookmarks => "Lesezeichen",
+        Msg::StatsHistory => "Verlaufseinträge",
+        Msg::StatsShadowed => "Von Lesezeichen verdeckt",
+        Msg::StatsMissing => "Fehlende Verzeichnisse",
+        Msg::StatsDeepest => "Tiefstes Lesezeichen",
+        Msg::StatsShallowest => "Flachstes Lesezeichen",
+        Msg::FuzzyMatched => "'{}' zugeordnet zu {}",
+        Msg::NoGlobMatches => "Keine Verzeichnisse passen zu '{}'",
+        Msg::StoppedAtRoot => "Nur {} von {} Ebenen vorhanden; beim Wurzelverzeichnis angehalten.",
+        Msg::NoSuchSubdirectory => "Kein Unterverzeichnis '{}' in {}",
+        Msg::NoSuchAncestor => "Kein übergeordnetes Verzeichnis namens '{}'",
+        Msg::InvalidLevels => "Ungültige Anzahl von Ebenen: {} (mindestens 1)",
+        Msg::MenuHelp => "↑/↓ bewegen, Tippen filtert, Enter wählt (ein Buchstabe allein wählt diesen Eintrag), Esc bricht ab",
+        Msg::NotInProject => "Nicht in einem Repository (kein {} oberhalb des aktuellen Verzeichnisses).",
+        Msg::TargetNotDirectory => "Kein vorhandenes Verzeichnis, es wird nicht gewechselt: {}",
+        Msg::CannotWriteFile => "{} kann nicht geschrieben werden: {}",
+        Msg::Exported => "{} Lesezeichen und {} Verlaufseinträge nach {} exportiert",
+        Msg::NotADirectory => "Existiert, ist aber kein Verzeichnis: {}",
+        Msg::CannotCreateDirectory => "{} kann nicht angelegt werden: {}",
+        Msg::CreatedDirectory => "Verzeichnis angelegt: {}",
+        Msg::ConfigProblem => "Warnung: Konfigurationsdatei {}: {}",
+        Msg::FzfUnavailable => "fzf kann nicht gestartet werden ({}); die eingebaute Auswahl wird verwendet.",
+        Msg::FilterPrompt => "Filter: ",
+        Msg::EntryGone => "[{}] war in der letzten Liste {}, ist aber kein Lesezeichen und nicht im Verlauf mehr.",
+        Msg::HistoryUntimed => "Kein Verlaufseintrag hat eine Besuchszeit, daher kann keiner nach Alter entfernt werden.",
+        Msg::UntimedKept => "{} Verlaufseinträge ohne Besuchszeit wurden behalten.",
+        Msg::PruneHistoryDryRun => "Würde {} Verlaufseinträge entfernen.",
+        Msg::PruneHistoryDone => "{} alte Verlaufseinträge entfernt.",
+        Msg::OpenedDirectory => "Geöffnet: {}",
+        Msg::OpenFailed => "{} konnte nicht im Dateimanager geöffnet werden (versucht: {})",
+        Msg::Interrupted => "Abgebrochen.",
+        Msg::FzfNoTerminal => "fzf braucht ein Terminal; die eingebaute Auswahl wird verwendet.",
+        Msg::FzfFailed => "fzf ist fehlgeschlagen ({}).",
+    }
+}
+
+fn french(msg: Msg) -> &'static str {
+    match msg {
+        Msg::NoBookmarks => "Aucun répertoire en favori.",
+        Msg::SelectPrompt => "Choisir un répertoire ({}) : ",
+        Msg::InvalidSelection => "Sélection invalide.",
+        Msg::InvalidDirectoryLetter => "Lettre de répertoire invalide : {}",
+        Msg::InvalidBookmarkLetter => "Lettre de favori invalide : {}",
+        Msg::InvalidDuration => "Durée invalide : {} (par ex. 90m, 8h, 3d)",
+        Msg::AlreadyBookmarked => "Le répertoire courant est déjà en favori.",
+        Msg::NotBookmarked => "Le répertoire courant n'est pas en favori.",
+        Msg::MaxBookmarksReached => "Erreur : maximum de {} favoris atteint. Supprimez d'abord un favori.",
+        Msg::Bookmarked => "Ajouté aux favoris : {}",
+        Msg::BookmarkedTemp => "Ajouté aux favoris : {} (expire dans {})",
+        Msg::ExpiresIn => "(expire dans {})",
+        Msg::Pinned => "Épinglé : {}",
+        Msg::RemovedBookmark => "Favori supprimé : {}",
+        Msg::AllBookmarksRemoved => "Tous les favoris ont été supprimés.",
+        Msg::NoBookmarksToRemove => "Aucun favori à supprimer.",
+        Msg::NoHistory => "Aucun historique de répertoires.",
+        Msg::PreviousMissing => "Le répertoire précédent n'existe plus : {}",
+        Msg::AlreadyAtRoot => "Déjà à la racine.",
+        Msg::NoSubdirectories => "Aucun sous-répertoire trouvé.",
+        Msg::TreeHelp => "↑↓ pour naviguer, Entrée pour choisir, tapez pour filtrer, Échap pour annuler",
+        Msg::SelectionError => "Erreur de sélection : {}",
+        Msg::DirectoryNotFound => "Répertoire introuvable : {}",
+        Msg::CurrentDirError => "Impossible de déterminer le répertoire courant : {}",
+        Msg::InvalidTreeDepth => "Profondeur d'arborescence invalide : {}",
+        Msg::Error => "Erreur : {}",
+        Msg::TargetDeleteWarning => "Avertissement : impossible de supprimer le fichier cible : {}",
+        Msg::CopiedToClipboard => "Copié dans le presse-papiers : {}",
+        Msg::ClipboardFailed => "Impossible de copier dans le presse-papiers (essayé : {})",
+        Msg::NoStaleBookmarks => "Tous les favoris ont été visités ces {} derniers jours.",
+        Msg::LastVisited => "dernière visite il y a {}",
+        Msg::NeverVisited => "jamais visité",
+        Msg::PinnedTag => "(épinglé)",
+        Msg::StaleRemovePrompt => "Supprimer quels favoris ? (lettres séparées par des espaces, Entrée pour aucun) : ",
+        Msg::ConfirmRemoveStale => "Supprimer {} favoris inutilisés ? [o/N] ",
+        Msg::NothingRemoved => "Rien n'a été supprimé.",
+        Msg::InvalidDays => "Nombre de jours invalide : {}",
+        Msg::YesAnswer => "o",
+        Msg::ProfileCounts => "{} favoris, {} entrées d'historique",
+        Msg::ActiveTag => "(actif)",
+        Msg::InvalidProfileName => "Nom de profil invalide : {} (utilisez lettres, chiffres, - et _)",
+        Msg::ProfileSame => "Les profils source et destination sont identiques.",
+        Msg::ProfileNotFound => "Profil introuvable : {}",
+        Msg::ProfileHasData => "Le profil {} contient déjà des données ; il n'est pas écrasé.",
+        Msg::ProfileCopied => "Profil {} copié vers {}",
+        Msg::UnknownShell => "Shell inconnu : {} (attendu : bash, zsh, fish ou powershell)",
+        Msg::CannotReadFile => "Impossible de lire {} : {}",
+        Msg::ImportedHistory => "{} répertoires importés de l'historique {} ({} ignorés).",
+        Msg::BoostSet => "L'ajustement de rang de {} est maintenant {}",
+        Msg::BoostLimit => "L'ajustement de rang de {} est déjà à sa limite ({})",
+        Msg::BoostReset => "Ajustement de rang de {} supprimé",
+        Msg::IntegrationInstalled => "Bloc changedir ajouté à {}.",
+        Msg::IntegrationAlreadyInstalled => "Le bloc changedir est déjà présent dans {}.",
+        Msg::IntegrationRemoved => "Bloc changedir retiré de {}.",
+        Msg::IntegrationNotInstalled => "Aucun bloc changedir trouvé dans {}.",
+        Msg::RestartShell => "Redémarrez votre shell (ou ouvrez un nouveau terminal) pour que cela prenne effet.",
+        Msg::DoctorIntegrationInstalled => "Intégration {} installée dans {}",
+        Msg::DoctorIntegrationMissing => "Intégration {} introuvable dans {} (lancez `changedir install`)",
+        Msg::DoctorTargetWritable => "Le répertoire cible {} est accessible en écriture",
+        Msg::DoctorTargetNotWritable => "Le répertoire cible {} n'est pas accessible en écriture",
+        Msg::InvalidLabel => "Libellé invalide : {} (au moins deux caractères, sans espaces, '/', '\\' ni '=')",
+        Msg::LabelInUse => "Le libellé {} est déjà utilisé pour {}",
+        Msg::Labelled => "{} nommé {}",
+        Msg::UnknownLabel => "Aucun favori nommé : {}",
+        Msg::ConflictingLabels => "Deux noms différents indiqués : {} et {}",
+        Msg::PathAlreadyBookmarked => "Déjà en favori : {}",
+        Msg::InvalidLimit => "{}={} invalide ignoré, utilisation de {}",
+        Msg::LetterIsHistory => "[{}] est une entrée d'historique, pas un favori",
+        Msg::ConfirmRemoveSelected => "Supprimer ces {} favoris ? [o/N] ",
+        Msg::CleanDryRun => "Supprimerait {} favoris et {} entrées d'historique introuvables.",
+        Msg::CleanDone => "{} favoris et {} entrées d'historique introuvables supprimés.",
+        Msg::InvalidTag => "Étiquette invalide : {} (sans espaces, ',' ni '=')",
+        Msg::Tagged => "Étiquettes ajoutées à {} : {}",
+        Msg::NoFilterMatches => "Aucun répertoire listé ne correspond à : {}",
+        Msg::Annotated => "Description de {} mise à jour",
+        Msg::NoteCleared => "Description de {} supprimée",
+        Msg::InvalidExport => "{} n'est pas un export valide : {}",
+        Msg::ConfirmImport => "Remplacer tous les favoris et l'historique actuels ? [o/N] ",
+        Msg::NothingImported => "Rien n'a été importé.",
+        Msg::ImportedBookmarks => "{} favoris importés ({} déjà présents, ignorés).",
+        Msg::ImportNoRoom => "{} favoris n'ont pas pu être ajoutés (maximum {}) : {}",
+        Msg::JustNow => "à l'instant",
+        Msg::MinuteAgo => "il y a 1 minute",
+        Msg::MinutesAgo => "il y a {} minutes",
+        Msg::HourAgo => "il y a 1 heure",
+        Msg::HoursAgo => "il y a {} heures",
+        Msg::DayAgo => "il y a 1 jour",
+        Msg::DaysAgo => "il y a {} jours",
+        Msg::AgeUnknown => "date inconnue",
+        Msg::CorruptStore => "Impossible de lire {} : {}. Corrigez ou supprimez le fichier.",
+        Msg::PruneDryRun => "Supprimerait {} favoris introuvables.",
+        Msg::PruneDone => "{} favoris introuvables supprimés.",
+        Msg::SkippedUncheckable => "{} est conservé : impossible de vérifier son existence ({})",
+        Msg::EntriesHidden => "{} autres entrées ne sont pas affichées (seules {} tiennent dans les lettres 0-9, a-z, A-Z) ; utilisez -c pour les parcourir.",
+        Msg::PageOf => "Page {} sur {}",
+        Msg::NextPage => "{} de plus",
+        Msg::PreviousPage => "page précédente",
+        Msg::MissingSuffix => "(introuvable)",
+        Msg::SelectedMissing => "{} n'existe plus. Supprimez-le avec --clean ou --prune.",
+        Msg::MoreMatches => "{} autres résultats ne sont pas affichés ; tapez une partie du chemin pour affiner la liste.",
+        Msg::NoSearchMatches => "Aucun répertoire correspondant à « {} » sur {} niveaux.",
+        Msg::HistoryDisabled => "L'historique est désactivé (CHANGEDIR_HISTORY_SIZE=0)",
+        Msg::HistoryCleared => "Historique des répertoires effacé.",
+        Msg::NoHistoryToClear => "Aucun historique à effacer.",
+        Msg::ConfirmForgetAll => "Supprimer les {} favoris ? [o/N] ",
+        Msg::NoVisits => "Aucune visite enregistrée pour l'instant.",
+        Msg::StatsHeader => "Les {} répertoires les plus visités sur {} ({} sauts au total) :",
+        Msg::StatsOneVisit => "1 visite ({} %), {}",
+        Msg::StatsVisits => "{} visites ({} %), première {}, dernière {}",
+        Msg::NothingForward => "Aucun répertoire vers lequel avancer.",
+        Msg::NotUnderBase => "{} n'est pas dans {}",
+        Msg::RelativeNotFound => "{} est introuvable sous le répertoire courant ou ses parents (définissez CHANGEDIR_BASE pour choisir la base)",
+        Msg::StepsOutOfRange => "Impossible de reculer de {} pas : seulement {} disponibles",
+        Msg::SkippedMissing => "{} ignoré (n'existe plus)",
+        Msg::StatsBookmarks => "Favoris",
+        Msg::StatsHistory => "Entrées d'historique",
+        Msg::StatsShadowed => "Masquées par des favoris",
+        Msg::StatsMissing => "Répertoires introuvables",
+        Msg::StatsDeepest => "Favori le plus profond",
+        Msg::StatsShallowest => "Favori le moins profond",
+        Msg::FuzzyMatched => "« {} » correspond à {}",
+        Msg::NoGlobMatches => "Aucun répertoire ne correspond à '{}'",
+        Msg::StoppedAtRoot => "Seulement {} niveaux sur {} au-dessus ; arrêt à la racine.",
+        Msg::NoSuchSubdirectory => "Aucun sous-répertoire '{}' dans {}",
+        Msg::NoSuchAncestor => "Aucun répertoire parent nommé '{}'",
+        Msg::InvalidLevels => "Nombre de niveaux invalide : {} (au moins 1)",
+        Msg::MenuHelp => "↑/↓ pour se déplacer, tapez pour filtrer, Entrée pour choisir (une lettre seule choisit cette entrée), Échap annule",
+        Msg::NotInProject => "Pas dans un dépôt (aucun {} au-dessus du répertoire courant).",
+        Msg::TargetNotDirectory => "Pas un répertoire existant, pas de changement : {}",
+        Msg::CannotWriteFile => "Impossible d'écrire {} : {}",
+        Msg::Exported => "{} favoris et {} entrées d'historique exportés vers {}",
+        Msg::NotADirectory => "Existe mais n'est pas un répertoire : {}",
+        Msg::CannotCreateDirectory => "Impossible de créer {} : {}",
+        Msg::CreatedDirectory => "Répertoire créé : {}",
+        Msg::ConfigProblem => "Avertissement : fichier de configuration {} : {}",
+        Msg::FzfUnavailable => "Impossible de lancer fzf ({}) ; utilisation du sélecteur intégré.",
+        Msg::FilterPrompt => "Filtre : ",
+        Msg::EntryGone => "[{}] était {} dans la dernière liste, mais n'est plus un signet ni dans l'historique.",
+        Msg::HistoryUntimed => "Aucune entrée de l'historique n'a d'heure de visite ; aucune ne peut être supprimée selon l'âge.",
+        Msg::UntimedKept => "{} entrées de l'historique sans heure de visite ont été conservées.",
+        Msg::PruneHistoryDryRun => "Supprimerait {} entrées de l'historique.",
+        Msg::PruneHistoryDone => "{} anciennes entrées de l'historique supprimées.",
+        Msg::OpenedDirectory => "Ouvert : {}",
+        Msg::OpenFailed => "Impossible d'ouvrir {} dans le gestionnaire de fichiers (essayé : {})",
+        Msg::Interrupted => "Interrompu.",
+        Msg::FzfNoTerminal => "fzf a besoin d'un terminal ; utilisation du sélecteur intégré.",
+        Msg::FzfFailed => "fzf a échoué ({}).",
+    }
+}
+
+pub fn template(language: Language, msg: Msg) -> &'static str {
+    match language {
+        Language::En => english(msg),
+        Language::De => german(msg),
+        Language::Fr => french(msg),
+    }
+}
+
+// Look up a message in the active language and fill its placeholders
+pub fn tr(msg: Msg, args: &[&dyn Display]) -> String {
+    let mut pieces = template(language(), msg).split("{}");
+    let mut text = pieces.next().unwrap_or("").to_string();
+    let mut args = args.iter();
+    for piece in pieces {
+        if let Some(arg) = args.next() {
+            text.push_str(&arg.to_string());
+        }
+        text.push_str(piece);
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The "Name" => "template" arms of one catalog function, read from this
+    // file since Msg can't be enumerated
+    fn catalog(function: &str) -> Vec<(&'static str, &'static str)> {
+        let source = include_str!("messages.rs");
+        let start = source.find(&format!("\nfn {}(msg: Msg)", function)).unwrap();
+        let body = &source[start..];
+        let body = &body[..body.find("\n}\n").unwrap()];
+        body.lines()
+            .filter_map(|line| line.trim().strip_prefix("Msg::"))
+            .filter_map(|arm| arm.split_once(" => "))
+            .collect()
+    }
+
+    fn variants() -> Vec<&'static str> {
+        let source = include_str!("messages.rs");
+        let start = source.find("pub enum Msg {").unwrap();
+        let body = &source[start..];
+        body[..body.find('}').unwrap()]
+            .lines()
+            .skip(1)
+            .map(|line| line.trim().trim_end_matches(','))
+            .filter(|name| !name.is_empty() && !name.starts_with("//"))
+            .collect()
+    }
+
+    #[test]
+    fn every_catalog_has_every_message_with_the_same_placeholders() {
+        let english = catalog("english");
+        assert_eq!(english.len(), variants().len());
+        for function in ["german", "french"] {
+            let translated = catalog(function);
+            assert_eq!(translated.len(), english.len(), "{} has a different number of messages", function);
+            for (name, template) in &english {
+                let (_, translation) = translated
+                    .iter()
+                    .find(|(other, _)| other == name)
+                    .unwrap_or_else(|| panic!("{} has no {}", function, name));
+                assert_eq!(
+                    translation.matches("{}").count(),
+                    template.matches("{}").count(),
+                    "{} translation of {} has a different number of placeholders",
+                    function,
+                    name
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn templates_are_single_string_literals() {
+        // Otherwise the catalogs above would be read incompletely
+        for function in ["english", "german", "french"] {
+            for (name, template) in catalog(function) {
+                assert!(
+                    template.starts_with('"') && template.ends_with("\","),
+                    "{} in {} is not a one-line string literal",
+                    name,
+                    function
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn tr_fills_placeholders_in_order() {
+        let text = tr(Msg::CopiedToClipboard, &[&"/tmp/x"]);
+        assert!(text.contains("/tmp/x"));
+        assert!(!text.contains("{}"));
+    }
+
+    #[test]
+    fn language_codes() {
+        assert_eq!(Language::from_code("de_DE.UTF-8"), Some(Language::De));
+        assert_eq!(Language::from_code("fr-CA"), Some(Language::Fr));
+        assert_eq!(Language::from_code("en"), Some(Language::En));
+        assert_eq!(Language::from_code("C"), None);
+    }
+
+    #[test]
+    fn configured_language_wins_over_the_locale() {
+        assert_eq!(choose_language(Some(Language::En), Some(Language::De)), Language::En);
+        assert_eq!(choose_language(None, Some(Language::De)), Language::De);
+        assert_eq!(choose_language(Some(Language::Fr), None), Language::Fr);
+        assert_eq!(choose_language(None, None), Language::En);
+    }
+}
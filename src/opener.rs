@@ -0,0 +1,51 @@
+// Opening a directory in the desktop's file manager, through the platform's
+// opener command.
+
+use std::io;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use crate::debug_print;
+
+// Tried in order; the first one that starts (and, where it reports one,
+// succeeds) wins
+#[cfg(target_os = "macos")]
+const OPENERS: &[(&str, &[&str])] = &[("open", &[])];
+
+#[cfg(windows)]
+const OPENERS: &[(&str, &[&str])] = &[("explorer", &[])];
+
+#[cfg(not(any(target_os = "macos", windows)))]
+const OPENERS: &[(&str, &[&str])] = &[("xdg-open", &[]), ("gio", &["open"])];
+
+// Open `path`, returning the name of the opener that worked, or the names of
+// every opener tried if none did
+pub fn open(path: &Path, verbose: bool) -> Result<&'static str, Vec<&'static str>> {
+    let mut tried = Vec::new();
+
+    for (tool, args) in OPENERS {
+        tried.push(*tool);
+        match run_opener(tool, args, path) {
+            Ok(true) => {
+                debug_print(verbose, &format!("Opened using {}", tool));
+                return Ok(tool);
+            }
+            Ok(false) => debug_print(verbose, &format!("{} exited with an error", tool)),
+            Err(e) => debug_print(verbose, &format!("{} not usable: {}", tool, e)),
+        }
+    }
+
+    Err(tried)
+}
+
+fn run_opener(tool: &str, args: &[&str], path: &Path) -> io::Result<bool> {
+    let status = Command::new(tool)
+        .args(args)
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()?;
+    // explorer exits with 1 even when the window opened
+    Ok(status.success() || cfg!(windows))
+}
@@ -0,0 +1,123 @@
+// Extracting `cd` targets from shell history files.
+//
+// This only ever parses the history text; nothing read from it is executed
+// or expanded beyond a leading `~`.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+impl Shell {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.rsplit('/').next().unwrap_or(name) {
+            "bash" => Some(Shell::Bash),
+            "zsh" => Some(Shell::Zsh),
+            "fish" => Some(Shell::Fish),
+            "powershell" | "pwsh" | "powershell.exe" | "pwsh.exe" => Some(Shell::Powershell),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::Powershell => "powershell",
+        }
+    }
+
+    // Default history file, relative to the home directory
+    pub fn default_history_file(self) -> &'static str {
+        match self {
+            Shell::Bash => ".bash_history",
+            Shell::Zsh => ".zsh_history",
+            Shell::Fish => ".local/share/fish/fish_history",
+            // PSReadLine keeps its history under the platform's data directory
+            Shell::Powershell if cfg!(windows) => {
+                "AppData/Roaming/Microsoft/Windows/PowerShell/PSReadLine/ConsoleHost_history.txt"
+            }
+            Shell::Powershell => ".local/share/powershell/PSReadLine/ConsoleHost_history.txt",
+        }
+    }
+}
+
+// The commands recorded in a history file, oldest first
+fn commands(shell: Shell, content: &str) -> Vec<String> {
+    match shell {
+        // With HISTTIMEFORMAT set, bash writes "#<timestamp>" lines between commands
+        Shell::Bash => content
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(str::to_string)
+            .collect(),
+        // Extended history lines look like ": 1712000000:0;cd foo"
+        Shell::Zsh => content
+            .lines()
+            .map(|line| match line.strip_prefix(": ") {
+                Some(rest) => rest.split_once(';').map(|(_, cmd)| cmd).unwrap_or(rest),
+                None => line,
+            })
+            .map(str::to_string)
+            .collect(),
+        // Fish uses a YAML-like format with "- cmd: ..." entries
+        Shell::Fish => content
+            .lines()
+            .filter_map(|line| line.strip_prefix("- cmd: "))
+            .map(|cmd| cmd.replace("\\n", "\n").replace("\\\\", "\\"))
+            .collect(),
+        // PSReadLine writes one command per line
+        Shell::Powershell => content.lines().map(str::to_string).collect(),
+    }
+}
+
+// The argument of a `cd`/`pushd` command, unquoted. Arguments that would
+// need the shell to interpret them (variables, substitutions, globs, `-`)
+// are skipped.
+fn cd_argument(command: &str) -> Option<String> {
+    let mut words = command.split_whitespace();
+    let program = words.next()?;
+    if !["cd", "pushd", "Set-Location", "Push-Location"].contains(&program) {
+        return None;
+    }
+    let rest: Vec<&str> = words.collect();
+    let arg = rest.join(" ");
+    let arg = arg.trim();
+    if arg.is_empty() || arg.starts_with('-') {
+        return None;
+    }
+
+    let unquoted = if (arg.starts_with('\'') && arg.ends_with('\'') && arg.len() > 1)
+        || (arg.starts_with('"') && arg.ends_with('"') && arg.len() > 1)
+    {
+        arg[1..arg.len() - 1].to_string()
+    } else if rest.len() == 1 {
+        arg.replace("\\ ", " ")
+    } else {
+        // Several unquoted words: more than a plain directory argument
+        return None;
+    };
+
+    if unquoted.is_empty() || unquoted.contains(['$', '`', '*', '?', '[', '(', '{', '\'', '"']) {
+        return None;
+    }
+    Some(unquoted)
+}
+
+// Every `cd` target in the history, oldest first. Chained commands such as
+// `cd foo && make` are split so the `cd` part is still found.
+pub fn cd_targets(shell: Shell, content: &str) -> Vec<String> {
+    commands(shell, content)
+        .iter()
+        .flat_map(|command| {
+            command
+                .split(['\n', ';', '&', '|'])
+                .filter_map(|segment| cd_argument(segment.trim()))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
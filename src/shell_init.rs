@@ -0,0 +1,165 @@
+// Shell integration: the wrapper function printed by `init` and the rc file
+// block written by `install`.
+
+use crate::shell_history::Shell;
+
+// The block written to the rc file is delimited by these lines so it can be
+// found again by `install --remove` and `doctor`
+pub const BLOCK_START: &str = "# >>> changedir shell integration >>>";
+pub const BLOCK_END: &str = "# <<< changedir shell integration <<<";
+
+const POSIX_FUNCTION: &str = r#"cdir() {
+    local target
+    target=$(changedir --print-target-path "$@") || return
+    # CHANGEDIR_WRAPPER tells changedir that this function reports errors itself
+    CHANGEDIR_WRAPPER=2 changedir "$@"
+    local ret=$?
+
+    if [ -f "$target.error" ]; then
+        local record
+        record=$(cat "$target.error")
+        rm -f "$target.error"
+        printf '%s\n' "${record#*$'\t'}" >&2
+        return "${record%%$'\t'*}"
+    fi
+
+    if [ -f "$target" ]; then
+        local dir
+        dir=$(cat "$target")
+        rm -f "$target"
+        [ -d "$dir" ] && cd -- "$dir"
+    fi
+    return $ret
+}
+"#;
+
+const FISH_FUNCTION: &str = r#"function cdir
+    set -l target (changedir --print-target-path $argv); or return
+    # CHANGEDIR_WRAPPER tells changedir that this function reports errors itself
+    CHANGEDIR_WRAPPER=2 changedir $argv
+    set -l cmd_status $status
+
+    if test -f "$target.error"
+        set -l record (string split -m 1 \t < "$target.error")
+        rm -f "$target.error"
+        printf '%s\n' $record[2] >&2
+        return $record[1]
+    end
+
+    if test -f "$target"
+        read -l -z dir < "$target"
+        rm -f "$target"
+        test -d "$dir"; and cd "$dir"
+    end
+    return $cmd_status
+end
+"#;
+
+const POWERSHELL_FUNCTION: &str = r#"function cdir {
+    $target = changedir --print-target-path @args
+    if ($LASTEXITCODE -ne 0) {
+        return
+    }
+    # CHANGEDIR_WRAPPER tells changedir that this function reports errors itself
+    $env:CHANGEDIR_WRAPPER = "2"
+    try {
+        changedir @args
+    } finally {
+        Remove-Item Env:CHANGEDIR_WRAPPER -ErrorAction SilentlyContinue
+    }
+    $cmdStatus = $LASTEXITCODE
+
+    if (Test-Path -LiteralPath "$target.error") {
+        # changedir writes UTF-8, which Windows PowerShell doesn't assume
+        $code, $message = (Get-Content -Raw -Encoding UTF8 -LiteralPath "$target.error").Split("`t", 2)
+        Remove-Item -LiteralPath "$target.error"
+        [Console]::Error.WriteLine($message.TrimEnd())
+        $global:LASTEXITCODE = [int]$code
+        return
+    }
+
+    if (Test-Path -LiteralPath $target) {
+        $dir = (Get-Content -Raw -Encoding UTF8 -LiteralPath $target).TrimEnd("`r", "`n")
+        Remove-Item -LiteralPath $target
+        if (Test-Path -LiteralPath $dir -PathType Container) {
+            Set-Location -LiteralPath $dir
+        }
+    }
+    $global:LASTEXITCODE = $cmdStatus
+}
+"#;
+
+// The wrapper function definition for `shell`
+pub fn init_script(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash | Shell::Zsh => POSIX_FUNCTION,
+        Shell::Fish => FISH_FUNCTION,
+        Shell::Powershell => POWERSHELL_FUNCTION,
+    }
+}
+
+// The shell's startup file, relative to the home directory
+pub fn rc_file(shell: Shell) -> &'static str {
+    match shell {
+        Shell::Bash => ".bashrc",
+        Shell::Zsh => ".zshrc",
+        Shell::Fish => ".config/fish/config.fish",
+        Shell::Powershell if cfg!(windows) => "Documents/PowerShell/Microsoft.PowerShell_profile.ps1",
+        Shell::Powershell => ".config/powershell/Microsoft.PowerShell_profile.ps1",
+    }
+}
+
+// The delimited block that loads the wrapper at shell startup
+pub fn install_block(shell: Shell) -> String {
+    let load = match shell {
+        Shell::Bash | Shell::Zsh => format!("eval \"$(changedir init {})\"", shell.name()),
+        Shell::Fish => "changedir init fish | source".to_string(),
+        Shell::Powershell => "changedir init powershell | Out-String | Invoke-Expression".to_string(),
+    };
+    format!("{}\n{}\n{}\n", BLOCK_START, load, BLOCK_END)
+}
+
+pub fn has_block(content: &str) -> bool {
+    content.lines().any(|line| line.trim_end() == BLOCK_START)
+}
+
+// `content` without the installed block(s), or None if there is no block.
+// An unterminated block is left alone rather than deleting the rest of the file.
+pub fn strip_block(content: &str) -> Option<String> {
+    let mut out = String::with_capacity(content.len());
+    let mut found = false;
+    let mut rest = content;
+    while let Some(start) = find_line(rest, BLOCK_START) {
+        let after_start = &rest[start..];
+        let end = find_line(after_start, BLOCK_END)?;
+        let block_end = start + end + BLOCK_END.len();
+        let block_end = match rest[block_end..].strip_prefix('\n') {
+            Some(_) => block_end + 1,
+            None => block_end,
+        };
+        out.push_str(&rest[..start]);
+        // Also drop the blank line install puts in front of the block
+        if out.ends_with("\n\n") {
+            out.pop();
+        }
+        rest = &rest[block_end..];
+        found = true;
+    }
+    if !found {
+        return None;
+    }
+    out.push_str(rest);
+    Some(out)
+}
+
+// Byte offset of the first line equal to `marker`
+fn find_line(content: &str, marker: &str) -> Option<usize> {
+    let mut offset = 0;
+    for line in content.split_inclusive('\n') {
+        if line.trim_end() == marker {
+            return Some(offset);
+        }
+        offset += line.len();
+    }
+    None
+}
@@ -0,0 +1,23 @@
+mod common;
+
+use std::fs;
+
+use common::{stderr, stdout, TestHome};
+
+#[test]
+fn clean_removes_missing_pinned_bookmarks_too() {
+    let home = TestHome::new("clean-pinned");
+    let gone = home.bookmark("gone");
+    home.bookmark("kept");
+    let output = home.run(&["--pin", "0"], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    fs::remove_dir(&gone).unwrap();
+
+    for args in [&["--prune", "--dry-run"][..], &["--prune"][..]] {
+        let output = home.run(args, "");
+        assert!(output.status.success(), "{}", stderr(&output));
+        assert!(stdout(&output).contains(gone.to_str().unwrap()), "{}", stdout(&output));
+    }
+    let listing = stdout(&home.run(&["-l", "--no-color"], ""));
+    assert!(!listing.contains("gone") && listing.contains("kept"), "{}", listing);
+}
@@ -0,0 +1,94 @@
+// With colour turned off, listings must not contain any ANSI escapes
+
+mod common;
+
+use common::{stdout, TestHome};
+
+const ESCAPE: &str = "\x1b[";
+
+fn home_with_bookmarks(name: &str) -> TestHome {
+    let home = TestHome::new(name);
+    home.bookmark("projects/api");
+    home.bookmark("notes");
+    home
+}
+
+#[test]
+fn forced_colour_is_escaped() {
+    // Shows the checks below can fail
+    let home = home_with_bookmarks("colour-forced");
+    let output = home.command(&["-l"]).env("CLICOLOR_FORCE", "1").output().unwrap();
+    assert!(output.status.success());
+    assert!(stdout(&output).contains(ESCAPE));
+}
+
+#[test]
+fn no_color_flag_wins_over_clicolor_force() {
+    let home = home_with_bookmarks("colour-flag");
+    let output = home.command(&["-l", "--no-color"]).env("CLICOLOR_FORCE", "1").output().unwrap();
+    assert!(output.status.success());
+    let text = stdout(&output);
+    assert!(text.contains("projects/api"));
+    assert!(!text.contains(ESCAPE), "{:?}", text);
+}
+
+#[test]
+fn no_color_variable_wins_over_clicolor_force() {
+    let home = home_with_bookmarks("colour-env");
+    let output = home
+        .command(&["-l"])
+        .env("CLICOLOR_FORCE", "1")
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!stdout(&output).contains(ESCAPE));
+}
+
+#[test]
+fn no_color_with_any_value_turns_colour_off() {
+    let home = home_with_bookmarks("colour-env-zero");
+    let output = home
+        .command(&["-l"])
+        .env("CLICOLOR_FORCE", "1")
+        .env("NO_COLOR", "0")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert!(!stdout(&output).contains(ESCAPE));
+}
+
+// Colour is only on by default when stdout is a terminal, so the config key is
+// checked through `script`, which provides one
+#[cfg(target_os = "linux")]
+#[test]
+fn color_false_in_config_turns_colour_off_on_a_terminal() {
+    let home = home_with_bookmarks("colour-config");
+    let in_terminal = |home: &TestHome| {
+        // The same environment as the binary would get, set on script
+        let settings = home.command(&[]);
+        let mut command = std::process::Command::new("script");
+        for (name, value) in settings.get_envs() {
+            match value {
+                Some(value) => command.env(name, value),
+                None => command.env_remove(name),
+            };
+        }
+        let output = command
+            .args(["-qec", &format!("'{}' -l", env!("CARGO_BIN_EXE_changedir")), "/dev/null"])
+            .current_dir(&home.root)
+            .output();
+        output.ok().filter(|o| o.status.success()).map(|o| stdout(&o))
+    };
+
+    let Some(colored) = in_terminal(&home) else {
+        eprintln!("script(1) is not usable here; skipping");
+        return;
+    };
+    assert!(colored.contains(ESCAPE), "{:?}", colored);
+
+    home.write_config("color = false\n");
+    let plain = in_terminal(&home).unwrap();
+    assert!(plain.contains("projects/api"));
+    assert!(!plain.contains(ESCAPE), "{:?}", plain);
+}
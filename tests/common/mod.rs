@@ -0,0 +1,120 @@
+// Shared setup for the integration tests: each test runs the binary against
+// its own home directory, so nothing touches the real bookmarks and tests
+// can run in parallel.
+
+#![allow(dead_code)]
+
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Output, Stdio};
+
+pub struct TestHome {
+    pub root: PathBuf,
+}
+
+impl TestHome {
+    pub fn new(name: &str) -> TestHome {
+        let root = std::env::temp_dir().join(format!("changedir-test-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        // Canonical, since stored paths are
+        TestHome { root: fs::canonicalize(&root).unwrap() }
+    }
+
+    pub fn config_dir(&self) -> PathBuf {
+        self.root.join(".config")
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        self.root.join(".local/share")
+    }
+
+    pub fn target_file(&self) -> PathBuf {
+        self.data_dir().join("changedir/target")
+    }
+
+    pub fn error_file(&self) -> PathBuf {
+        self.data_dir().join("changedir/target.error")
+    }
+
+    // Create a directory (and its parents) below the home directory
+    pub fn mkdir(&self, relative: &str) -> PathBuf {
+        let path = self.root.join(relative);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    pub fn write_config(&self, text: &str) {
+        fs::create_dir_all(self.config_dir().join("changedir")).unwrap();
+        fs::write(self.config_dir().join("changedir/config.toml"), text).unwrap();
+    }
+
+    // The binary with this home and the settings that would make output
+    // depend on the machine running the tests cleared
+    pub fn command(&self, args: &[&str]) -> Command {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_changedir"));
+        command
+            .args(args)
+            .current_dir(&self.root)
+            .env("HOME", &self.root)
+            .env("XDG_CONFIG_HOME", self.config_dir())
+            .env("XDG_DATA_HOME", self.data_dir())
+            .env("LC_ALL", "C")
+            .env_remove("NO_COLOR")
+            .env_remove("CLICOLOR_FORCE")
+            .env_remove("PAGER");
+        for (name, _) in std::env::vars_os() {
+            if name.to_string_lossy().starts_with("CHANGEDIR_") {
+                command.env_remove(name);
+            }
+        }
+        command
+    }
+
+    // Run with `input` on stdin, from `cwd` (relative to the home directory)
+    pub fn run_in(&self, cwd: &str, args: &[&str], input: &str) -> Output {
+        let mut child = self
+            .command(args)
+            .current_dir(self.root.join(cwd))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        child.stdin.take().unwrap().write_all(input.as_bytes()).unwrap();
+        child.wait_with_output().unwrap()
+    }
+
+    pub fn run(&self, args: &[&str], input: &str) -> Output {
+        self.run_in(".", args, input)
+    }
+
+    // Bookmark `relative` (below the home directory), failing the test if
+    // that doesn't work
+    pub fn bookmark(&self, relative: &str) -> PathBuf {
+        let path = self.mkdir(relative);
+        let output = self.run(&["--bookmark", path.to_str().unwrap()], "");
+        assert!(output.status.success(), "bookmarking {}: {}", relative, stderr(&output));
+        path
+    }
+
+    // Where the last command sent the shell wrapper, if anywhere
+    pub fn target(&self) -> Option<PathBuf> {
+        fs::read_to_string(self.target_file()).ok().map(PathBuf::from)
+    }
+}
+
+impl Drop for TestHome {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stdout).to_string()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).to_string()
+}
@@ -0,0 +1,29 @@
+// With no clipboard tool on PATH and no terminal, every mechanism fails, so
+// the error lists which ones were tried
+
+mod common;
+
+use common::{stderr, TestHome};
+
+fn copy_without_tools(home: &TestHome, osc52: Option<&str>) -> String {
+    let empty = home.mkdir("empty-path");
+    let mut command = home.command(&["-c", "0", "--copy"]);
+    command.env("PATH", &empty);
+    if let Some(value) = osc52 {
+        command.env("CHANGEDIR_OSC52", value);
+    }
+    let output = command.output().unwrap();
+    assert_eq!(output.status.code(), Some(1), "{}", stderr(&output));
+    stderr(&output)
+}
+
+#[test]
+fn osc52_comes_from_the_config_file() {
+    let home = TestHome::new("copy-osc52");
+    home.bookmark("work");
+    assert!(!copy_without_tools(&home, None).contains("OSC 52"));
+    home.write_config("osc52 = true\n");
+    assert!(copy_without_tools(&home, None).contains("OSC 52"));
+    // The environment variable wins over the file
+    assert!(!copy_without_tools(&home, Some("0")).contains("OSC 52"));
+}
@@ -0,0 +1,93 @@
+// fzf is replaced by a script with a fixed exit status; how changedir reacts
+// to that status is what is checked
+
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::PermissionsExt;
+use std::process::Stdio;
+
+use common::{stderr, TestHome};
+
+// A home with one bookmark and an `fzf` on PATH that exits with `status`
+fn home_with_fzf(name: &str, status: i32) -> (TestHome, String) {
+    let home = TestHome::new(name);
+    home.bookmark("work");
+    let bin = home.mkdir("bin");
+    let fzf = bin.join("fzf");
+    fs::write(&fzf, format!("#!/bin/sh\ncat >/dev/null\nexit {}\n", status)).unwrap();
+    fs::set_permissions(&fzf, fs::Permissions::from_mode(0o755)).unwrap();
+    let path = format!("{}:{}", bin.display(), std::env::var("PATH").unwrap_or_default());
+    (home, path)
+}
+
+// Exit code and stderr of `-c --fzf` run in a terminal, or None if script(1)
+// can't be used here
+#[cfg(target_os = "linux")]
+fn choose_in_terminal(home: &TestHome, path: &str) -> Option<(Option<i32>, String)> {
+    let settings = home.command(&[]);
+    let mut command = std::process::Command::new("script");
+    for (name, value) in settings.get_envs() {
+        match value {
+            Some(value) => command.env(name, value),
+            None => command.env_remove(name),
+        };
+    }
+    let shell = format!("'{}' -c --fzf", env!("CARGO_BIN_EXE_changedir"));
+    let output = command
+        .env("PATH", path)
+        .args(["-qec", &shell, "/dev/null"])
+        .current_dir(&home.root)
+        .output()
+        .ok()?;
+    Some((output.status.code(), common::stdout(&output)))
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn leaving_fzf_cancels() {
+    for status in [1, 130] {
+        let (home, path) = home_with_fzf(&format!("fzf-cancel-{}", status), status);
+        let Some((code, _)) = choose_in_terminal(&home, &path) else {
+            eprintln!("script(1) is not usable here; skipping");
+            return;
+        };
+        assert_eq!(code, Some(130), "fzf status {}", status);
+        assert_eq!(home.target(), None);
+    }
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn other_fzf_failures_are_reported() {
+    let (home, path) = home_with_fzf("fzf-error", 2);
+    let Some((code, text)) = choose_in_terminal(&home, &path) else {
+        eprintln!("script(1) is not usable here; skipping");
+        return;
+    };
+    assert_eq!(code, Some(1));
+    assert!(text.contains("fzf failed"), "{:?}", text);
+    assert_eq!(home.target(), None);
+}
+
+#[test]
+fn fzf_is_not_used_with_piped_input() {
+    let (home, path) = home_with_fzf("fzf-piped", 2);
+    let dir = home.root.join("work");
+    let mut child = home
+        .command(&["-c", "--fzf"])
+        .env("PATH", &path)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"0\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("fzf needs a terminal"));
+    assert_eq!(home.target(), Some(dir));
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use std::fs;
+
+use common::{stderr, stdout, TestHome};
+
+#[test]
+fn import_skips_directories_already_bookmarked_under_another_spelling() {
+    let home = TestHome::new("import-dedup");
+    let work = home.bookmark("work");
+    #[cfg(unix)]
+    std::os::unix::fs::symlink(&work, home.root.join("link")).unwrap();
+    let mut paths = vec![format!("{}/", work.display()), format!("{}/.", work.display())];
+    if cfg!(unix) {
+        paths.push(home.root.join("link").display().to_string());
+    }
+    let entries: Vec<String> = paths.iter().map(|p| format!("{{\"path\":\"{}\"}}", p)).collect();
+    let export = format!(
+        "{{\"version\":1,\"bookmarks\":[{}],\"history\":[{}]}}",
+        entries.join(","),
+        entries.join(",")
+    );
+    let file = home.root.join("export.json");
+    fs::write(&file, export).unwrap();
+
+    let output = home.run(&["--import", file.to_str().unwrap()], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let store = fs::read_to_string(home.config_dir().join("changedir/store.json")).unwrap();
+    assert_eq!(store.matches("\"path\"").count(), 2, "{}", store);
+    let report = format!("Imported 0 bookmarks ({} already bookmarked, skipped).", paths.len());
+    assert!(stdout(&output).contains(&report), "{}", stdout(&output));
+}
@@ -0,0 +1,17 @@
+mod common;
+
+use common::{stdout, TestHome};
+
+fn empty_listing(home: &TestHome) -> String {
+    let output = home.command(&["-l", "--no-color"]).env("LC_ALL", "de_DE.UTF-8").output().unwrap();
+    assert!(output.status.success());
+    stdout(&output)
+}
+
+#[test]
+fn configured_language_wins_over_the_locale() {
+    let home = TestHome::new("language-config");
+    assert_eq!(empty_listing(&home), "Keine Verzeichnisse mit Lesezeichen.\n");
+    home.write_config("[ui]\nlanguage = \"en\"\n");
+    assert_eq!(empty_listing(&home), "No bookmarked directories.\n");
+}
@@ -0,0 +1,96 @@
+// Snapshots of `-l --tree`, the listing grouped by shared parent directories
+
+mod common;
+
+use common::{stderr, stdout, TestHome};
+
+// The listing with the test's home directory written as $HOME
+fn render(home: &TestHome, args: &[&str]) -> String {
+    let output = home.run(args, "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    stdout(&output).replace(home.root.to_str().unwrap(), "$HOME")
+}
+
+#[test]
+fn groups_bookmarks_under_shared_parents() {
+    let home = TestHome::new("tree-bookmarks");
+    home.bookmark("work/api/server");
+    home.bookmark("work/web");
+    home.bookmark("work/api/client");
+    let docs = home.mkdir("work/api/docs");
+    let labelled = home.run(&["--bookmark", docs.to_str().unwrap(), "--name", "apidocs"], "");
+    assert!(labelled.status.success());
+    home.bookmark("notes/2024");
+    home.bookmark("notes/2025");
+    home.bookmark("music");
+
+    assert_eq!(
+        render(&home, &["-l", "--tree"]),
+        "\
+$HOME/work/api/
+  [0] server
+  [2] client
+  [3] apidocs → docs
+[1] $HOME/work/web
+$HOME/notes/
+  [4] 2024
+  [5] 2025
+[6] $HOME/music
+"
+    );
+}
+
+#[test]
+fn letters_match_the_flat_listing() {
+    let home = TestHome::new("tree-letters");
+    home.bookmark("a/one");
+    home.bookmark("b");
+    home.bookmark("a/two");
+
+    let flat = render(&home, &["-l"]);
+    let tree = render(&home, &["-l", "--tree"]);
+    for (letter, name) in [("[0]", "one"), ("[1]", "b"), ("[2]", "two")] {
+        let line = |listing: &str| listing.lines().find(|line| line.contains(letter)).map(str::to_string);
+        assert!(line(&flat).unwrap().ends_with(name));
+        assert!(line(&tree).unwrap().ends_with(name), "{}", tree);
+    }
+}
+
+#[test]
+fn groups_history_separately_from_bookmarks() {
+    let home = TestHome::new("tree-history");
+    home.bookmark("projects/site");
+    home.mkdir("projects/tool");
+    home.mkdir("projects/lib");
+    for name in ["tool", "lib"] {
+        let visited = home.run_in("projects", &[name], "");
+        assert!(visited.status.success(), "{}", stderr(&visited));
+    }
+
+    assert_eq!(
+        render(&home, &["-l", "--tree"]),
+        "\
+[0] $HOME/projects/site
+
+$HOME/projects/
+  [1] lib
+  [2] tool
+"
+    );
+}
+
+#[test]
+fn single_members_are_not_grouped() {
+    let home = TestHome::new("tree-single");
+    home.bookmark("one/deep/path");
+    // Shares only the root with the other one, which never forms a group
+    assert!(home.run(&["--bookmark", "/"], "").status.success());
+
+    assert_eq!(
+        render(&home, &["-l", "--tree"]),
+        "\
+[0] $HOME/one/deep/path
+[1] /
+"
+    );
+}
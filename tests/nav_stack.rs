@@ -0,0 +1,83 @@
+// -b and --forward within one shell session, which CHANGEDIR_SESSION names
+
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Output;
+
+use common::{stderr, TestHome};
+
+const SESSION: &str = "test-session";
+
+fn run_in_session(home: &TestHome, cwd: &str, args: &[&str]) -> Output {
+    let output = home
+        .command(args)
+        .current_dir(home.root.join(cwd))
+        .env("CHANGEDIR_SESSION", SESSION)
+        .env_remove("OLDPWD")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{:?}: {}", args, stderr(&output));
+    output
+}
+
+// The paths in the store's history, most recent first
+fn history(home: &TestHome) -> Vec<PathBuf> {
+    let store = fs::read_to_string(home.config_dir().join("changedir/store.json")).unwrap();
+    let history = &store[store.find("\"history\"").unwrap()..];
+    history
+        .split("\"path\":\"")
+        .skip(1)
+        .map(|rest| PathBuf::from(&rest[..rest.find('"').unwrap()]))
+        .collect()
+}
+
+#[test]
+fn going_back_along_the_stack_is_recorded_in_history() {
+    let home = TestHome::new("nav-history");
+    let a = home.bookmark("a");
+    let b = home.bookmark("b");
+    run_in_session(&home, ".", &["-c", "0"]);
+    run_in_session(&home, "a", &["-c", "1"]);
+    assert_eq!(history(&home)[0], b);
+
+    run_in_session(&home, "b", &["-b"]);
+    assert_eq!(home.target(), Some(a.clone()));
+    assert_eq!(history(&home)[0], a);
+
+    run_in_session(&home, "a", &["--forward"]);
+    assert_eq!(home.target(), Some(b.clone()));
+    assert_eq!(history(&home)[0], b);
+}
+
+#[test]
+fn gone_stack_entries_are_skipped_with_a_note() {
+    let home = TestHome::new("nav-missing");
+    home.bookmark("a");
+    home.bookmark("b");
+    let gone = home.mkdir("gone");
+    run_in_session(&home, ".", &["-c", "0"]);
+    run_in_session(&home, "gone", &["-c", "1"]);
+    fs::remove_dir(&gone).unwrap();
+
+    let output = run_in_session(&home, "b", &["-b"]);
+    assert!(stderr(&output).contains(&format!("Skipping {} (no longer exists)", gone.display())), "{}", stderr(&output));
+    assert_eq!(home.target(), Some(home.root.clone()));
+}
+
+#[test]
+fn printing_a_step_leaves_the_stacks_alone() {
+    let home = TestHome::new("nav-print");
+    let a = home.bookmark("a");
+    home.bookmark("b");
+    run_in_session(&home, ".", &["-c", "0"]);
+    run_in_session(&home, "a", &["-c", "1"]);
+
+    for _ in 0..2 {
+        let output = run_in_session(&home, "b", &["-b", "--print", "--absolute"]);
+        assert_eq!(common::stdout(&output).trim_end(), a.to_str().unwrap());
+    }
+    run_in_session(&home, "b", &["-b", "2"]);
+    assert_eq!(home.target(), Some(home.root.clone()));
+}
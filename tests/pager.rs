@@ -0,0 +1,95 @@
+// Paging needs a terminal, so these run the binary under script(1) with a
+// small window
+
+#![cfg(target_os = "linux")]
+
+mod common;
+
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use common::{stdout, TestHome};
+
+fn home_with_long_listing(name: &str) -> TestHome {
+    let home = TestHome::new(name);
+    for i in 0..10 {
+        home.bookmark(&format!("dir{}", i));
+    }
+    home
+}
+
+// Output of `-l` in a five-row terminal with PAGER set to `pager`, or None if
+// script(1) can't be used here
+fn listing_with_pager(home: &TestHome, pager: &str) -> Option<String> {
+    listing_output(home, pager).filter(|o| o.status.success()).map(|o| stdout(&o))
+}
+
+fn listing_output(home: &TestHome, pager: &str) -> Option<std::process::Output> {
+    let settings = home.command(&[]);
+    let mut command = std::process::Command::new("script");
+    for (name, value) in settings.get_envs() {
+        match value {
+            Some(value) => command.env(name, value),
+            None => command.env_remove(name),
+        };
+    }
+    let shell = format!("stty rows 5 cols 80 && '{}' -l --no-color", env!("CARGO_BIN_EXE_changedir"));
+    let output = command
+        .env("PAGER", pager)
+        .args(["-qec", &shell, "/dev/null"])
+        .current_dir(&home.root)
+        .output();
+    output.ok()
+}
+
+#[test]
+fn listing_goes_through_the_pager_once() {
+    let home = home_with_long_listing("pager-cat");
+    let Some(text) = listing_with_pager(&home, "cat") else {
+        eprintln!("script(1) is not usable here; skipping");
+        return;
+    };
+    assert_eq!(text.matches("dir3").count(), 1, "{:?}", text);
+}
+
+#[test]
+fn listing_is_not_repeated_when_the_pager_stops_reading() {
+    let home = home_with_long_listing("pager-true");
+    let Some(text) = listing_with_pager(&home, "true") else {
+        eprintln!("script(1) is not usable here; skipping");
+        return;
+    };
+    assert!(!text.contains("dir3"), "{:?}", text);
+}
+
+#[test]
+fn listing_is_printed_when_the_pager_cannot_start() {
+    let home = home_with_long_listing("pager-missing");
+    let Some(text) = listing_with_pager(&home, "/nonexistent/pager") else {
+        eprintln!("script(1) is not usable here; skipping");
+        return;
+    };
+    assert_eq!(text.matches("dir3").count(), 1, "{:?}", text);
+}
+
+#[test]
+fn ctrl_c_in_the_pager_is_left_to_it() {
+    let home = home_with_long_listing("pager-sigint");
+    // Interrupts changedir the way Ctrl-C in the terminal would, then keeps
+    // running for a moment as less does
+    let pager = home.root.join("interrupting-pager");
+    let marker = home.root.join("pager-finished");
+    fs::write(
+        &pager,
+        format!("#!/bin/sh\ncat >/dev/null\nkill -INT $PPID\nsleep 0.3\ntouch '{}'\n", marker.display()),
+    )
+    .unwrap();
+    fs::set_permissions(&pager, fs::Permissions::from_mode(0o755)).unwrap();
+
+    let Some(output) = listing_output(&home, pager.to_str().unwrap()) else {
+        eprintln!("script(1) is not usable here; skipping");
+        return;
+    };
+    assert!(output.status.success(), "{:?}: {}", output.status, stdout(&output));
+    assert!(marker.exists());
+}
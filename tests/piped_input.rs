@@ -0,0 +1,82 @@
+// With stdin not a terminal, the choosers print their menu to stderr and read
+// exactly one selection from stdin
+
+mod common;
+
+use common::{stderr, stdout, TestHome};
+
+fn home_with_bookmarks(name: &str) -> TestHome {
+    let home = TestHome::new(name);
+    home.bookmark("first");
+    home.bookmark("second");
+    home
+}
+
+#[test]
+fn choose_reads_a_letter_from_stdin() {
+    let home = home_with_bookmarks("piped-choose");
+    let output = home.run(&["-c"], "1\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(home.target(), Some(home.root.join("second")));
+    // The menu stays off stdout, so it can be captured cleanly
+    assert_eq!(stdout(&output), "");
+    assert!(stderr(&output).contains("[1] "));
+}
+
+#[test]
+fn choose_uses_only_the_first_token() {
+    let home = home_with_bookmarks("piped-token");
+    let output = home.run(&["-c"], "0 1\n1\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(home.target(), Some(home.root.join("first")));
+}
+
+#[test]
+fn choose_cancels_at_end_of_input() {
+    let home = home_with_bookmarks("piped-eof");
+    let output = home.run(&["-c"], "");
+    assert_eq!(output.status.code(), Some(130));
+    assert_eq!(home.target(), None);
+}
+
+#[test]
+fn choose_does_not_retry_an_invalid_selection() {
+    let home = home_with_bookmarks("piped-invalid");
+    // A retry would read the valid letter on the second line
+    let output = home.run(&["-c"], "z\n0\n");
+    assert_eq!(output.status.code(), Some(3));
+    assert_eq!(home.target(), None);
+}
+
+#[test]
+fn subdirectory_prompt_reads_a_letter_from_stdin() {
+    let home = TestHome::new("piped-subdir");
+    home.mkdir("work/alpha");
+    home.mkdir("work/beta");
+    let output = home.run_in("work", &["-d"], "1\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(home.target(), Some(home.root.join("work/beta")));
+    assert_eq!(stdout(&output), "");
+}
+
+#[test]
+fn ambiguous_name_reads_a_letter_from_stdin() {
+    let home = TestHome::new("piped-ambiguous");
+    home.mkdir("work/apple");
+    home.mkdir("work/apricot");
+    let output = home.run_in("work", &["ap"], "1\n");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let menu = stderr(&output);
+    assert!(menu.contains("[0] ") && menu.contains("apple"), "{}", menu);
+    assert_eq!(home.target(), Some(home.root.join("work/apricot")));
+}
+
+#[test]
+fn ambiguous_name_cancels_at_end_of_input() {
+    let home = TestHome::new("piped-ambiguous-eof");
+    home.mkdir("work/apple");
+    home.mkdir("work/apricot");
+    let output = home.run_in("work", &["ap"], "");
+    assert_eq!(output.status.code(), Some(130));
+    assert_eq!(home.target(), None);
+}
@@ -0,0 +1,87 @@
+// `-l --porcelain` is a stable interface for scripts: one line per entry,
+// "<slot>\t<kind>\t<path>", with the path as raw bytes
+
+mod common;
+
+use std::fs;
+
+use common::{stderr, TestHome};
+
+fn porcelain(home: &TestHome) -> Vec<u8> {
+    let output = home.run(&["-l", "--porcelain"], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    output.stdout
+}
+
+// A store with the given bookmark and history paths, written directly
+fn write_store(home: &TestHome, bookmarks: &[String], history: &[String]) {
+    let entries = |paths: &[String]| {
+        paths.iter().map(|p| format!("{{\"path\":\"{}\"}}", p)).collect::<Vec<_>>().join(",")
+    };
+    fs::create_dir_all(home.config_dir().join("changedir")).unwrap();
+    fs::write(
+        home.config_dir().join("changedir/store.json"),
+        format!("{{\"version\":1,\"bookmarks\":[{}],\"history\":[{}]}}", entries(bookmarks), entries(history)),
+    )
+    .unwrap();
+}
+
+#[test]
+fn one_tab_separated_line_per_entry() {
+    let home = TestHome::new("porcelain-layout");
+    let root = home.root.to_str().unwrap().to_string();
+    home.mkdir("with space");
+    write_store(
+        &home,
+        &[format!("{}/with space", root), "/".to_string()],
+        &[format!("{}/gone", root)],
+    );
+
+    let expected = format!(
+        "0\tbookmark\t{root}/with space\n1\tbookmark\t/\n2\thistory\t{root}/gone\n",
+        root = root
+    );
+    assert_eq!(String::from_utf8(porcelain(&home)).unwrap(), expected);
+}
+
+#[test]
+fn entries_past_the_last_letter_have_no_slot() {
+    let home = TestHome::new("porcelain-slots");
+    let bookmarks: Vec<String> = (0..30).map(|i| format!("/b{}", i)).collect();
+    let history: Vec<String> = (0..40).map(|i| format!("/h{}", i)).collect();
+    write_store(&home, &bookmarks, &history);
+
+    let output = home.command(&["-l", "--porcelain"]).env("CHANGEDIR_HISTORY_SIZE", "62").output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    let text = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    let slots: Vec<&str> = lines.iter().map(|line| line.split('\t').next().unwrap()).collect();
+    assert_eq!(lines.len(), 70);
+    assert_eq!(slots[0], "0");
+    assert_eq!(slots[10], "a");
+    assert_eq!(slots[36], "A");
+    assert_eq!(slots[61], "Z");
+    assert_eq!(lines[29], "t\tbookmark\t/b29");
+    assert_eq!(lines[30], "u\thistory\t/h0");
+    assert_eq!(lines[62], "-\thistory\t/h32");
+    assert!(slots[62..].iter().all(|&slot| slot == "-"));
+    assert!(lines.iter().all(|line| line.split('\t').count() == 3));
+}
+
+#[cfg(unix)]
+#[test]
+fn paths_are_written_as_raw_bytes() {
+    use std::os::unix::ffi::OsStrExt;
+
+    let home = TestHome::new("porcelain-bytes");
+    let name = std::ffi::OsStr::from_bytes(b"caf\xe9");
+    let dir = home.root.join(name);
+    fs::create_dir(&dir).unwrap();
+    let output = home.command(&["--bookmark"]).current_dir(&dir).output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let mut expected = b"0\tbookmark\t".to_vec();
+    expected.extend_from_slice(dir.as_os_str().as_bytes());
+    expected.push(b'\n');
+    assert_eq!(porcelain(&home), expected);
+}
@@ -0,0 +1,34 @@
+mod common;
+
+use common::{stderr, TestHome};
+
+#[test]
+fn profiles_live_in_the_xdg_data_directory() {
+    let home = TestHome::new("profiles-xdg");
+    let data = home.mkdir("data");
+    let dir = home.mkdir("work");
+    let output = home
+        .command(&["--profile", "work", "--bookmark", dir.to_str().unwrap()])
+        .env("XDG_DATA_HOME", &data)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(data.join("changedir/profiles/work/store.json").is_file());
+    assert!(!home.root.join(".local/share/changedir/profiles").exists());
+}
+
+#[test]
+fn profiles_at_the_old_location_keep_being_used() {
+    let home = TestHome::new("profiles-old");
+    let data = home.mkdir("data");
+    let old = home.mkdir(".local/share/changedir/profiles/work");
+    let dir = home.mkdir("work");
+    let output = home
+        .command(&["--profile", "work", "--bookmark", dir.to_str().unwrap()])
+        .env("XDG_DATA_HOME", &data)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(old.join("store.json").is_file());
+    assert!(!data.join("changedir/profiles").exists());
+}
@@ -0,0 +1,53 @@
+// Interrupting a prompt exits with the cancelled status, tells the wrapper,
+// and leaves the state files alone
+#![cfg(unix)]
+
+mod common;
+
+use std::fs;
+use std::io::Read;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+use common::TestHome;
+
+#[test]
+fn sigint_at_the_prompt_cancels() {
+    let home = TestHome::new("sigint");
+    home.bookmark("projects/api");
+    home.bookmark("notes");
+    let store = home.config_dir().join("changedir/store.json");
+    let before = fs::read_to_string(&store).unwrap();
+
+    let mut child = home
+        .command(&["-c"])
+        .env("CHANGEDIR_WRAPPER", "2")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Keep stdin open so the prompt waits for a selection
+    let _stdin = child.stdin.take().unwrap();
+
+    // Wait for the prompt before interrupting
+    let mut stderr = child.stderr.take().unwrap();
+    let mut shown = Vec::new();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while !String::from_utf8_lossy(&shown).contains("Select") {
+        let mut buffer = [0; 256];
+        let read = stderr.read(&mut buffer).unwrap();
+        assert!(read > 0 && Instant::now() < deadline, "no prompt: {:?}", String::from_utf8_lossy(&shown));
+        shown.extend_from_slice(&buffer[..read]);
+    }
+
+    let killed = Command::new("kill").args(["-INT", &child.id().to_string()]).status().unwrap();
+    assert!(killed.success());
+    let status = child.wait().unwrap();
+
+    assert_eq!(status.code(), Some(130));
+    let record = fs::read_to_string(home.error_file()).unwrap();
+    assert_eq!(record, "130\tInterrupted.\n");
+    assert!(home.target().is_none());
+    assert_eq!(fs::read_to_string(&store).unwrap(), before);
+}
@@ -0,0 +1,31 @@
+// Letters taken from a sorted listing mean the same bookmarks to the commands
+// that change bookmarks by letter, given the same --sort
+
+mod common;
+
+use common::{stderr, stdout, TestHome};
+
+#[test]
+fn remove_by_letter_follows_the_sort_order() {
+    let home = TestHome::new("sorted-remove");
+    home.bookmark("zebra");
+    let apple = home.bookmark("apple");
+    let listing = stdout(&home.run(&["-l", "--sort", "name", "--no-color"], ""));
+    assert!(listing.lines().next().unwrap().contains("apple"), "{}", listing);
+
+    let output = home.run(&["--remove", "0", "--sort", "name"], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains(apple.to_str().unwrap()));
+    let listing = stdout(&home.run(&["-l", "--no-color"], ""));
+    assert!(listing.contains("zebra") && !listing.contains("apple"), "{}", listing);
+}
+
+#[test]
+fn without_a_sort_letters_use_the_stored_order() {
+    let home = TestHome::new("stored-remove");
+    let zebra = home.bookmark("zebra");
+    home.bookmark("apple");
+    let output = home.run(&["--remove", "0"], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains(zebra.to_str().unwrap()));
+}
@@ -0,0 +1,11 @@
+mod common;
+
+use common::{stderr, TestHome};
+
+#[test]
+fn huge_day_counts_do_not_overflow() {
+    let home = TestHome::new("stale-huge");
+    home.bookmark("work");
+    let output = home.run(&["--stale", &u64::MAX.to_string()], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+}
@@ -0,0 +1,23 @@
+// The store holds only what was recorded; letters and entry kinds are worked
+// out when listing
+
+mod common;
+
+use std::fs;
+
+use common::{stderr, stdout, TestHome};
+
+#[test]
+fn store_and_export_hold_only_real_data() {
+    let home = TestHome::new("store-schema");
+    let work = home.bookmark("work");
+    let store = fs::read_to_string(home.config_dir().join("changedir/store.json")).unwrap();
+    assert_eq!(
+        store,
+        format!("{{\n  \"version\": 1,\n  \"bookmarks\": [\n    {{\"path\":\"{}\"}}\n  ],\n  \"history\": []\n}}\n", work.display())
+    );
+
+    let output = home.run(&["--export"], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), store);
+}
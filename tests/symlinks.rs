@@ -0,0 +1,57 @@
+// Bookmarks stored through a symlink (with canonicalize = false) still match
+// the current directory, which the system reports resolved
+
+#![cfg(unix)]
+
+mod common;
+
+use std::os::unix::fs::symlink;
+
+use common::{stderr, stdout, TestHome};
+
+fn home_with_linked_bookmark(name: &str) -> TestHome {
+    let home = TestHome::new(name);
+    home.mkdir("real");
+    symlink(home.root.join("real"), home.root.join("link")).unwrap();
+    home.write_config("canonicalize = false\n");
+    let output = home.run(&["--bookmark", home.root.join("link").to_str().unwrap()], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    home
+}
+
+#[test]
+fn pin_finds_the_current_directory_through_a_symlink() {
+    let home = home_with_linked_bookmark("symlink-pin");
+    let output = home.run_in("link", &["--pin"], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stdout(&output).contains("link"), "{}", stdout(&output));
+}
+
+// -b in a session of its own, so only history and $OLDPWD are looked at
+fn back_from_link(home: &TestHome) -> std::process::Output {
+    home.command(&["-b"])
+        .current_dir(home.root.join("link"))
+        .env("CHANGEDIR_SESSION", "symlink-back")
+        .env("OLDPWD", home.root.join("link"))
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn back_skips_the_current_directory_in_history_through_a_symlink() {
+    let home = home_with_linked_bookmark("symlink-back-history");
+    let output = home.command(&["-c", "0"]).env("CHANGEDIR_SESSION", "symlink-setup").output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+
+    let output = back_from_link(&home);
+    assert_eq!(output.status.code(), Some(2), "{}", stderr(&output));
+    assert_eq!(home.target(), None);
+}
+
+#[test]
+fn back_skips_oldpwd_through_a_symlink() {
+    let home = home_with_linked_bookmark("symlink-back-oldpwd");
+    let output = back_from_link(&home);
+    assert_eq!(output.status.code(), Some(2), "{}", stderr(&output));
+    assert_eq!(home.target(), None);
+}
@@ -0,0 +1,61 @@
+mod common;
+
+use std::fs;
+use std::io::Write;
+use std::process::Stdio;
+
+use common::{stderr, TestHome};
+
+fn choose_first(home: &TestHome, wrapper: &str) {
+    let mut child = home
+        .command(&["-c"])
+        .env("CHANGEDIR_WRAPPER", wrapper)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(b"0\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+}
+
+#[test]
+fn current_wrappers_use_the_xdg_target_file() {
+    let home = TestHome::new("target-current");
+    let dir = home.bookmark("work");
+    choose_first(&home, "2");
+    assert_eq!(home.target(), Some(dir));
+    assert!(!home.root.join(".local/share/changedir.target").exists());
+}
+
+#[test]
+fn wrappers_from_before_the_move_get_the_old_target_file() {
+    let home = TestHome::new("target-old");
+    let dir = home.bookmark("work");
+    choose_first(&home, "1");
+    let old = home.root.join(".local/share/changedir.target");
+    assert_eq!(fs::read_to_string(old).unwrap(), dir.to_str().unwrap());
+    assert_eq!(home.target(), None);
+}
+
+#[test]
+fn relative_target_comes_from_the_config_file() {
+    let home = TestHome::new("target-relative");
+    let web = home.bookmark("work/web");
+    home.mkdir("work/api");
+    home.write_config("relative_target = true\n");
+    let output = home.run_in("work/api", &["-c", "0"], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(home.target(), Some("../web".into()));
+
+    // The environment variable wins over the file
+    let output = home
+        .command(&["-c", "0"])
+        .current_dir(home.root.join("work/api"))
+        .env("CHANGEDIR_RELATIVE_TARGET", "0")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(home.target(), Some(web));
+}
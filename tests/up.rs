@@ -0,0 +1,25 @@
+// `/` is the root here, so these only run on Unix
+
+#![cfg(unix)]
+
+mod common;
+
+use common::{stderr, TestHome};
+
+#[test]
+fn up_from_the_root_stays_there() {
+    let home = TestHome::new("up-root");
+    let output = home.command(&["-u", "3"]).current_dir("/").output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert!(stderr(&output).contains("Already at root directory."));
+    assert_eq!(home.target(), Some("/".into()));
+}
+
+#[test]
+fn up_stops_at_the_root() {
+    let home = TestHome::new("up-clamp");
+    let depth = home.root.components().count() - 1;
+    let output = home.run(&["-u", &(depth + 5).to_string()], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(home.target(), Some("/".into()));
+}
@@ -0,0 +1,37 @@
+// --which prints where a relative bookmark resolves to, not its stored form
+
+mod common;
+
+use common::{stderr, stdout, TestHome};
+
+fn home_with_relative_bookmark(name: &str) -> TestHome {
+    let home = TestHome::new(name);
+    let repo = home.mkdir("repo");
+    home.mkdir("repo/crates/parser");
+    home.mkdir("repo/other");
+    let output = home.run_in(
+        "repo/crates/parser",
+        &["--bookmark", "--relative-to", repo.to_str().unwrap(), "--name", "parser"],
+        "",
+    );
+    assert!(output.status.success(), "{}", stderr(&output));
+    home
+}
+
+#[test]
+fn which_prints_a_relative_bookmark_resolved() {
+    let home = home_with_relative_bookmark("which-label");
+    let output = home.run_in("repo/other", &["--which", "parser"], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let expected = format!("{}\tlabel\n", home.root.join("repo/crates/parser").display());
+    assert_eq!(stdout(&output), expected);
+}
+
+#[test]
+fn fuzzy_matching_resolves_relative_bookmarks() {
+    let home = home_with_relative_bookmark("which-fuzzy");
+    let output = home.run_in("repo/other", &["--which", "prsr"], "");
+    assert!(output.status.success(), "{}", stderr(&output));
+    let expected = format!("{}\tfuzzy\n", home.root.join("repo/crates/parser").display());
+    assert_eq!(stdout(&output), expected);
+}
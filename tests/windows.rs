@@ -0,0 +1,108 @@
+// Windows locations and the PowerShell wrapper
+#![cfg(windows)]
+
+mod common;
+
+use std::path::PathBuf;
+use std::process::Command;
+
+use common::{stderr, stdout, TestHome};
+
+// An existing setup in the Unix-style location keeps being used, which
+// these tests can't rule out on a developer's machine
+fn unix_style_setup_exists() -> bool {
+    let home = dirs::home_dir().unwrap();
+    home.join(".config/changedir").exists() || home.join(".local/share/changedir").exists()
+}
+
+fn print_target_path(home: &TestHome, data_home: Option<&PathBuf>) -> PathBuf {
+    let mut command = home.command(&["--print-target-path"]);
+    command.env_remove("XDG_CONFIG_HOME").env_remove("XDG_DATA_HOME");
+    if let Some(dir) = data_home {
+        command.env("XDG_DATA_HOME", dir);
+    }
+    let output = command.output().unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    PathBuf::from(stdout(&output).trim_end())
+}
+
+#[test]
+fn target_file_defaults_to_local_app_data() {
+    if unix_style_setup_exists() {
+        eprintln!("Unix-style changedir directories exist; skipping");
+        return;
+    }
+    let home = TestHome::new("windows-app-data");
+    let expected = dirs::data_local_dir().unwrap().join("changedir/target");
+    assert_eq!(print_target_path(&home, None), expected);
+}
+
+#[test]
+fn xdg_data_home_still_wins() {
+    let home = TestHome::new("windows-xdg");
+    let data_home = home.mkdir("data");
+    assert_eq!(print_target_path(&home, Some(&data_home)), data_home.join("changedir/target"));
+}
+
+fn powershell() -> Option<&'static str> {
+    ["pwsh", "powershell"].into_iter().find(|shell| {
+        Command::new(shell)
+            .args(["-NoProfile", "-Command", "exit 0"])
+            .output()
+            .is_ok_and(|output| output.status.success())
+    })
+}
+
+// Run `script` in PowerShell with the wrapper loaded, the binary first on
+// PATH and the state files kept in the test's directory
+fn run_wrapped(home: &TestHome, shell: &str, script: &str) -> String {
+    let binary_dir = PathBuf::from(env!("CARGO_BIN_EXE_changedir")).parent().unwrap().to_path_buf();
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let path = std::env::join_paths(std::iter::once(binary_dir).chain(std::env::split_paths(&path))).unwrap();
+    let script = format!(
+        "[Console]::OutputEncoding = [Text.Encoding]::UTF8; changedir init powershell | Out-String | Invoke-Expression; {}",
+        script
+    );
+    let output = home
+        .command(&[])
+        .get_envs()
+        .fold(Command::new(shell), |mut command, (name, value)| {
+            match value {
+                Some(value) => command.env(name, value),
+                None => command.env_remove(name),
+            };
+            command
+        })
+        .env("PATH", path)
+        .env("CHANGEDIR_STORE_FILE", home.root.join("store.json"))
+        .env("CHANGEDIR_TARGET_FILE", home.root.join("target"))
+        .args(["-NoProfile", "-Command", &script])
+        .current_dir(&home.root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn powershell_wrapper_changes_to_a_non_ascii_directory() {
+    let Some(shell) = powershell() else {
+        eprintln!("PowerShell not found; skipping");
+        return;
+    };
+    let home = TestHome::new("windows-powershell");
+    home.mkdir("Müller Straße");
+    let location = run_wrapped(&home, shell, "Set-Location -LiteralPath 'Müller Straße'; cdir --bookmark | Out-Null; Set-Location ..; cdir 0; (Get-Location).Path");
+    assert!(location.trim_end().ends_with("Müller Straße"), "{:?}", location);
+}
+
+#[test]
+fn powershell_wrapper_returns_the_error_code() {
+    let Some(shell) = powershell() else {
+        eprintln!("PowerShell not found; skipping");
+        return;
+    };
+    let home = TestHome::new("windows-powershell-error");
+    let status = run_wrapped(&home, shell, "cdir 9 2>$null; $LASTEXITCODE");
+    assert_eq!(status.trim(), "2");
+}